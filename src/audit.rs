@@ -0,0 +1,177 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::access_code::AccessCode;
+use crate::models::api_token::ApiToken;
+use crate::repo::Repos;
+
+/// Actions tracées dans la table `audit_log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    LoginLink,
+    LoginLinkRejected,
+    CodeCreated,
+    CodeUpdated,
+    CodeArchived,
+    CodeRestored,
+    CodePurged,
+    Draw,
+    DrawOnBehalf,
+    DrawReset,
+    DrawRevealed,
+    DrawSealed,
+    IntegrityRepair,
+    BackupCreated,
+    BackupRestored,
+    DataExported,
+    ProfileUpdated,
+    CodeRotated,
+    InvitationCreated,
+    InvitationRevoked,
+    SignupRequested,
+    SignupApproved,
+    SignupRejected,
+    TotpEnabled,
+    TotpReset,
+    ApiTokenCreated,
+    ApiTokenRevoked,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login.failed",
+            AuditAction::LoginLink => "login.link",
+            AuditAction::LoginLinkRejected => "login.link_rejected",
+            AuditAction::CodeCreated => "code.create",
+            AuditAction::CodeUpdated => "code.update",
+            AuditAction::CodeArchived => "code.archive",
+            AuditAction::CodeRestored => "code.restore",
+            AuditAction::CodePurged => "code.purge",
+            AuditAction::Draw => "draw",
+            AuditAction::DrawOnBehalf => "draw.on_behalf",
+            AuditAction::DrawReset => "draw.reset",
+            AuditAction::DrawRevealed => "draw.reveal",
+            AuditAction::DrawSealed => "draw.seal",
+            AuditAction::IntegrityRepair => "integrity.repair",
+            AuditAction::BackupCreated => "backup.create",
+            AuditAction::BackupRestored => "backup.restore",
+            AuditAction::DataExported => "data.export",
+            AuditAction::ProfileUpdated => "profile.update",
+            AuditAction::CodeRotated => "profile.code_rotate",
+            AuditAction::InvitationCreated => "invitation.create",
+            AuditAction::InvitationRevoked => "invitation.revoke",
+            AuditAction::SignupRequested => "signup.request",
+            AuditAction::SignupApproved => "signup.approve",
+            AuditAction::SignupRejected => "signup.reject",
+            AuditAction::TotpEnabled => "totp.enable",
+            AuditAction::TotpReset => "totp.reset",
+            AuditAction::ApiTokenCreated => "api_token.create",
+            AuditAction::ApiTokenRevoked => "api_token.revoke",
+        }
+    }
+}
+
+/// Évènement d'audit en cours de construction.
+///
+/// ```ignore
+/// AuditEvent::new(AuditAction::CodeArchived)
+///     .actor(&admin.access_code)
+///     .target(id)
+///     .before(&code)
+///     .record(&state.repos)
+///     .await;
+/// ```
+pub struct AuditEvent {
+    pub(crate) action: AuditAction,
+    pub(crate) actor_id: Option<i64>,
+    pub(crate) actor_name: Option<String>,
+    pub(crate) target_id: Option<i64>,
+    pub(crate) before: Option<Value>,
+    pub(crate) after: Option<Value>,
+    pub(crate) detail: Option<String>,
+    api_token_id: Option<i64>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction) -> Self {
+        Self {
+            action,
+            actor_id: None,
+            actor_name: None,
+            target_id: None,
+            before: None,
+            after: None,
+            detail: None,
+            api_token_id: None,
+        }
+    }
+
+    pub fn actor(mut self, actor: &AccessCode) -> Self {
+        self.actor_id = Some(actor.id);
+        self.actor_name = Some(actor.name.clone());
+        self
+    }
+
+    pub fn target(mut self, id: i64) -> Self {
+        self.target_id = Some(id);
+        self
+    }
+
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Action faite par un script avec ce jeton d'API plutôt que depuis la
+    /// session de l'acteur : l'id du jeton est ajouté au détail.
+    pub fn via(mut self, api_token: Option<&ApiToken>) -> Self {
+        self.api_token_id = api_token.map(|token| token.id);
+        self
+    }
+
+    /// Enregistre l'évènement (hors du runtime async). Un échec est journalisé
+    /// sans interrompre la requête.
+    pub async fn record(mut self, repos: &Repos) {
+        if let Some(id) = self.api_token_id {
+            self.detail = Some(match self.detail.take() {
+                Some(detail) => format!("{detail} api_token={id}"),
+                None => format!("api_token={id}"),
+            });
+        }
+        let action = self.action.as_str();
+        let result = repos.run(move |repos| {
+            repos.audit.record(&self);
+            Ok(())
+        }).await;
+        if let Err(e) = result {
+            log::error!("audit action={action} error={e:?}");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: String,
+    pub actor_id: Option<i64>,
+    pub actor_name: Option<String>,
+    pub action: String,
+    pub target_id: Option<i64>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub detail: Option<String>,
+}
@@ -1,14 +1,128 @@
-use rocket::http::Status;
+use std::marker::PhantomData;
+
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
+use crate::metrics::LoginMethod;
+use crate::models::access_code::AccessCode;
+use crate::models::api_token::{hash_token, ApiScope, ApiToken};
+use crate::repo::RepoError;
 use crate::state::AppState;
 
-pub struct AuthenticatedUser;
+/// Cookie privé (chiffré et signé par Rocket) contenant l'id de l'access code connecté.
+pub const SESSION_COOKIE: &str = "session";
+
+/// Ouvre une session pour `access_code` sur le navigateur appelant.
+pub fn start_session(cookies: &CookieJar<'_>, access_code: &AccessCode) {
+    let cookie = Cookie::build((SESSION_COOKIE, access_code.id.to_string()))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .path("/");
+    cookies.add_private(cookie);
+}
+
+pub fn end_session(cookies: &CookieJar<'_>) {
+    cookies.remove_private(Cookie::build(SESSION_COOKIE).path("/"));
+}
+
+/// Cookie privé d'une connexion qui attend le code TOTP : la session n'est
+/// ouverte qu'une fois le second facteur vérifié.
+pub const PENDING_LOGIN_COOKIE: &str = "pending_login";
+/// Délai pour saisir le code TOTP, en secondes.
+pub const PENDING_LOGIN_TTL_SECS: i64 = 300;
+
+/// Connexion en attente du second facteur (premier facteur déjà vérifié,
+/// mais pas encore compté). Les codes erronés sont comptés en base, jamais
+/// dans ce cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingLogin {
+    pub access_code_id: i64,
+    /// Expiration (timestamp Unix, en secondes).
+    pub expires_at: i64,
+    /// Premier facteur utilisé (code d'accès ou lien), compté à l'ouverture
+    /// de la session.
+    pub method: LoginMethod,
+}
+
+impl PendingLogin {
+    pub fn new(access_code_id: i64, method: LoginMethod, now: i64) -> Self {
+        Self { access_code_id, expires_at: now + PENDING_LOGIN_TTL_SECS, method }
+    }
+}
+
+pub fn start_pending_login(cookies: &CookieJar<'_>, pending: &PendingLogin) {
+    let value = format!("{}:{}:{}", pending.access_code_id, pending.expires_at, pending.method.as_str());
+    let cookie = Cookie::build((PENDING_LOGIN_COOKIE, value))
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .path("/login");
+    cookies.add_private(cookie);
+}
+
+/// Connexion en attente du navigateur appelant, si elle n'a pas expiré à `now`.
+pub fn pending_login(cookies: &CookieJar<'_>, now: i64) -> Option<PendingLogin> {
+    let cookie = cookies.get_private(PENDING_LOGIN_COOKIE)?;
+    let mut parts = cookie.value().splitn(3, ':');
+    let pending = PendingLogin {
+        access_code_id: parts.next()?.parse().ok()?,
+        expires_at: parts.next()?.parse().ok()?,
+        method: LoginMethod::parse(parts.next()?)?,
+    };
+    (pending.expires_at > now).then_some(pending)
+}
+
+pub fn end_pending_login(cookies: &CookieJar<'_>) {
+    cookies.remove_private(Cookie::build(PENDING_LOGIN_COOKIE).path("/login"));
+}
+
+// Résultat de la résolution de session, mis en cache pour la durée de la requête
+struct SessionCache(Option<AccessCode>);
+
+/// Access code de la session courante, s'il existe, est toujours actif et
+/// dans sa fenêtre de validité.
+///
+/// Le code est relu en base à chaque requête : désactiver, archiver ou
+/// laisser expirer un code ferme immédiatement ses sessions.
+pub async fn session_access_code(request: &Request<'_>) -> Option<AccessCode> {
+    let cache = request.local_cache_async(async {
+        let id = request
+            .cookies()
+            .get_private(SESSION_COOKIE)
+            .and_then(|cookie| cookie.value().parse::<i64>().ok());
+        let state = request.rocket().state::<AppState>();
+        let access_code = match (id, state) {
+            (Some(id), Some(state)) => state.repos.run(move |repos| repos.access_codes.find_active(id))
+                .await
+                .ok()
+                .filter(|access_code| access_code.is_valid_at(&state.clock.timestamp())),
+            _ => None,
+        };
+        SessionCache(access_code)
+    }).await;
+    cache.0.clone()
+}
+
+/// Jeton `Authorization: Bearer …` de la requête, s'il y en a un.
+pub fn bearer_token<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    request.headers()
+        .get_one("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Participant connecté, résolu depuis la session du navigateur.
+pub struct AuthenticatedUser {
+    pub access_code: AccessCode,
+}
 
 #[derive(Debug)]
 pub enum AuthError {
-    NotAuthenticated,
     MissingState,
+    /// Jeton d'API inconnu, révoqué ou expiré.
+    InvalidToken,
+    /// Le jeton d'API n'a pas le droit exigé par la route.
+    MissingScope,
+    Repository,
 }
 
 #[rocket::async_trait]
@@ -16,20 +130,137 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
     type Error = AuthError;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        let state = match request.rocket().state::<AppState>() {
-            Some(state) => state,
-            None => return Outcome::Error((Status::InternalServerError, AuthError::MissingState)),
-        };
+        if request.rocket().state::<AppState>().is_none() {
+            return Outcome::Error((Status::InternalServerError, AuthError::MissingState));
+        }
+
+        // un jeton d'API n'ouvre jamais la session du navigateur qui l'envoie
+        if bearer_token(request).is_some() {
+            return Outcome::Forward(Status::Unauthorized);
+        }
+        match session_access_code(request).await {
+            Some(access_code) => Outcome::Success(AuthenticatedUser { access_code }),
+            None => Outcome::Forward(Status::Unauthorized),
+        }
+    }
+}
+
+/// Guard réservé à l'access code administrateur (id 1).
+pub struct AdminUser {
+    pub access_code: AccessCode,
+}
 
-        let is_auth = match state.is_authenticated.lock() {
-            Ok(guard) => *guard,
-            Err(_) => return Outcome::Error((Status::InternalServerError, AuthError::NotAuthenticated)),
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match AuthenticatedUser::from_request(request).await {
+            Outcome::Success(user) => user,
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(status) => return Outcome::Forward(status),
         };
 
-        if is_auth {
-            Outcome::Success(AuthenticatedUser)
+        if user.access_code.id == 1 {
+            Outcome::Success(AdminUser { access_code: user.access_code })
         } else {
-            Outcome::Forward(Status::Unauthorized)
+            Outcome::Forward(Status::Forbidden)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Droit exigé par une route de l'API d'administration (voir `AdminAccess`).
+pub trait RequiredScope: Send + Sync + 'static {
+    const SCOPE: ApiScope;
+}
+
+/// Un marqueur par `ApiScope`, à passer en paramètre de `AdminAccess`.
+pub mod scope {
+    use super::RequiredScope;
+    use crate::models::api_token::ApiScope;
+
+    pub struct ReadParticipants;
+    pub struct WriteParticipants;
+    pub struct RunDraw;
+    pub struct Export;
+
+    impl RequiredScope for ReadParticipants {
+        const SCOPE: ApiScope = ApiScope::ReadParticipants;
+    }
+
+    impl RequiredScope for WriteParticipants {
+        const SCOPE: ApiScope = ApiScope::WriteParticipants;
+    }
+
+    impl RequiredScope for RunDraw {
+        const SCOPE: ApiScope = ApiScope::RunDraw;
+    }
+
+    impl RequiredScope for Export {
+        const SCOPE: ApiScope = ApiScope::Export;
+    }
+}
+
+/// Guard des routes d'API ouvertes aux scripts : la session de l'admin, ou
+/// un jeton d'API (`Authorization: Bearer …`) qui a le droit `S`.
+///
+/// Une requête qui envoie un jeton n'est jugée que sur ce jeton : un jeton
+/// invalide donne `401` et un droit manquant `403`, même avec une session.
+pub struct AdminAccess<S: RequiredScope> {
+    /// Admin connecté, ou admin qui a créé le jeton.
+    pub access_code: AccessCode,
+    /// Jeton utilisé, `None` pour une session.
+    pub api_token: Option<ApiToken>,
+    scope: PhantomData<S>,
+}
+
+#[rocket::async_trait]
+impl<'r, S: RequiredScope> FromRequest<'r> for AdminAccess<S> {
+    type Error = AuthError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(state) = request.rocket().state::<AppState>() else {
+            return Outcome::Error((Status::InternalServerError, AuthError::MissingState));
+        };
+        let Some(token) = bearer_token(request) else {
+            return AdminUser::from_request(request).await
+                .map(|admin| AdminAccess { access_code: admin.access_code, api_token: None, scope: PhantomData });
+        };
+
+        let token_hash = hash_token(token);
+        let now = state.clock.timestamp();
+        let result = state.repos.run(move |repos| {
+            let api_token = match repos.api_tokens.find_by_hash(&token_hash) {
+                Ok(api_token) if api_token.is_valid_at(&now) => api_token,
+                Ok(_) | Err(RepoError::NotFound) => return Ok(Err(AuthError::InvalidToken)),
+                Err(e) => return Err(e),
+            };
+            // le jeton agit au nom de l'admin, qui doit pouvoir se connecter
+            let access_code = match repos.access_codes.find_active(api_token.created_by) {
+                Ok(access_code) if access_code.is_valid_at(&now) => access_code,
+                Ok(_) | Err(RepoError::NotFound) => return Ok(Err(AuthError::InvalidToken)),
+                Err(e) => return Err(e),
+            };
+            if !api_token.allows(S::SCOPE) {
+                return Ok(Err(AuthError::MissingScope));
+            }
+            repos.api_tokens.touch(api_token.id, &now)?;
+            Ok(Ok((api_token, access_code)))
+        }).await;
+
+        match result {
+            Ok(Ok((api_token, access_code))) => {
+                Outcome::Success(AdminAccess { access_code, api_token: Some(api_token), scope: PhantomData })
+            }
+            Ok(Err(error)) => {
+                log::warn!("api token rejected reason={error:?} scope={} path={}", S::SCOPE.as_str(), request.uri().path());
+                let status = match error {
+                    AuthError::MissingScope => Status::Forbidden,
+                    _ => Status::Unauthorized,
+                };
+                Outcome::Error((status, error))
+            }
+            Err(e) => Outcome::Error((e.into(), AuthError::Repository)),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Sauvegardes de la base SQLite : instantanés cohérents via l'API de
+//! sauvegarde en ligne de SQLite, rotation dans `backup_dir` et restauration
+//! depuis un fichier envoyé par l'admin.
+
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::{Orbit, Rocket};
+use rusqlite::{Connection, OpenFlags, MAIN_DB};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::AppConfig;
+use crate::db::{init_db, schema_version, SqlitePool};
+use crate::state::AppState;
+
+/// Préfixe des fichiers de sauvegarde (`winter_card-20251224-180000.db`).
+const FILE_PREFIX: &str = "winter_card-";
+
+/// Tables indispensables dans un fichier à restaurer.
+const REQUIRED_TABLES: &[&str] = &["access_codes", "draws"];
+
+#[derive(Debug)]
+pub enum BackupError {
+    Io(std::io::Error),
+    Sqlite(rusqlite::Error),
+    Pool(r2d2::Error),
+}
+
+impl From<std::io::Error> for BackupError {
+    fn from(e: std::io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        BackupError::Sqlite(e)
+    }
+}
+
+impl From<r2d2::Error> for BackupError {
+    fn from(e: r2d2::Error) -> Self {
+        BackupError::Pool(e)
+    }
+}
+
+impl From<BackupError> for Status {
+    fn from(e: BackupError) -> Self {
+        log::error!("backup error={e:?}");
+        match e {
+            BackupError::Pool(_) => Status::ServiceUnavailable,
+            BackupError::Sqlite(e) if matches!(
+                e.sqlite_error_code(),
+                Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+            ) => Status::ServiceUnavailable,
+            _ => Status::InternalServerError,
+        }
+    }
+}
+
+/// Fichier temporaire vide, supprimé par `TempFile::drop`.
+///
+/// Le nom est aléatoire et le fichier est créé de façon exclusive (un fichier
+/// ou un lien symbolique déjà présent fait échouer la création), lisible par
+/// le seul propriétaire : un autre utilisateur de la machine ne peut ni le
+/// devancer ni le remplacer dans le répertoire temporaire partagé.
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn new() -> std::io::Result<Self> {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        let name: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+        let path = std::env::temp_dir().join(format!("winter_card_{name}.db"));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Nom de fichier horodaté d'une sauvegarde.
+pub fn file_name(now: chrono::DateTime<chrono::Utc>) -> String {
+    format!("{FILE_PREFIX}{}.db", now.format("%Y%m%d-%H%M%S"))
+}
+
+/// Copie cohérente de la base vers `dest`, sans interrompre les requêtes en cours.
+pub fn snapshot(pool: &SqlitePool, dest: &Path) -> Result<(), BackupError> {
+    let conn = pool.get()?;
+    conn.backup(MAIN_DB, dest, None)?;
+    Ok(())
+}
+
+/// Résultat de la validation d'un fichier à restaurer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RestoreReport {
+    pub valid: bool,
+    pub dry_run: bool,
+    pub restored: bool,
+    pub schema_version: Option<usize>,
+    pub current_schema_version: usize,
+    pub access_codes: Option<i64>,
+    pub draws: Option<i64>,
+    pub errors: Vec<String>,
+}
+
+/// Vérifie qu'un fichier est une base SQLite saine, d'un schéma que cette
+/// version sait migrer, contenant les tables attendues. La base n'est
+/// ouverte qu'en lecture.
+pub fn validate(path: &Path) -> RestoreReport {
+    let mut report = RestoreReport { current_schema_version: schema_version(), ..Default::default() };
+    if let Err(e) = inspect(path, &mut report) {
+        report.errors.push(format!("fichier illisible : {e}"));
+    }
+    report.valid = report.errors.is_empty();
+    report
+}
+
+fn inspect(path: &Path, report: &mut RestoreReport) -> rusqlite::Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        report.errors.push(format!("intégrité : {integrity}"));
+        return Ok(());
+    }
+
+    // références cassées : la base restaurée violerait ses clés étrangères
+    let broken: Vec<(String, i64)> = conn.prepare("PRAGMA foreign_key_check")?
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, Option<i64>>(1)?.unwrap_or_default())))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (table, rowid) in broken.iter().take(10) {
+        report.errors.push(format!("clé étrangère cassée : {table} (rowid {rowid})"));
+    }
+    if broken.len() > 10 {
+        report.errors.push(format!("… et {} autres clés étrangères cassées", broken.len() - 10));
+    }
+
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    report.schema_version = Some(version);
+    if version > report.current_schema_version {
+        report.errors.push(format!(
+            "schéma {version} plus récent que celui de l'application ({})",
+            report.current_schema_version
+        ));
+    }
+
+    for table in REQUIRED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            report.errors.push(format!("table manquante : {table}"));
+        }
+    }
+    if report.errors.is_empty() {
+        report.access_codes = Some(conn.query_row("SELECT COUNT(*) FROM access_codes", [], |row| row.get(0))?);
+        report.draws = Some(conn.query_row("SELECT COUNT(*) FROM draws", [], |row| row.get(0))?);
+    }
+    Ok(())
+}
+
+/// Remplace le contenu de la base par le fichier `path` (déjà validé), puis
+/// applique les migrations manquantes.
+pub fn restore(pool: &SqlitePool, path: &Path) -> Result<(), BackupError> {
+    let mut conn = pool.get()?;
+    conn.restore(MAIN_DB, path, None::<fn(rusqlite::backup::Progress)>)?;
+    drop(conn);
+    init_db(pool);
+    Ok(())
+}
+
+/// Écrit une sauvegarde horodatée dans `dir` et ne garde que les `keep` plus récentes.
+pub fn rotate(pool: &SqlitePool, dir: &Path, keep: usize) -> Result<PathBuf, BackupError> {
+    std::fs::create_dir_all(dir)?;
+    let dest = dir.join(file_name(chrono::Utc::now()));
+    snapshot(pool, &dest)?;
+
+    // l'horodatage du nom donne l'ordre chronologique
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(FILE_PREFIX) && name.ends_with(".db"))
+        })
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        std::fs::remove_file(old)?;
+    }
+    Ok(dest)
+}
+
+/// Lance au démarrage les sauvegardes périodiques si `backup_dir` est configuré.
+pub struct BackupScheduler;
+
+#[rocket::async_trait]
+impl Fairing for BackupScheduler {
+    fn info(&self) -> Info {
+        Info { name: "Scheduled backups", kind: Kind::Liftoff }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let Some(state) = rocket.state::<AppState>() else { return };
+        let AppConfig { backup_dir: Some(dir), backup_interval_hours, backup_keep, .. } = state.config.clone() else {
+            return;
+        };
+        let Some(pool) = state.db.sqlite().cloned() else {
+            log::warn!("backup_dir ignoré : les sauvegardes planifiées ne concernent que SQLite");
+            return;
+        };
+
+        let interval = Duration::from_secs(backup_interval_hours.max(1) * 3600);
+        let mut shutdown = rocket.shutdown();
+        log::info!("scheduled backups dir={dir} interval_hours={backup_interval_hours} keep={backup_keep}");
+        rocket::tokio::spawn(async move {
+            loop {
+                let pool = pool.clone();
+                let dir = PathBuf::from(&dir);
+                let result = rocket::tokio::task::spawn_blocking(move || rotate(&pool, &dir, backup_keep)).await;
+                match result {
+                    Ok(Ok(path)) => log::info!("backup written path={}", path.display()),
+                    Ok(Err(e)) => log::error!("backup failed error={e:?}"),
+                    Err(e) => log::error!("backup task failed error={e}"),
+                }
+                rocket::tokio::select! {
+                    _ = rocket::tokio::time::sleep(interval) => {}
+                    _ = &mut shutdown => break,
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_pool;
+
+    fn setup_pool() -> SqlitePool {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        pool
+    }
+
+    #[test]
+    fn test_snapshot_is_valid() {
+        let pool = setup_pool();
+        let file = TempFile::new().unwrap();
+        snapshot(&pool, file.path()).unwrap();
+
+        let report = validate(file.path());
+        assert!(report.valid, "{:?}", report.errors);
+        assert_eq!(report.schema_version, Some(schema_version()));
+        assert_eq!(report.access_codes, Some(1));
+    }
+
+    #[test]
+    fn test_validate_rejects_garbage_and_newer_schema() {
+        let garbage = TempFile::new().unwrap();
+        std::fs::write(garbage.path(), b"not a database").unwrap();
+        assert!(!validate(garbage.path()).valid);
+
+        let newer = TempFile::new().unwrap();
+        let conn = Connection::open(newer.path()).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE access_codes (id INTEGER); CREATE TABLE draws (id INTEGER); PRAGMA user_version = {};",
+            schema_version() + 1
+        )).unwrap();
+        drop(conn);
+        let report = validate(newer.path());
+        assert!(!report.valid);
+        assert_eq!(report.schema_version, Some(schema_version() + 1));
+    }
+
+    #[test]
+    fn test_temp_file_is_exclusive() {
+        let file = TempFile::new().unwrap();
+        assert_ne!(file.path(), TempFile::new().unwrap().path());
+        assert_eq!(std::fs::metadata(file.path()).unwrap().len(), 0);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(file.path()).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        let path = file.path().to_path_buf();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_validate_rejects_broken_foreign_keys() {
+        let pool = setup_pool();
+        let file = TempFile::new().unwrap();
+        snapshot(&pool, file.path()).unwrap();
+        let conn = Connection::open(file.path()).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF; INSERT INTO draws (giver_id, receiver_id, year) VALUES (1, 42, 2025);").unwrap();
+        drop(conn);
+
+        let report = validate(file.path());
+        assert!(!report.valid);
+        assert!(report.errors[0].contains("draws"), "{:?}", report.errors);
+    }
+
+    #[test]
+    fn test_rotate_keeps_most_recent() {
+        let pool = setup_pool();
+        let dir = std::env::temp_dir().join(format!("winter_card_backups_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for stamp in ["20200101-000000", "20210101-000000", "20220101-000000"] {
+            std::fs::write(dir.join(format!("{FILE_PREFIX}{stamp}.db")), b"").unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        let latest = rotate(&pool, &dir, 2).unwrap();
+
+        let mut names: Vec<String> = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        let _ = std::fs::remove_dir_all(&dir);
+        let latest = latest.file_name().unwrap().to_str().unwrap().to_string();
+        assert_eq!(names, vec!["notes.txt".to_string(), format!("{FILE_PREFIX}20220101-000000.db"), latest]);
+    }
+}
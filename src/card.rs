@@ -0,0 +1,135 @@
+use qrcode::types::QrError;
+use qrcode::{Color, QrCode};
+
+use crate::i18n::translate;
+
+/// Contenu d'une carte imprimable remise à un participant.
+pub struct Card<'a> {
+    pub name: &'a str,
+    pub code: &'a str,
+    pub login_url: &'a str,
+    pub event_name: &'a str,
+    pub event_budget: Option<&'a str>,
+    pub event_date: Option<&'a str>,
+    /// Langue des libellés imprimés.
+    pub lang: &'a str,
+}
+
+// format A6 paysage, en millimètres
+const CARD_WIDTH: u32 = 148;
+const CARD_HEIGHT: u32 = 105;
+const QR_SIZE: u32 = 46;
+// marge blanche autour du QR code (en modules) exigée par la norme
+const QR_QUIET_ZONE: usize = 4;
+
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Chemin SVG des modules sombres du QR code encodant `data`, avec la
+/// largeur totale (zone blanche comprise) exprimée en modules.
+fn qr_path(data: &str) -> Result<(String, usize), QrError> {
+    let code = QrCode::new(data.as_bytes())?;
+    let width = code.width();
+    let mut path = String::new();
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let x = i % width + QR_QUIET_ZONE;
+            let y = i / width + QR_QUIET_ZONE;
+            path.push_str(&format!("M{x},{y}h1v1h-1z"));
+        }
+    }
+    Ok((path, width + 2 * QR_QUIET_ZONE))
+}
+
+/// QR code seul, en SVG autonome (fond blanc).
+pub fn render_qr_svg(data: &str) -> Result<String, QrError> {
+    let (path, size) = qr_path(data)?;
+    Ok(format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}" shape-rendering="crispEdges"><rect width="{size}" height="{size}" fill="#fff"/><path d="{path}" fill="#000"/></svg>"##
+    ))
+}
+
+/// Carte complète en SVG : évènement, participant, code d'accès et QR code
+/// vers la page de connexion.
+pub fn render_card_svg(card: &Card) -> Result<String, QrError> {
+    let (path, modules) = qr_path(card.login_url)?;
+    let qr_x = CARD_WIDTH - QR_SIZE - 8;
+    let qr_y = (CARD_HEIGHT - QR_SIZE) / 2;
+    let scale = QR_SIZE as f64 / modules as f64;
+
+    let mut details = Vec::new();
+    if let Some(date) = card.event_date {
+        details.push(format!("📅 {}", escape_xml(date)));
+    }
+    if let Some(budget) = card.event_budget {
+        details.push(format!("🎁 {} {}", escape_xml(&translate(card.lang, "card.budget")), escape_xml(budget)));
+    }
+    let details_svg: String = details
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!(r#"<text x="8" y="{}" font-size="4.2">{line}</text>"#, 80 + i as u32 * 7))
+        .collect();
+
+    Ok(format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{CARD_WIDTH}mm" height="{CARD_HEIGHT}mm" viewBox="0 0 {CARD_WIDTH} {CARD_HEIGHT}" font-family="Georgia, serif">
+<rect x="0.5" y="0.5" width="{w}" height="{h}" rx="4" fill="#fff" stroke="#0d6efd" stroke-width="0.8"/>
+<text x="8" y="16" font-size="7" font-weight="bold" fill="#0d6efd">❄ {event}</text>
+<text x="8" y="32" font-size="5.5">{name}</text>
+<text x="8" y="50" font-size="3.8" fill="#555">{your_code}</text>
+<text x="8" y="60" font-size="8" font-family="monospace" font-weight="bold">{code}</text>
+{details_svg}
+<g transform="translate({qr_x} {qr_y}) scale({scale})" shape-rendering="crispEdges"><path d="{path}" fill="#000"/></g>
+<text x="{qr_center}" y="{qr_caption_y}" font-size="3" text-anchor="middle" fill="#555">{scan}</text>
+</svg>"##,
+        w = CARD_WIDTH - 1,
+        h = CARD_HEIGHT - 1,
+        event = escape_xml(card.event_name),
+        name = escape_xml(card.name),
+        code = escape_xml(card.code),
+        your_code = escape_xml(&translate(card.lang, "card.your_code")),
+        scan = escape_xml(&translate(card.lang, "card.scan")),
+        qr_center = qr_x + QR_SIZE / 2,
+        qr_caption_y = qr_y + QR_SIZE + 5,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card<'a>(name: &'a str) -> Card<'a> {
+        Card {
+            name,
+            code: "NOEL42",
+            login_url: "http://localhost:8000/",
+            event_name: "Noël en famille",
+            event_budget: Some("20 €"),
+            event_date: Some("24/12"),
+            lang: "fr",
+        }
+    }
+
+    #[test]
+    fn test_card_contains_participant_and_event() {
+        let svg = render_card_svg(&card("Alice")).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Alice"));
+        assert!(svg.contains("NOEL42"));
+        assert!(svg.contains("Noël en famille"));
+        assert!(svg.contains("20 €"));
+        assert!(svg.contains("<path d=\"M"));
+    }
+
+    #[test]
+    fn test_card_escapes_names() {
+        let svg = render_card_svg(&card("<Bob & Co>")).unwrap();
+        assert!(svg.contains("&lt;Bob &amp; Co&gt;"));
+        assert!(!svg.contains("<Bob"));
+    }
+}
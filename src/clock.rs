@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::Datelike;
+
+use crate::models::access_code::TIMESTAMP_FORMAT;
+
+/// Horloge de l'application (timestamp Unix, en secondes).
+///
+/// Elle suit l'horloge système, sauf en test où elle peut être figée puis
+/// avancée à la main (codes TOTP, expiration d'une connexion en attente).
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    fixed: Option<Arc<AtomicI64>>,
+}
+
+impl Clock {
+    #[cfg(test)]
+    pub fn fixed(now: i64) -> Self {
+        Self { fixed: Some(Arc::new(AtomicI64::new(now))) }
+    }
+
+    pub fn now(&self) -> i64 {
+        match &self.fixed {
+            Some(now) => now.load(Ordering::SeqCst),
+            None => chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// Date et heure courantes au format `TIMESTAMP_FORMAT`.
+    pub fn timestamp(&self) -> String {
+        chrono::DateTime::from_timestamp(self.now(), 0)
+            .unwrap_or_default()
+            .format(TIMESTAMP_FORMAT)
+            .to_string()
+    }
+
+    /// Année courante (UTC), celle des tirages en cours.
+    pub fn year(&self) -> i32 {
+        chrono::DateTime::from_timestamp(self.now(), 0).unwrap_or_default().year()
+    }
+
+    /// Avance une horloge figée de `secs` secondes.
+    #[cfg(test)]
+    pub fn advance(&self, secs: i64) {
+        let now = self.fixed.as_ref().expect("only a fixed clock can be advanced");
+        now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
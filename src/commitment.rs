@@ -0,0 +1,259 @@
+//! Tirage vérifiable : l'affectation complète d'une année est tirée d'un
+//! coup, puis engagée par une empreinte publiée avant le premier tirage.
+//!
+//! - Chaque paire reçoit un nonce `HMAC-SHA256(sel, "<année>:<donateur>")`,
+//!   qui empêche de deviner les paires des autres à partir des empreintes.
+//! - Feuille : `SHA-256(0x00 || "<année>:<donateur>:<destinataire>:<nonce>")`,
+//!   les feuilles étant triées par donateur.
+//! - Nœud : `SHA-256(0x01 || gauche || droite)` ; un nœud sans frère remonte
+//!   tel quel au niveau supérieur.
+//! - L'engagement est la racine de cet arbre de Merkle, en hexadécimal.
+//!
+//! Avant la révélation, chaque participant reçoit une preuve d'inclusion de
+//! sa paire (son nonce et les empreintes sœurs) ; après, le sel et
+//! l'affectation publiés permettent à chacun de recalculer la racine.
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::models::commitment::Pairing;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Côté de l'empreinte sœur dans une étape de preuve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct ProofStep {
+    pub side: Side,
+    /// Empreinte sœur, en hexadécimal.
+    pub hash: String,
+}
+
+/// Preuve qu'une paire appartient à l'affectation engagée.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct PairingProof {
+    pub year: i32,
+    pub giver_id: i64,
+    pub receiver_id: i64,
+    pub nonce: String,
+    /// Empreintes sœurs, de la feuille vers la racine.
+    pub path: Vec<ProofStep>,
+}
+
+/// Nouveau sel : 32 octets aléatoires en hexadécimal.
+pub fn generate_salt() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// Affectation tirée uniformément parmi celles où personne ne se tire
+/// lui-même (rejet des permutations avec un point fixe), triée par donateur.
+/// Vide s'il y a moins de deux participants.
+pub fn random_assignment(participant_ids: &[i64]) -> Vec<Pairing> {
+    let mut givers = participant_ids.to_vec();
+    givers.sort_unstable();
+    givers.dedup();
+    if givers.len() < 2 {
+        return Vec::new();
+    }
+    let mut receivers = givers.clone();
+    loop {
+        // Fisher-Yates
+        for i in (1..receivers.len()).rev() {
+            let j = (OsRng.next_u64() % (i as u64 + 1)) as usize;
+            receivers.swap(i, j);
+        }
+        if givers.iter().zip(&receivers).all(|(giver, receiver)| giver != receiver) {
+            break;
+        }
+    }
+    givers.into_iter().zip(receivers).map(|(giver_id, receiver_id)| Pairing { giver_id, receiver_id }).collect()
+}
+
+/// Nonce de la paire de `giver_id`, en hexadécimal. `None` si le sel n'est
+/// pas de l'hexadécimal.
+pub fn leaf_nonce(salt: &str, year: i32, giver_id: i64) -> Option<String> {
+    let key = from_hex(salt)?;
+    let mut mac = HmacSha256::new_from_slice(&key).ok()?;
+    mac.update(format!("{year}:{giver_id}").as_bytes());
+    Some(to_hex(&mac.finalize().into_bytes()))
+}
+
+fn leaf_hash(year: i32, pairing: Pairing, nonce: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(format!("{year}:{}:{}:{nonce}", pairing.giver_id, pairing.receiver_id).as_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn leaves(year: i32, salt: &str, assignment: &[Pairing]) -> Option<Vec<[u8; 32]>> {
+    let mut sorted = assignment.to_vec();
+    sorted.sort_by_key(|pairing| pairing.giver_id);
+    sorted.into_iter()
+        .map(|pairing| Some(leaf_hash(year, pairing, &leaf_nonce(salt, year, pairing.giver_id)?)))
+        .collect()
+}
+
+// Niveau supérieur de l'arbre
+fn parent_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level.chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Engagement de l'affectation : racine de Merkle des paires salées.
+/// `None` si l'affectation est vide ou le sel invalide.
+pub fn commit(year: i32, salt: &str, assignment: &[Pairing]) -> Option<String> {
+    let mut level = leaves(year, salt, assignment)?;
+    if level.is_empty() {
+        return None;
+    }
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    Some(to_hex(&level[0]))
+}
+
+/// Preuve d'inclusion de la paire de `giver_id`.
+pub fn prove(year: i32, salt: &str, assignment: &[Pairing], giver_id: i64) -> Option<PairingProof> {
+    let mut level = leaves(year, salt, assignment)?;
+    let mut sorted = assignment.to_vec();
+    sorted.sort_by_key(|pairing| pairing.giver_id);
+    let mut index = sorted.iter().position(|pairing| pairing.giver_id == giver_id)?;
+    let pairing = sorted[index];
+
+    let mut path = Vec::new();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if let Some(hash) = level.get(sibling) {
+            let side = if sibling < index { Side::Left } else { Side::Right };
+            path.push(ProofStep { side, hash: to_hex(hash) });
+        }
+        level = parent_level(&level);
+        index /= 2;
+    }
+    Some(PairingProof {
+        year,
+        giver_id,
+        receiver_id: pairing.receiver_id,
+        nonce: leaf_nonce(salt, year, giver_id)?,
+        path,
+    })
+}
+
+/// La preuve mène-t-elle à l'engagement publié ? Les participants font la
+/// même vérification dans leur navigateur ; celle-ci sert aux tests.
+#[cfg(test)]
+pub fn verify(proof: &PairingProof, commitment: &str) -> bool {
+    let pairing = Pairing { giver_id: proof.giver_id, receiver_id: proof.receiver_id };
+    let mut hash = leaf_hash(proof.year, pairing, &proof.nonce);
+    for step in &proof.path {
+        let Some(sibling) = from_hex(&step.hash).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) else {
+            return false;
+        };
+        hash = match step.side {
+            Side::Left => node_hash(&sibling, &hash),
+            Side::Right => node_hash(&hash, &sibling),
+        };
+    }
+    to_hex(&hash) == commitment
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len()).step_by(2).map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_assignment() {
+        assert!(random_assignment(&[1]).is_empty());
+        for _ in 0..50 {
+            let assignment = random_assignment(&[5, 2, 9, 3, 7]);
+            assert_eq!(assignment.iter().map(|p| p.giver_id).collect::<Vec<_>>(), [2, 3, 5, 7, 9]);
+            let mut receivers: Vec<i64> = assignment.iter().map(|p| p.receiver_id).collect();
+            receivers.sort_unstable();
+            assert_eq!(receivers, [2, 3, 5, 7, 9]);
+            assert!(assignment.iter().all(|p| p.giver_id != p.receiver_id));
+        }
+    }
+
+    #[test]
+    fn test_commit_and_prove() {
+        let salt = generate_salt();
+        // nombre impair de feuilles : un nœud remonte sans frère
+        let assignment = random_assignment(&[1, 2, 3, 4, 5]);
+        let commitment = commit(2025, &salt, &assignment).unwrap();
+        assert_eq!(commitment.len(), 64);
+        // l'ordre de l'affectation n'importe pas
+        let reversed: Vec<Pairing> = assignment.iter().rev().copied().collect();
+        assert_eq!(commit(2025, &salt, &reversed).unwrap(), commitment);
+        assert_ne!(commit(2025, &generate_salt(), &assignment).unwrap(), commitment);
+        assert_ne!(commit(2026, &salt, &assignment).unwrap(), commitment);
+
+        for pairing in &assignment {
+            let proof = prove(2025, &salt, &assignment, pairing.giver_id).unwrap();
+            assert_eq!(proof.receiver_id, pairing.receiver_id);
+            assert!(verify(&proof, &commitment));
+
+            // une autre paire ne vérifie pas
+            let other = (1..=5).find(|id| *id != pairing.giver_id && *id != pairing.receiver_id).unwrap();
+            assert!(!verify(&PairingProof { receiver_id: other, ..proof.clone() }, &commitment));
+            assert!(!verify(&PairingProof { nonce: leaf_nonce(&salt, 2025, other).unwrap(), ..proof.clone() }, &commitment));
+        }
+        assert!(prove(2025, &salt, &assignment, 42).is_none());
+        assert!(commit(2025, "not hex", &assignment).is_none());
+        assert!(commit(2025, &salt, &[]).is_none());
+    }
+
+    // vecteur fixe : les vérificateurs externes (page de vérification,
+    // scripts) doivent retrouver exactement cette racine
+    #[test]
+    fn test_known_commitment() {
+        let salt = "00".repeat(32);
+        let assignment = [Pairing { giver_id: 1, receiver_id: 2 }, Pairing { giver_id: 2, receiver_id: 1 }];
+        let nonce = leaf_nonce(&salt, 2025, 1).unwrap();
+        let leaf_1 = leaf_hash(2025, assignment[0], &nonce);
+        let leaf_2 = leaf_hash(2025, assignment[1], &leaf_nonce(&salt, 2025, 2).unwrap());
+        assert_eq!(commit(2025, &salt, &assignment).unwrap(), to_hex(&node_hash(&leaf_1, &leaf_2)));
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!("\x002025:1:2:{nonce}").as_bytes());
+        assert_eq!(leaf_1, <[u8; 32]>::from(hasher.finalize()));
+    }
+}
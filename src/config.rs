@@ -0,0 +1,74 @@
+use serde::Deserialize;
+
+/// Configuration applicative lue depuis la configuration Rocket
+/// (`Rocket.toml` ou variables d'environnement `ROCKET_*`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Base de données : chemin d'un fichier SQLite (préfixe `sqlite://`
+    /// facultatif) ou URL `postgres://…` (feature `postgres`).
+    pub database_url: String,
+    /// Nombre maximal de connexions du pool.
+    pub database_pool_size: u32,
+    /// Attente maximale d'une connexion libre, en secondes (au-delà : 503).
+    pub database_timeout_secs: u64,
+    /// Fermeture des connexions inutilisées depuis ce délai, en secondes.
+    pub database_idle_timeout_secs: Option<u64>,
+    /// SQLite : attente d'un verrou avant d'échouer (`PRAGMA busy_timeout`), en ms.
+    pub database_busy_timeout_ms: u64,
+    /// Clé serveur (32 octets encodés en base64) utilisée pour chiffrer les
+    /// adresses postales. Sans clé, la saisie d'adresse est désactivée.
+    pub address_key: Option<String>,
+    /// URL publique de l'application, utilisée dans les QR codes des cartes.
+    pub public_url: String,
+    /// Informations de l'évènement imprimées sur les cartes.
+    pub event_name: String,
+    pub event_budget: Option<String>,
+    pub event_date: Option<String>,
+    /// Clé (base64, 32 octets min.) signant les liens de connexion à usage unique.
+    pub login_link_key: Option<String>,
+    /// Durée de validité d'un lien de connexion, en heures.
+    pub login_link_ttl_hours: i64,
+    /// Répertoire des sauvegardes planifiées (SQLite). Sans valeur, aucune
+    /// sauvegarde automatique.
+    pub backup_dir: Option<String>,
+    pub backup_interval_hours: u64,
+    /// Nombre de sauvegardes conservées dans `backup_dir`.
+    pub backup_keep: usize,
+    /// Taille maximale d'un fichier de restauration, en Mio.
+    pub backup_max_upload_mb: u64,
+    /// Jeton exigé par `/metrics` (`Authorization: Bearer …`). Sans valeur,
+    /// les métriques sont publiques.
+    pub metrics_token: Option<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            database_url: "winter_card.db".to_string(),
+            database_pool_size: 10,
+            database_timeout_secs: 30,
+            database_idle_timeout_secs: Some(600),
+            database_busy_timeout_ms: 5000,
+            address_key: None,
+            public_url: "http://localhost:8000".to_string(),
+            event_name: "Secret Santa".to_string(),
+            event_budget: None,
+            event_date: None,
+            login_link_key: None,
+            login_link_ttl_hours: 72,
+            backup_dir: None,
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            backup_max_upload_mb: 64,
+            metrics_token: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// URL absolue vers `path` (qui commence par `/`).
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.public_url.trim_end_matches('/'), path)
+    }
+}
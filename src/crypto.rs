@@ -0,0 +1,79 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+#[derive(Debug)]
+pub enum CryptoError {
+    InvalidKey,
+    Encrypt,
+    Decrypt,
+}
+
+/// Chiffre / déchiffre les adresses postales avec AES-256-GCM.
+///
+/// L'id de l'access code est utilisé comme donnée associée : un chiffré
+/// copié sur la ligne d'un autre participant ne se déchiffre pas.
+pub struct AddressCipher {
+    cipher: Aes256Gcm,
+}
+
+impl AddressCipher {
+    /// Construit le chiffreur à partir d'une clé de 32 octets encodée en base64.
+    pub fn from_base64(key: &str) -> Result<Self, CryptoError> {
+        let bytes = STANDARD.decode(key.trim()).map_err(|_| CryptoError::InvalidKey)?;
+        if bytes.len() != 32 {
+            return Err(CryptoError::InvalidKey);
+        }
+        let key = Key::<Aes256Gcm>::from_slice(&bytes);
+        Ok(Self { cipher: Aes256Gcm::new(key) })
+    }
+
+    /// Retourne `(nonce, chiffré)`.
+    pub fn encrypt(&self, access_code_id: i64, plaintext: &str) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = access_code_id.to_be_bytes();
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: plaintext.as_bytes(), aad: &aad })
+            .map_err(|_| CryptoError::Encrypt)?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    pub fn decrypt(&self, access_code_id: i64, nonce: &[u8], ciphertext: &[u8]) -> Result<String, CryptoError> {
+        if nonce.len() != 12 {
+            return Err(CryptoError::Decrypt);
+        }
+        let aad = access_code_id.to_be_bytes();
+        let plaintext = self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| CryptoError::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| CryptoError::Decrypt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    #[test]
+    fn test_roundtrip() {
+        let cipher = AddressCipher::from_base64(KEY).expect("valid key");
+        let (nonce, ct) = cipher.encrypt(42, "1 rue du Pôle Nord").unwrap();
+        assert_ne!(ct, b"1 rue du P\xc3\xb4le Nord".to_vec());
+        assert_eq!(cipher.decrypt(42, &nonce, &ct).unwrap(), "1 rue du Pôle Nord");
+    }
+
+    #[test]
+    fn test_ciphertext_bound_to_access_code() {
+        let cipher = AddressCipher::from_base64(KEY).expect("valid key");
+        let (nonce, ct) = cipher.encrypt(42, "1 rue du Pôle Nord").unwrap();
+        assert!(cipher.decrypt(43, &nonce, &ct).is_err());
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(AddressCipher::from_base64("trop-court").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Cookie, SameSite, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request};
+
+/// Cookie privé contenant le jeton CSRF du navigateur.
+pub const CSRF_COOKIE: &str = "csrf";
+/// En-tête envoyé par les appels `fetch` des templates.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+// Jeton de la requête, mis en cache pour la durée de la requête
+struct CsrfCache(String);
+
+/// Jeton CSRF du navigateur appelant, créé (et posé en cookie) s'il n'existe pas.
+///
+/// Un cookie ajouté pendant la requête n'est pas relu par `get_private` : le
+/// jeton est donc conservé dans le cache local de la requête.
+fn current_token(request: &Request<'_>) -> String {
+    request.local_cache(|| {
+        let cookies = request.cookies();
+        if let Some(cookie) = cookies.get_private(CSRF_COOKIE) {
+            return CsrfCache(cookie.value().to_string());
+        }
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        let cookie = Cookie::build((CSRF_COOKIE, token.clone()))
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .path("/");
+        cookies.add_private(cookie);
+        CsrfCache(token)
+    }).0.clone()
+}
+
+// comparaison en temps constant
+pub(crate) fn same_token(expected: &str, submitted: &str) -> bool {
+    expected.len() == submitted.len()
+        && expected.bytes().zip(submitted.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Fairing posant le cookie CSRF sur toute réponse qui n'en a pas encore.
+pub struct CsrfFairing;
+
+#[rocket::async_trait]
+impl Fairing for CsrfFairing {
+    fn info(&self) -> Info {
+        Info { name: "CSRF token", kind: Kind::Request }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        current_token(request);
+    }
+}
+
+/// Jeton CSRF à intégrer aux templates (`csrf_token`).
+pub struct CsrfToken(pub String);
+
+impl CsrfToken {
+    /// Vérifie le jeton soumis par un formulaire (champ `csrf_token`).
+    pub fn verify(&self, submitted: &str) -> bool {
+        same_token(&self.0, submitted)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfToken {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(CsrfToken(current_token(request)))
+    }
+}
+
+#[derive(Debug)]
+pub enum CsrfError {
+    Missing,
+    Mismatch,
+}
+
+/// Guard des routes d'API qui modifient l'état : l'en-tête `X-CSRF-Token`
+/// doit correspondre au jeton du cookie, sauf pour un script authentifié par
+/// jeton d'API (`auth::AdminAccess`).
+pub struct CsrfVerified;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CsrfVerified {
+    type Error = CsrfError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        // un navigateur n'envoie pas de lui-même l'en-tête `Authorization` :
+        // une requête authentifiée par jeton d'API ne peut pas être forgée
+        // par un autre site, et elle ne s'appuie jamais sur la session
+        if crate::auth::bearer_token(request).is_some() {
+            return Outcome::Success(CsrfVerified);
+        }
+        let Some(submitted) = request.headers().get_one(CSRF_HEADER) else {
+            log::warn!("csrf rejected reason=missing path={}", request.uri().path());
+            return Outcome::Error((Status::Forbidden, CsrfError::Missing));
+        };
+        if same_token(&current_token(request), submitted) {
+            Outcome::Success(CsrfVerified)
+        } else {
+            log::warn!("csrf rejected reason=mismatch path={}", request.uri().path());
+            Outcome::Error((Status::Forbidden, CsrfError::Mismatch))
+        }
+    }
+}
+
+/// Aides pour les tests de routes : récupèrent le jeton d'un client suivi.
+#[cfg(test)]
+pub mod testing {
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+
+    /// Jeton CSRF du client, obtenu en visitant `/` au besoin.
+    ///
+    /// Une requête locale copie les cookies du client à sa création : appeler
+    /// cette fonction avant de construire la requête qui utilise le jeton.
+    pub fn token(client: &Client) -> String {
+        if client.cookies().get_private(super::CSRF_COOKIE).is_none() {
+            client.get("/").dispatch();
+        }
+        client.cookies()
+            .get_private(super::CSRF_COOKIE)
+            .map(|cookie| cookie.value().to_string())
+            .expect("csrf cookie")
+    }
+
+    pub fn header(client: &Client) -> Header<'static> {
+        Header::new(super::CSRF_HEADER, token(client))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_token() {
+        assert!(same_token("abc", "abc"));
+        assert!(!same_token("abc", "abd"));
+        assert!(!same_token("abc", "ab"));
+        assert!(!same_token("abc", ""));
+    }
+}
@@ -1,12 +1,168 @@
 use r2d2::{Pool};
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::params;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::repo::Repos;
 
 pub type SqlitePool = Pool<SqliteConnectionManager>;
+#[cfg(feature = "postgres")]
+type PgManager = r2d2_postgres::PostgresConnectionManager<postgres::NoTls>;
+#[cfg(feature = "postgres")]
+pub type PgPool = Pool<PgManager>;
+
+/// Base de données de l'application, choisie d'après `database_url`.
+#[derive(Clone)]
+pub enum Database {
+    Sqlite(SqlitePool),
+    #[cfg(feature = "postgres")]
+    Postgres(PgPool),
+}
+
+impl Database {
+    /// `postgres://…` ou `postgresql://…` ouvre une base PostgreSQL (feature
+    /// `postgres`) ; toute autre valeur est un chemin SQLite, avec ou sans
+    /// préfixe `sqlite://`.
+    pub fn connect(config: &AppConfig) -> Self {
+        let url = config.database_url.as_str();
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            #[cfg(feature = "postgres")]
+            return Database::Postgres(init_pg_pool(url, pool_builder(config)));
+            #[cfg(not(feature = "postgres"))]
+            panic!("database_url PostgreSQL : recompiler avec `--features postgres`");
+        }
+        Database::Sqlite(sqlite_pool(url.strip_prefix("sqlite://").unwrap_or(url), config))
+    }
+
+    /// Crée les tables et applique les migrations.
+    pub fn init(&self) {
+        match self {
+            Database::Sqlite(pool) => init_db(pool),
+            #[cfg(feature = "postgres")]
+            Database::Postgres(pool) => init_pg_db(pool),
+        }
+    }
+
+    pub fn repos(&self) -> Repos {
+        match self {
+            Database::Sqlite(pool) => Repos::sqlite(pool.clone()),
+            #[cfg(feature = "postgres")]
+            Database::Postgres(pool) => Repos::postgres(pool.clone()),
+        }
+    }
+
+    /// Pool SQLite, pour les opérations propres à ce moteur.
+    pub fn sqlite(&self) -> Option<&SqlitePool> {
+        match self {
+            Database::Sqlite(pool) => Some(pool),
+            #[cfg(feature = "postgres")]
+            Database::Postgres(_) => None,
+        }
+    }
+
+    /// Occupation du pool de connexions.
+    pub fn pool_usage(&self) -> PoolUsage {
+        match self {
+            Database::Sqlite(pool) => PoolUsage::of(pool),
+            #[cfg(feature = "postgres")]
+            Database::Postgres(pool) => PoolUsage::of(pool),
+        }
+    }
 
+    /// Vérifie qu'une connexion s'obtient rapidement et que toutes les
+    /// migrations sont appliquées ; renvoie la version du schéma. Bloquant.
+    pub fn check_ready(&self) -> Result<usize, String> {
+        let version = match self {
+            Database::Sqlite(pool) => {
+                let conn = pool.get_timeout(READY_TIMEOUT).map_err(|e| format!("pool: {e}"))?;
+                conn.query_row("PRAGMA user_version", [], |row| row.get::<_, usize>(0))
+                    .map_err(|e| format!("schema: {e}"))?
+            }
+            #[cfg(feature = "postgres")]
+            Database::Postgres(pool) => blocking(|| {
+                let mut conn = pool.get_timeout(READY_TIMEOUT).map_err(|e| format!("pool: {e}"))?;
+                conn.query_opt("SELECT version FROM schema_version", &[])
+                    .map(|row| row.map_or(0, |row| row.get::<_, i32>(0) as usize))
+                    .map_err(|e| format!("schema: {e}"))
+            })?,
+        };
+        if version < schema_version() {
+            return Err(format!("schema version {version}, expected {}", schema_version()));
+        }
+        Ok(version)
+    }
+}
+
+/// Attente maximale d'une connexion pour la sonde de disponibilité.
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Connexions du pool : ouvertes, inactives et maximum autorisé.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolUsage {
+    pub connections: u32,
+    pub idle: u32,
+    pub max_size: u32,
+}
+
+impl PoolUsage {
+    fn of<M: r2d2::ManageConnection>(pool: &Pool<M>) -> Self {
+        let state = pool.state();
+        Self { connections: state.connections, idle: state.idle_connections, max_size: pool.max_size() }
+    }
+}
+
+/// Réglages du pool (taille, délais) lus depuis la configuration.
+fn pool_builder<M: r2d2::ManageConnection>(config: &AppConfig) -> r2d2::Builder<M> {
+    Pool::builder()
+        .max_size(config.database_pool_size)
+        .connection_timeout(Duration::from_secs(config.database_timeout_secs))
+        .idle_timeout(config.database_idle_timeout_secs.map(Duration::from_secs))
+}
+
+/// Pool de connexions vers la base `path`, avec les réglages par défaut.
+#[cfg(test)]
 pub fn init_pool(path: &str) -> SqlitePool {
-    let manager = SqliteConnectionManager::file(path);
-    Pool::new(manager).expect("Failed to create SQLite pool")
+    sqlite_pool(path, &AppConfig::default())
+}
+
+/// PRAGMAs appliqués à chaque connexion SQLite à son ouverture par le pool.
+#[derive(Debug)]
+struct SqlitePragmas {
+    busy_timeout: Duration,
+}
+
+impl r2d2::CustomizeConnection<rusqlite::Connection, rusqlite::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        // SQLite n'applique les clés étrangères que si on le lui demande
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.busy_timeout(self.busy_timeout)?;
+        // les lectures ne bloquent plus les écritures (sans effet en mémoire)
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        Ok(())
+    }
+}
+
+/// Pool de connexions vers la base `path`.
+///
+/// `:memory:` crée une base en mémoire partagée par toutes les connexions du
+/// pool (et propre à ce pool), au lieu d'une base distincte par connexion.
+fn sqlite_pool(path: &str, config: &AppConfig) -> SqlitePool {
+    let manager = if path == ":memory:" {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let uri = format!("file:winter_card_mem_{}_{id}?mode=memory&cache=shared", std::process::id());
+        // les URI `file:` sont acceptées par les flags d'ouverture par défaut
+        SqliteConnectionManager::file(uri)
+    } else {
+        SqliteConnectionManager::file(path)
+    };
+    let pragmas = SqlitePragmas { busy_timeout: Duration::from_millis(config.database_busy_timeout_ms) };
+    pool_builder(config)
+        .connection_customizer(Box::new(pragmas))
+        .build(manager)
+        .expect("Failed to create SQLite pool")
 }
 
 pub fn init_db(pool: &SqlitePool) {
@@ -44,4 +200,395 @@ pub fn init_db(pool: &SqlitePool) {
         );
         ",
     ).expect("Failed to create tables");
-}
\ No newline at end of file
+
+    // adresses postales chiffrées (visibles uniquement par le donateur tiré au sort)
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS shipping_addresses (
+            access_code_id INTEGER PRIMARY KEY,
+            nonce BLOB NOT NULL,
+            ciphertext BLOB NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (access_code_id) REFERENCES access_codes(id)
+        );
+        ",
+    ).expect("Failed to create tables");
+
+    // liens de connexion à usage unique (QR code / lien magique)
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS login_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            access_code_id INTEGER NOT NULL,
+            expires_at INTEGER NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (access_code_id) REFERENCES access_codes(id)
+        );
+        ",
+    ).expect("Failed to create tables");
+
+    migrate(&conn);
+}
+
+/// Migrations de schéma appliquées dans l'ordre. `PRAGMA user_version`
+/// mémorise le nombre de migrations déjà appliquées sur la base.
+const MIGRATIONS: &[&str] = &[
+    // 1 : langue préférée du participant
+    "ALTER TABLE access_codes ADD COLUMN locale TEXT;",
+    // 2 : journal d'audit des actions sensibles
+    "
+    CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        actor_id INTEGER,
+        actor_name TEXT,
+        action TEXT NOT NULL,
+        target_id INTEGER,
+        before TEXT,
+        after TEXT,
+        detail TEXT
+    );
+    CREATE INDEX idx_audit_log_created_at ON audit_log(created_at);
+    ",
+    // 3 : archivage des participants (suppression logique)
+    "ALTER TABLE access_codes ADD COLUMN archived_at TEXT;",
+    // 4 : fenêtre de validité, nombre de connexions maximal et suivi des connexions
+    "
+    ALTER TABLE access_codes ADD COLUMN valid_from TEXT;
+    ALTER TABLE access_codes ADD COLUMN valid_until TEXT;
+    ALTER TABLE access_codes ADD COLUMN max_uses INTEGER;
+    ALTER TABLE access_codes ADD COLUMN last_login_at TEXT;
+    ALTER TABLE access_codes ADD COLUMN login_count INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 5 : index de la liste des participants (recherche, tri, jointure par année)
+    "
+    CREATE INDEX idx_access_codes_name ON access_codes(name COLLATE NOCASE);
+    CREATE INDEX idx_access_codes_last_login_at ON access_codes(last_login_at);
+    CREATE INDEX idx_draws_year_giver ON draws(year, giver_id);
+    ",
+    // 6 : préférences de notification choisies par le participant
+    "
+    ALTER TABLE access_codes ADD COLUMN notify_draw INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE access_codes ADD COLUMN notify_reminders INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 7 : liens d'invitation et inscriptions en attente de validation
+    "
+    CREATE TABLE invitations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        token TEXT NOT NULL UNIQUE,
+        label TEXT NOT NULL,
+        max_signups INTEGER,
+        expires_at TEXT,
+        revoked_at TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE signups (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        invitation_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        code TEXT NOT NULL UNIQUE,
+        status TEXT NOT NULL DEFAULT 'pending',
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        decided_at TEXT,
+        FOREIGN KEY (invitation_id) REFERENCES invitations(id)
+    );
+    CREATE INDEX idx_signups_status ON signups(status);
+    ",
+    // 8 : double authentification TOTP (RFC 6238) et codes de secours
+    "
+    CREATE TABLE totp_credentials (
+        access_code_id INTEGER PRIMARY KEY,
+        secret BLOB NOT NULL,
+        confirmed_at TEXT,
+        last_step INTEGER,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (access_code_id) REFERENCES access_codes(id)
+    );
+    CREATE TABLE totp_recovery_codes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        access_code_id INTEGER NOT NULL,
+        code_hash TEXT NOT NULL,
+        used_at TEXT,
+        FOREIGN KEY (access_code_id) REFERENCES access_codes(id)
+    );
+    CREATE INDEX idx_totp_recovery_codes_access_code ON totp_recovery_codes(access_code_id);
+    ",
+    // 9 : jetons d'API de l'admin (empreinte SHA-256, droits séparés par des espaces)
+    "
+    CREATE TABLE api_tokens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        token_hash TEXT NOT NULL UNIQUE,
+        scopes TEXT NOT NULL,
+        created_by INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        expires_at TEXT,
+        revoked_at TEXT,
+        last_used_at TEXT,
+        FOREIGN KEY (created_by) REFERENCES access_codes(id)
+    );
+    ",
+    // 10 : participant créé par la validation d'une inscription (statistiques
+    // d'inscription), retrouvé par son code pour les inscriptions déjà validées
+    "
+    ALTER TABLE signups ADD COLUMN access_code_id INTEGER REFERENCES access_codes(id);
+    UPDATE signups SET access_code_id = (SELECT id FROM access_codes WHERE access_codes.code = signups.code)
+        WHERE status = 'approved';
+    ",
+    // 11 : affectation scellée d'une année et son engagement (tirage vérifiable) ;
+    // sans clé étrangère, pour rester vérifiable après la purge d'un participant
+    "
+    CREATE TABLE draw_commitments (
+        year INTEGER PRIMARY KEY,
+        commitment TEXT NOT NULL,
+        salt TEXT NOT NULL,
+        assignment TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        revealed_at TEXT
+    );
+    ",
+    // 12 : codes TOTP erronés comptés côté serveur (blocage du second facteur)
+    "
+    ALTER TABLE totp_credentials ADD COLUMN failed_attempts INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE totp_credentials ADD COLUMN last_failure_at INTEGER;
+    ",
+];
+
+/// Version du schéma une fois toutes les migrations appliquées (SQLite et
+/// PostgreSQL suivent la même numérotation).
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+fn migrate(conn: &rusqlite::Connection) {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .expect("Failed to read schema version");
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(&format!(
+            "BEGIN; {migration} PRAGMA user_version = {}; COMMIT;",
+            index + 1
+        )).unwrap_or_else(|e| panic!("Failed to apply migration {}: {e}", index + 1));
+    }
+}
+
+/// Exécute `f`, qui bloque sur le runtime interne du client `postgres`.
+///
+/// Depuis un thread du runtime tokio de Rocket, `block_in_place` libère ce
+/// thread le temps de l'appel ; ailleurs (tests, threads r2d2) `f` est
+/// appelée directement.
+#[cfg(feature = "postgres")]
+pub fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    if rocket::tokio::runtime::Handle::try_current().is_ok() {
+        rocket::tokio::task::block_in_place(f)
+    } else {
+        f()
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn init_pg_pool(url: &str, builder: r2d2::Builder<PgManager>) -> PgPool {
+    let config: postgres::Config = url.parse().expect("Invalid PostgreSQL URL");
+    pg_pool(config, builder)
+}
+
+#[cfg(feature = "postgres")]
+fn pg_pool(config: postgres::Config, builder: r2d2::Builder<PgManager>) -> PgPool {
+    let manager = PgManager::new(config, postgres::NoTls);
+    blocking(|| builder.build(manager)).expect("Failed to create PostgreSQL pool")
+}
+
+/// Pool PostgreSQL vers `config`, avec les réglages par défaut.
+#[cfg(all(test, feature = "postgres"))]
+pub fn test_pg_pool(config: postgres::Config) -> PgPool {
+    pg_pool(config, pool_builder(&AppConfig::default()))
+}
+
+/// Schéma PostgreSQL, équivalent à celui de `init_db` (mêmes contraintes
+/// d'unicité sur les tirages).
+#[cfg(feature = "postgres")]
+const PG_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS access_codes (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        name TEXT NOT NULL,
+        code TEXT NOT NULL UNIQUE,
+        active BOOLEAN NOT NULL DEFAULT TRUE
+    );
+    INSERT INTO access_codes (name, code, active) VALUES ('Admin', 'Winter2025', TRUE)
+        ON CONFLICT (code) DO NOTHING;
+
+    CREATE TABLE IF NOT EXISTS draws (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        giver_id BIGINT NOT NULL REFERENCES access_codes(id),
+        receiver_id BIGINT NOT NULL REFERENCES access_codes(id),
+        year INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+        UNIQUE (giver_id, year),
+        UNIQUE (receiver_id, year)
+    );
+
+    CREATE TABLE IF NOT EXISTS shipping_addresses (
+        access_code_id BIGINT PRIMARY KEY REFERENCES access_codes(id),
+        nonce BYTEA NOT NULL,
+        ciphertext BYTEA NOT NULL,
+        updated_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+    );
+
+    CREATE TABLE IF NOT EXISTS login_links (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        access_code_id BIGINT NOT NULL REFERENCES access_codes(id),
+        expires_at BIGINT NOT NULL,
+        used_at TEXT,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+    );
+
+    CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);
+";
+
+/// Migrations PostgreSQL, numérotées comme `MIGRATIONS` (la version est
+/// mémorisée dans la table `schema_version`).
+#[cfg(feature = "postgres")]
+const PG_MIGRATIONS: &[&str] = &[
+    // 1 : langue préférée du participant
+    "ALTER TABLE access_codes ADD COLUMN locale TEXT;",
+    // 2 : journal d'audit des actions sensibles
+    "
+    CREATE TABLE audit_log (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+        actor_id BIGINT,
+        actor_name TEXT,
+        action TEXT NOT NULL,
+        target_id BIGINT,
+        before TEXT,
+        after TEXT,
+        detail TEXT
+    );
+    CREATE INDEX idx_audit_log_created_at ON audit_log(created_at);
+    ",
+    // 3 : archivage des participants (suppression logique)
+    "ALTER TABLE access_codes ADD COLUMN archived_at TEXT;",
+    // 4 : fenêtre de validité, nombre de connexions maximal et suivi des connexions
+    "
+    ALTER TABLE access_codes ADD COLUMN valid_from TEXT;
+    ALTER TABLE access_codes ADD COLUMN valid_until TEXT;
+    ALTER TABLE access_codes ADD COLUMN max_uses BIGINT;
+    ALTER TABLE access_codes ADD COLUMN last_login_at TEXT;
+    ALTER TABLE access_codes ADD COLUMN login_count BIGINT NOT NULL DEFAULT 0;
+    ",
+    // 5 : index de la liste des participants (recherche, tri, jointure par année)
+    "
+    CREATE INDEX idx_access_codes_name ON access_codes(lower(name));
+    CREATE INDEX idx_access_codes_last_login_at ON access_codes(last_login_at);
+    CREATE INDEX idx_draws_year_giver ON draws(year, giver_id);
+    ",
+    // 6 : préférences de notification choisies par le participant
+    "
+    ALTER TABLE access_codes ADD COLUMN notify_draw BOOLEAN NOT NULL DEFAULT FALSE;
+    ALTER TABLE access_codes ADD COLUMN notify_reminders BOOLEAN NOT NULL DEFAULT FALSE;
+    ",
+    // 7 : liens d'invitation et inscriptions en attente de validation
+    "
+    CREATE TABLE invitations (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        token TEXT NOT NULL UNIQUE,
+        label TEXT NOT NULL,
+        max_signups BIGINT,
+        expires_at TEXT,
+        revoked_at TEXT,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+    );
+    CREATE TABLE signups (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        invitation_id BIGINT NOT NULL REFERENCES invitations(id),
+        name TEXT NOT NULL,
+        code TEXT NOT NULL UNIQUE,
+        status TEXT NOT NULL DEFAULT 'pending',
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+        decided_at TEXT
+    );
+    CREATE INDEX idx_signups_status ON signups(status);
+    ",
+    // 8 : double authentification TOTP (RFC 6238) et codes de secours
+    "
+    CREATE TABLE totp_credentials (
+        access_code_id BIGINT PRIMARY KEY REFERENCES access_codes(id),
+        secret BYTEA NOT NULL,
+        confirmed_at TEXT,
+        last_step BIGINT,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+    );
+    CREATE TABLE totp_recovery_codes (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        access_code_id BIGINT NOT NULL REFERENCES access_codes(id),
+        code_hash TEXT NOT NULL,
+        used_at TEXT
+    );
+    CREATE INDEX idx_totp_recovery_codes_access_code ON totp_recovery_codes(access_code_id);
+    ",
+    // 9 : jetons d'API de l'admin (empreinte SHA-256, droits séparés par des espaces)
+    "
+    CREATE TABLE api_tokens (
+        id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+        name TEXT NOT NULL,
+        token_hash TEXT NOT NULL UNIQUE,
+        scopes TEXT NOT NULL,
+        created_by BIGINT NOT NULL REFERENCES access_codes(id),
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+        expires_at TEXT,
+        revoked_at TEXT,
+        last_used_at TEXT
+    );
+    ",
+    // 10 : participant créé par la validation d'une inscription
+    "
+    ALTER TABLE signups ADD COLUMN access_code_id BIGINT REFERENCES access_codes(id);
+    UPDATE signups SET access_code_id = (SELECT id FROM access_codes WHERE access_codes.code = signups.code)
+        WHERE status = 'approved';
+    ",
+    // 11 : affectation scellée d'une année et son engagement (tirage vérifiable)
+    "
+    CREATE TABLE draw_commitments (
+        year INTEGER PRIMARY KEY,
+        commitment TEXT NOT NULL,
+        salt TEXT NOT NULL,
+        assignment TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'),
+        revealed_at TEXT
+    );
+    ",
+    // 12 : codes TOTP erronés comptés côté serveur
+    "
+    ALTER TABLE totp_credentials ADD COLUMN failed_attempts BIGINT NOT NULL DEFAULT 0;
+    ALTER TABLE totp_credentials ADD COLUMN last_failure_at BIGINT;
+    ",
+];
+
+#[cfg(feature = "postgres")]
+pub fn init_pg_db(pool: &PgPool) {
+    blocking(|| {
+        let mut conn = pool.get().expect("Get connection from pool");
+        conn.batch_execute(PG_SCHEMA).expect("Failed to create tables");
+
+        let mut tx = conn.transaction().expect("Failed to start migration");
+        // une seule instance applique les migrations à la fois
+        tx.batch_execute("LOCK TABLE schema_version IN EXCLUSIVE MODE").expect("Failed to lock schema version");
+        let version: i32 = tx
+            .query_opt("SELECT version FROM schema_version", &[])
+            .expect("Failed to read schema version")
+            .map(|row| row.get(0))
+            .unwrap_or(0);
+        for (index, migration) in PG_MIGRATIONS.iter().enumerate().skip(version as usize) {
+            tx.batch_execute(migration)
+                .unwrap_or_else(|e| panic!("Failed to apply migration {}: {e}", index + 1));
+        }
+        if (version as usize) < PG_MIGRATIONS.len() {
+            tx.execute("DELETE FROM schema_version", &[]).expect("Failed to write schema version");
+            tx.execute("INSERT INTO schema_version (version) VALUES ($1)", &[&(PG_MIGRATIONS.len() as i32)])
+                .expect("Failed to write schema version");
+        }
+        tx.commit().expect("Failed to commit migrations");
+    });
+}
@@ -0,0 +1,167 @@
+//! Évènements diffusés en direct (Server-Sent Events) pendant la soirée du
+//! tirage : l'admin et les participants suivent l'avancement sans recharger
+//! la page.
+
+use rocket::tokio::sync::broadcast;
+use serde::Serialize;
+
+use crate::models::access_code::AccessCode;
+use crate::state::AppState;
+
+/// Évènements conservés pour un abonné lent avant qu'il en perde.
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveEventKind {
+    /// Avancement courant, envoyé à l'ouverture du flux.
+    Progress,
+    /// Un participant a tiré son destinataire (jamais nommé).
+    Drew,
+    /// Un participant a rejoint l'échange (code créé, activé ou inscription validée).
+    Joined,
+    /// Un participant a été désactivé ou archivé.
+    Deactivated,
+    /// L'admin annonce la révélation des tirages.
+    Reveal,
+}
+
+impl LiveEventKind {
+    /// Nom de l'évènement SSE (`event:`), écouté par `addEventListener`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LiveEventKind::Progress => "progress",
+            LiveEventKind::Drew => "drew",
+            LiveEventKind::Joined => "joined",
+            LiveEventKind::Deactivated => "deactivated",
+            LiveEventKind::Reveal => "reveal",
+        }
+    }
+}
+
+/// Avancement du tirage d'une année.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DrawProgress {
+    pub drawn: i64,
+    /// Participants actifs, non archivés.
+    pub participants: i64,
+}
+
+/// Évènement en cours de construction, puis diffusé.
+///
+/// ```ignore
+/// LiveEvent::new(LiveEventKind::Drew, year)
+///     .subject(&user.access_code)
+///     .publish(&state)
+///     .await;
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveEvent {
+    #[serde(skip)]
+    pub kind: LiveEventKind,
+    pub access_code_id: Option<i64>,
+    pub name: Option<String>,
+    pub year: i32,
+    pub progress: DrawProgress,
+}
+
+impl LiveEvent {
+    pub fn new(kind: LiveEventKind, year: i32) -> Self {
+        Self { kind, access_code_id: None, name: None, year, progress: DrawProgress::default() }
+    }
+
+    /// Participant concerné.
+    pub fn subject(mut self, access_code: &AccessCode) -> Self {
+        self.access_code_id = Some(access_code.id);
+        self.name = Some(access_code.name.clone());
+        self
+    }
+
+    /// Version envoyée aux participants : qui a été désactivé ne les regarde pas.
+    pub fn for_participant(&self) -> Self {
+        match self.kind {
+            LiveEventKind::Deactivated => Self { access_code_id: None, name: None, ..self.clone() },
+            _ => self.clone(),
+        }
+    }
+
+    /// Calcule l'avancement de l'année puis diffuse l'évènement. Sans abonné,
+    /// rien n'est lu en base ; un échec est journalisé sans interrompre la
+    /// requête.
+    pub async fn publish(mut self, state: &AppState) {
+        if !state.events.has_subscribers() {
+            return;
+        }
+        match progress(state, self.year).await {
+            Some(progress) => {
+                self.progress = progress;
+                state.events.send(self);
+            }
+            None => log::warn!("live event dropped kind={}", self.kind.as_str()),
+        }
+    }
+}
+
+/// Avancement du tirage de `year`, `None` si la base ne répond pas.
+pub async fn progress(state: &AppState, year: i32) -> Option<DrawProgress> {
+    let result = state.repos.run(move |repos| {
+        let participants = repos.access_codes.list(false)?.iter().filter(|code| code.active).count() as i64;
+        let drawn = repos.draws.count_by_year()?
+            .into_iter()
+            .find_map(|(draw_year, count)| (draw_year == year).then_some(count))
+            .unwrap_or(0);
+        Ok(DrawProgress { drawn, participants })
+    }).await;
+    result.map_err(|e| log::warn!("draw progress unavailable error={e:?}")).ok()
+}
+
+/// Canal de diffusion partagé par les flux SSE ouverts (dans `AppState`).
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(CHANNEL_CAPACITY).0 }
+    }
+}
+
+impl EventBus {
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    fn send(&self, event: LiveEvent) {
+        // les abonnés ont pu se déconnecter entre-temps
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_participant_view() {
+        let alice: AccessCode = serde_json::from_value(serde_json::json!({
+            "id": 2, "name": "Alice", "code": "ALICE", "active": true,
+        })).unwrap();
+        let drew = LiveEvent::new(LiveEventKind::Drew, 2025).subject(&alice).for_participant();
+        assert_eq!((drew.access_code_id, drew.name.as_deref()), (Some(2), Some("Alice")));
+        let deactivated = LiveEvent::new(LiveEventKind::Deactivated, 2025).subject(&alice).for_participant();
+        assert_eq!((deactivated.access_code_id, deactivated.name), (None, None));
+
+        // le type d'évènement est porté par le champ SSE `event:`, pas par les données
+        let json = serde_json::to_value(&drew).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "access_code_id": 2,
+            "name": "Alice",
+            "year": 2025,
+            "progress": { "drawn": 0, "participants": 0 },
+        }));
+    }
+}
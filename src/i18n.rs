@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use rocket::Request;
+use rocket::request::{FromRequest, Outcome};
+use rocket::fairing::Fairing;
+use rocket_dyn_templates::{Template, tera};
+
+use crate::auth::session_access_code;
+
+pub const DEFAULT_LOCALE: &str = "fr";
+pub const SUPPORTED_LOCALES: &[&str] = &["fr", "en", "de"];
+
+type Catalog = HashMap<String, String>;
+
+// catalogues embarqués dans le binaire : `locales/<langue>.json`
+static CATALOGS: LazyLock<HashMap<&'static str, Catalog>> = LazyLock::new(|| {
+    let sources = [
+        ("fr", include_str!("../locales/fr.json")),
+        ("en", include_str!("../locales/en.json")),
+        ("de", include_str!("../locales/de.json")),
+    ];
+    sources
+        .into_iter()
+        .map(|(lang, json)| {
+            let catalog: Catalog = serde_json::from_str(json)
+                .unwrap_or_else(|e| panic!("Invalid catalog locales/{lang}.json: {e}"));
+            (lang, catalog)
+        })
+        .collect()
+});
+
+/// Langue supportée correspondant à `tag` (`en-US` → `en`).
+pub fn supported(tag: &str) -> Option<&'static str> {
+    let primary = tag.split(['-', '_']).next()?.trim().to_ascii_lowercase();
+    SUPPORTED_LOCALES.iter().copied().find(|lang| *lang == primary)
+}
+
+/// Traduit `key` ; repli sur le français, puis sur la clé elle-même.
+pub fn translate(lang: &str, key: &str) -> String {
+    [lang, DEFAULT_LOCALE]
+        .iter()
+        .find_map(|lang| CATALOGS.get(lang).and_then(|catalog| catalog.get(key)))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Première langue supportée d'un en-tête `Accept-Language`, par qualité décroissante.
+pub fn from_accept_language(header: &str) -> Option<&'static str> {
+    let mut candidates: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.trim().split(';');
+            let tag = pieces.next()?.trim();
+            let quality = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag, quality))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    candidates.into_iter().find_map(|(tag, _)| supported(tag))
+}
+
+/// Langue de la requête : préférence de l'access code connecté, sinon
+/// `Accept-Language`, sinon le français.
+pub struct Locale(pub &'static str);
+
+impl Locale {
+    pub fn t(&self, key: &str) -> String {
+        translate(self.0, key)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Locale {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let preference = session_access_code(request)
+            .await
+            .and_then(|access_code| access_code.locale.as_deref().and_then(supported));
+
+        let locale = preference
+            .or_else(|| request.headers().get_one("Accept-Language").and_then(from_accept_language))
+            .unwrap_or(DEFAULT_LOCALE);
+        Outcome::Success(Locale(locale))
+    }
+}
+
+/// Fonction Tera `t(key="…", lang=lang)`.
+pub fn tera_translate(args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+    let key = args
+        .get("key")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| tera::Error::msg("t() requires a `key` argument"))?;
+    let lang = args.get("lang").and_then(|v| v.as_str()).unwrap_or(DEFAULT_LOCALE);
+    Ok(tera::Value::String(translate(lang, key)))
+}
+
+/// Fairing des templates Tera, avec la fonction `t` enregistrée.
+pub fn template_fairing() -> impl Fairing {
+    Template::custom(|engines| {
+        engines.tera.register_function("t", tera_translate);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(from_accept_language("de-DE,de;q=0.9,en;q=0.8"), Some("de"));
+        assert_eq!(from_accept_language("es;q=1.0, en-GB;q=0.5, fr;q=0.7"), Some("fr"));
+        assert_eq!(from_accept_language("es, it"), None);
+    }
+
+    #[test]
+    fn test_translate_fallbacks() {
+        assert_eq!(translate("en", "api.code_updated"), "Code updated successfully");
+        assert_eq!(translate("xx", "api.code_updated"), "Code mis à jour avec succès");
+        assert_eq!(translate("en", "no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn test_catalogs_have_same_keys() {
+        let reference = &CATALOGS[DEFAULT_LOCALE];
+        for lang in SUPPORTED_LOCALES {
+            let catalog = &CATALOGS[lang];
+            for key in reference.keys() {
+                assert!(catalog.contains_key(key), "missing key {key} in locales/{lang}.json");
+            }
+            assert_eq!(catalog.len(), reference.len(), "extra keys in locales/{lang}.json");
+        }
+    }
+}
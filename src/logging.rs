@@ -0,0 +1,49 @@
+use std::time::Instant;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Data, Request, Response};
+
+/// Instant de réception de la requête, conservé dans le cache local.
+struct RequestStart(Instant);
+
+/// Journalise chaque requête sous forme `clé=valeur`, avec un niveau
+/// dépendant du statut (INFO, WARN pour 4xx, ERROR pour 5xx).
+pub struct RequestLogger;
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request logger",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let start = request.local_cache(|| RequestStart(Instant::now()));
+        let status = response.status();
+        let route = request.route().map(|route| route.uri.to_string()).unwrap_or_else(|| "-".to_string());
+        let client = request.client_ip().map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string());
+
+        let level = match status.code {
+            500.. => log::Level::Error,
+            400..=499 => log::Level::Warn,
+            _ => log::Level::Info,
+        };
+        log::log!(
+            target: "winter_card::request",
+            level,
+            "request method={} path={} route={} status={} duration_ms={:.1} client={}",
+            request.method(),
+            request.uri().path(),
+            route,
+            status.code,
+            start.0.elapsed().as_secs_f64() * 1000.0,
+            client,
+        );
+    }
+}
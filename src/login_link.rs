@@ -0,0 +1,133 @@
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkError {
+    Malformed,
+    BadSignature,
+    Expired,
+}
+
+impl LinkError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            LinkError::Malformed => "malformed",
+            LinkError::BadSignature => "bad signature",
+            LinkError::Expired => "expired",
+        }
+    }
+}
+
+/// Contenu signé d'un lien de connexion à usage unique.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkClaims {
+    pub link_id: i64,
+    pub access_code_id: i64,
+    /// Expiration (timestamp Unix, en secondes).
+    pub expires_at: i64,
+}
+
+/// Signe et vérifie les jetons des liens de connexion (HMAC-SHA256).
+///
+/// Le jeton a la forme `<payload>.<signature>` (base64url) ; l'usage unique
+/// est garanti par la table `login_links`, pas par le jeton lui-même.
+pub struct LinkSigner {
+    key: Vec<u8>,
+}
+
+impl LinkSigner {
+    /// Utilise la clé configurée (base64) ou, à défaut, une clé aléatoire :
+    /// les liens émis sont alors invalidés au redémarrage.
+    pub fn from_config(key: Option<&str>) -> Self {
+        if let Some(key) = key {
+            match STANDARD.decode(key.trim()) {
+                Ok(bytes) if bytes.len() >= 32 => return Self { key: bytes },
+                _ => log::warn!("login_link_key invalide : utilisation d'une clé aléatoire"),
+            }
+        }
+        let mut key = vec![0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self { key }
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    pub fn sign(&self, claims: &LinkClaims) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}:{}",
+            claims.link_id, claims.access_code_id, claims.expires_at
+        ));
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    pub fn verify(&self, token: &str, now: i64) -> Result<LinkClaims, LinkError> {
+        let (payload, signature) = token.split_once('.').ok_or(LinkError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| LinkError::Malformed)?;
+        self.mac(payload).verify_slice(&signature).map_err(|_| LinkError::BadSignature)?;
+
+        let decoded = URL_SAFE_NO_PAD.decode(payload).map_err(|_| LinkError::Malformed)?;
+        let decoded = String::from_utf8(decoded).map_err(|_| LinkError::Malformed)?;
+        let parts: Vec<i64> = decoded
+            .split(':')
+            .map(|part| part.parse::<i64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| LinkError::Malformed)?;
+        let [link_id, access_code_id, expires_at] = parts[..] else {
+            return Err(LinkError::Malformed);
+        };
+
+        if expires_at <= now {
+            return Err(LinkError::Expired);
+        }
+        Ok(LinkClaims { link_id, access_code_id, expires_at })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_766_534_400;
+
+    fn claims() -> LinkClaims {
+        LinkClaims { link_id: 7, access_code_id: 2, expires_at: NOW + 3600 }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = LinkSigner::from_config(None);
+        let token = signer.sign(&claims());
+        assert_eq!(signer.verify(&token, NOW), Ok(claims()));
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let signer = LinkSigner::from_config(None);
+        let token = signer.sign(&claims());
+        assert_eq!(signer.verify(&token, NOW + 3600), Err(LinkError::Expired));
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        let signer = LinkSigner::from_config(None);
+        let token = signer.sign(&claims());
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged_payload = URL_SAFE_NO_PAD.encode(format!("7:1:{}", NOW + 3600));
+        let forged = format!("{forged_payload}.{signature}");
+        assert_eq!(signer.verify(&forged, NOW), Err(LinkError::BadSignature));
+
+        let other = LinkSigner::from_config(None);
+        assert_eq!(other.verify(&token, NOW), Err(LinkError::BadSignature));
+    }
+}
@@ -1,24 +1,48 @@
 #[macro_use] extern crate rocket;
 
 mod routes;
+mod audit;
 mod auth;
+mod backup;
+mod card;
+mod clock;
+mod commitment;
+mod config;
+mod crypto;
+mod csrf;
 mod db;
+mod events;
+mod i18n;
+mod logging;
+mod login_link;
+mod metrics;
+mod openapi;
+mod repo;
 mod state;
+mod stats;
+mod totp;
 mod models;
 
-use crate::db::{init_db, init_pool};
+use crate::config::AppConfig;
+use crate::db::Database;
 use crate::state::AppState;
-use rocket_dyn_templates::Template;
 
 #[launch]
 fn rocket() -> _ {
-    let pool = init_pool("winter_card.db");
-    init_db(&pool);
-    
-    let state = AppState::new(pool);
+    let rocket = rocket::build();
+    let config: AppConfig = rocket.figment().extract().expect("Invalid configuration");
 
-    rocket::build()
+    let db = Database::connect(&config);
+    db.init();
+    let state = AppState::new(db, config);
+
+    rocket
         .manage(state)
         .mount("/", routes::routes())
-        .attach(Template::fairing())
-}
\ No newline at end of file
+        .mount("/", openapi::routes())
+        .attach(i18n::template_fairing())
+        .attach(csrf::CsrfFairing)
+        .attach(logging::RequestLogger)
+        .attach(metrics::MetricsFairing)
+        .attach(backup::BackupScheduler)
+}
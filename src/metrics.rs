@@ -0,0 +1,217 @@
+//! Métriques au format texte de Prometheus : requêtes par route (nombre et
+//! durée), connexions réussies ou refusées. L'occupation du pool et les
+//! tirages par année sont lus au moment de la collecte.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Data, Request, Response};
+
+use crate::db::PoolUsage;
+use crate::state::AppState;
+
+/// Bornes (en secondes) de l'histogramme des durées de requête.
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Mode de connexion compté par `record_login`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoginMethod {
+    Code,
+    Link,
+    /// Second facteur (code TOTP ou code de secours) refusé ; une connexion
+    /// aboutie est comptée sous le mode de sa première étape.
+    Totp,
+}
+
+impl LoginMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LoginMethod::Code => "code",
+            LoginMethod::Link => "link",
+            LoginMethod::Totp => "totp",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "code" => Some(LoginMethod::Code),
+            "link" => Some(LoginMethod::Link),
+            "totp" => Some(LoginMethod::Totp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    /// (méthode, route, statut) -> nombre de requêtes
+    requests: BTreeMap<(String, String, u16), u64>,
+    /// (méthode, route) -> durées
+    latencies: BTreeMap<(String, String), Histogram>,
+    /// (mode, réussie) -> nombre de connexions
+    logins: BTreeMap<(LoginMethod, bool), u64>,
+}
+
+/// Compteurs de l'application depuis son démarrage.
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    fn counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Compte une requête servie par `route` (modèle d'URI de la route, pour
+    /// borner le nombre de séries).
+    pub fn record_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        let mut counters = self.counters();
+        *counters.requests.entry((method.to_string(), route.to_string(), status)).or_default() += 1;
+        counters.latencies.entry((method.to_string(), route.to_string())).or_default().observe(seconds);
+    }
+
+    pub fn record_login(&self, method: LoginMethod, success: bool) {
+        *self.counters().logins.entry((method, success)).or_default() += 1;
+    }
+
+    /// Exposition au format texte de Prometheus.
+    pub fn render(&self, pool: PoolUsage, draws_by_year: &[(i32, i64)]) -> String {
+        let counters = self.counters();
+        let mut out = String::new();
+
+        out.push_str("# HELP winter_card_http_requests_total Requêtes HTTP servies, par route et statut.\n");
+        out.push_str("# TYPE winter_card_http_requests_total counter\n");
+        for ((method, route, status), count) in &counters.requests {
+            let _ = writeln!(
+                out,
+                "winter_card_http_requests_total{{method=\"{method}\",route=\"{}\",status=\"{status}\"}} {count}",
+                escape(route),
+            );
+        }
+
+        out.push_str("# HELP winter_card_http_request_duration_seconds Durée de traitement des requêtes, par route.\n");
+        out.push_str("# TYPE winter_card_http_request_duration_seconds histogram\n");
+        for ((method, route), histogram) in &counters.latencies {
+            let labels = format!("method=\"{method}\",route=\"{}\"", escape(route));
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(out, "winter_card_http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}");
+            }
+            let _ = writeln!(out, "winter_card_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "winter_card_http_request_duration_seconds_sum{{{labels}}} {}", histogram.sum);
+            let _ = writeln!(out, "winter_card_http_request_duration_seconds_count{{{labels}}} {}", histogram.count);
+        }
+
+        out.push_str("# HELP winter_card_logins_total Tentatives de connexion, par mode et résultat.\n");
+        out.push_str("# TYPE winter_card_logins_total counter\n");
+        for method in [LoginMethod::Code, LoginMethod::Link, LoginMethod::Totp] {
+            for success in [true, false] {
+                let count = counters.logins.get(&(method, success)).copied().unwrap_or(0);
+                let result = if success { "success" } else { "failure" };
+                let _ = writeln!(out, "winter_card_logins_total{{method=\"{}\",result=\"{result}\"}} {count}", method.as_str());
+            }
+        }
+
+        out.push_str("# HELP winter_card_db_pool_connections Connexions ouvertes du pool.\n");
+        out.push_str("# TYPE winter_card_db_pool_connections gauge\n");
+        let _ = writeln!(out, "winter_card_db_pool_connections {}", pool.connections);
+        out.push_str("# HELP winter_card_db_pool_idle_connections Connexions inactives du pool.\n");
+        out.push_str("# TYPE winter_card_db_pool_idle_connections gauge\n");
+        let _ = writeln!(out, "winter_card_db_pool_idle_connections {}", pool.idle);
+        out.push_str("# HELP winter_card_db_pool_max_connections Taille maximale du pool.\n");
+        out.push_str("# TYPE winter_card_db_pool_max_connections gauge\n");
+        let _ = writeln!(out, "winter_card_db_pool_max_connections {}", pool.max_size);
+
+        out.push_str("# HELP winter_card_draws Tirages enregistrés, par année.\n");
+        out.push_str("# TYPE winter_card_draws gauge\n");
+        for (year, count) in draws_by_year {
+            let _ = writeln!(out, "winter_card_draws{{year=\"{year}\"}} {count}");
+        }
+        out
+    }
+}
+
+// valeur d'étiquette : `\`, `"` et retours à la ligne sont échappés
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Instant de réception de la requête, conservé dans le cache local.
+struct RequestStart(Instant);
+
+/// Alimente `Metrics` (dans `AppState`) à chaque réponse.
+pub struct MetricsFairing;
+
+#[rocket::async_trait]
+impl Fairing for MetricsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(state) = request.rocket().state::<AppState>() else { return };
+        let start = request.local_cache(|| RequestStart(Instant::now()));
+        // les requêtes sans route (404) sont regroupées
+        let route = request.route().map(|route| route.uri.to_string()).unwrap_or_else(|| "unmatched".to_string());
+        state.metrics.record_request(
+            request.method().as_str(),
+            &route,
+            response.status().code,
+            start.0.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record_request("GET", "/admin/api/codes?<params..>", 200, 0.02);
+        metrics.record_request("GET", "/admin/api/codes?<params..>", 200, 3.0);
+        metrics.record_login(LoginMethod::Code, false);
+
+        let pool = PoolUsage { connections: 2, idle: 1, max_size: 10 };
+        let text = metrics.render(pool, &[(2024, 6), (2025, 3)]);
+
+        let labels = "method=\"GET\",route=\"/admin/api/codes?<params..>\"";
+        assert!(text.contains(&format!("winter_card_http_requests_total{{{labels},status=\"200\"}} 2")));
+        assert!(text.contains(&format!("winter_card_http_request_duration_seconds_bucket{{{labels},le=\"0.025\"}} 1")));
+        assert!(text.contains(&format!("winter_card_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 2")));
+        assert!(text.contains("winter_card_logins_total{method=\"code\",result=\"failure\"} 1"));
+        assert!(text.contains("winter_card_logins_total{method=\"link\",result=\"success\"} 0"));
+        assert!(text.contains("winter_card_db_pool_max_connections 10"));
+        assert!(text.contains("winter_card_draws{year=\"2025\"} 3"));
+    }
+}
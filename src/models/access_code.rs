@@ -1,9 +1,167 @@
+use std::sync::LazyLock;
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Format des dates stockées en base (UTC) : l'ordre des chaînes est celui
+/// des dates, ce qui permet de les comparer directement.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// nom affiché : commence par une lettre ou un chiffre, 50 caractères au plus
+static NAME_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[\p{L}\p{N}][\p{L}\p{N} .'’-]{0,49}$").expect("valid name pattern"));
+// code généré par `generate_code` : groupes de 4 caractères sans ambiguïté (ni 0/O ni 1/I)
+static CODE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-HJ-NP-Z2-9]{4}(-[A-HJ-NP-Z2-9]{4}){2}$").expect("valid code pattern"));
+
+/// Alphabet des codes générés.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct AccessCode {
     pub id: i64,
     pub name: String,
     pub code: String,
     pub active: bool,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Date d'archivage : un participant archivé ne peut plus se connecter ni
+    /// être tiré au sort, mais reste présent dans l'historique des tirages.
+    #[serde(default)]
+    pub archived_at: Option<String>,
+    /// Fenêtre de validité du code (bornes incluses, `TIMESTAMP_FORMAT`).
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    /// Nombre maximal de connexions ; illimité si absent.
+    #[serde(default)]
+    pub max_uses: Option<i64>,
+    #[serde(default)]
+    pub last_login_at: Option<String>,
+    #[serde(default)]
+    pub login_count: i64,
+    /// Préférences de notification (ouverture du tirage, rappels avant
+    /// l'évènement), choisies par le participant sur son profil.
+    #[serde(default)]
+    pub notify_draw: bool,
+    #[serde(default)]
+    pub notify_reminders: bool,
+}
+
+impl AccessCode {
+    /// Le code est-il dans sa fenêtre de validité à `now` (`TIMESTAMP_FORMAT`) ?
+    pub fn is_valid_at(&self, now: &str) -> bool {
+        self.valid_from.as_deref().is_none_or(|from| from <= now)
+            && self.valid_until.as_deref().is_none_or(|until| now <= until)
+    }
+
+    /// Toutes les connexions autorisées ont-elles été utilisées ?
+    pub fn uses_exhausted(&self) -> bool {
+        self.max_uses.is_some_and(|max| self.login_count >= max)
+    }
+}
+
+/// Nom affiché acceptable (déjà débarrassé des espaces de début et de fin) ?
+pub fn is_valid_name(name: &str) -> bool {
+    NAME_PATTERN.is_match(name)
+}
+
+/// Nouveau code d'accès aléatoire, de la forme `ABCD-EFGH-JKLM`.
+pub fn generate_code() -> String {
+    let groups: Vec<String> = (0..3)
+        .map(|_| {
+            (0..4)
+                .map(|_| CODE_ALPHABET[(OsRng.next_u32() as usize) % CODE_ALPHABET.len()] as char)
+                .collect()
+        })
+        .collect();
+    let code = groups.join("-");
+    debug_assert!(CODE_PATTERN.is_match(&code));
+    code
+}
+
+/// Normalise une date saisie (RFC 3339, `2025-12-01T18:00`, `2025-12-01`...)
+/// au format `TIMESTAMP_FORMAT`. Sans fuseau, la date est lue en UTC.
+pub fn parse_timestamp(input: &str) -> Option<String> {
+    let input = input.trim();
+    let parsed = DateTime::parse_from_rfc3339(input)
+        .map(|date| date.with_timezone(&Utc).naive_utc())
+        .ok()
+        .or_else(|| {
+            ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        })
+        .or_else(|| NaiveDate::parse_from_str(input, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))?;
+    Some(parsed.format(TIMESTAMP_FORMAT).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code() -> AccessCode {
+        AccessCode {
+            id: 2,
+            name: "Alice".to_string(),
+            code: "ALICE".to_string(),
+            active: true,
+            locale: None,
+            archived_at: None,
+            valid_from: None,
+            valid_until: None,
+            max_uses: None,
+            last_login_at: None,
+            login_count: 0,
+            notify_draw: false,
+            notify_reminders: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("2025-12-01T18:00").as_deref(), Some("2025-12-01 18:00:00"));
+        assert_eq!(parse_timestamp("2025-12-01").as_deref(), Some("2025-12-01 00:00:00"));
+        assert_eq!(parse_timestamp("2025-12-01T18:00:00+01:00").as_deref(), Some("2025-12-01 17:00:00"));
+        assert_eq!(parse_timestamp("2025-12-01T17:00:00.000Z").as_deref(), Some("2025-12-01 17:00:00"));
+        assert_eq!(parse_timestamp("1er décembre"), None);
+    }
+
+    #[test]
+    fn test_name_and_generated_code() {
+        for name in ["Alice", "Jean-Éric", "Zoë O'Neil", "M. Dupont 2"] {
+            assert!(is_valid_name(name), "{name}");
+        }
+        for name in ["", " Alice", "<script>", "Alice\nBob", &"a".repeat(51)] {
+            assert!(!is_valid_name(name), "{name}");
+        }
+
+        let code = generate_code();
+        assert!(CODE_PATTERN.is_match(&code), "{code}");
+        assert_ne!(code, generate_code());
+    }
+
+    #[test]
+    fn test_validity_window_and_uses() {
+        let window = AccessCode {
+            valid_from: Some("2025-12-01 00:00:00".to_string()),
+            valid_until: Some("2025-12-24 23:59:59".to_string()),
+            ..code()
+        };
+        assert!(!window.is_valid_at("2025-11-30 23:59:59"));
+        assert!(window.is_valid_at("2025-12-01 00:00:00"));
+        assert!(window.is_valid_at("2025-12-24 23:59:59"));
+        assert!(!window.is_valid_at("2025-12-25 00:00:00"));
+        assert!(code().is_valid_at("2000-01-01 00:00:00"));
+
+        let limited = AccessCode { max_uses: Some(2), login_count: 1, ..code() };
+        assert!(!limited.uses_exhausted());
+        assert!(AccessCode { login_count: 2, ..limited }.uses_exhausted());
+        assert!(!code().uses_exhausted());
+    }
+}
@@ -0,0 +1,143 @@
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+/// Préfixe des jetons, pour les reconnaître dans un fichier de configuration
+/// ou un outil de détection de secrets.
+pub const TOKEN_PREFIX: &str = "wc_";
+
+/// Droit accordé à un jeton d'API sur l'API d'administration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub enum ApiScope {
+    /// Lire la liste des participants.
+    #[serde(rename = "participants:read")]
+    ReadParticipants,
+    /// Créer, modifier, archiver les participants et générer leurs liens de connexion.
+    #[serde(rename = "participants:write")]
+    WriteParticipants,
+    /// Tirer pour un participant ou annuler les tirages d'une année.
+    #[serde(rename = "draws:run")]
+    RunDraw,
+    /// Exporter les participants et les tirages, sans codes d'accès ni adresses.
+    #[serde(rename = "export")]
+    Export,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 4] = [ApiScope::ReadParticipants, ApiScope::WriteParticipants, ApiScope::RunDraw, ApiScope::Export];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::ReadParticipants => "participants:read",
+            ApiScope::WriteParticipants => "participants:write",
+            ApiScope::RunDraw => "draws:run",
+            ApiScope::Export => "export",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ApiScope::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+}
+
+/// Droits stockés en base : séparés par des espaces, comme le `scope` d'OAuth 2.
+pub fn join_scopes(scopes: &[ApiScope]) -> String {
+    scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+/// Les droits inconnus (retirés depuis la création du jeton) sont ignorés.
+pub fn split_scopes(value: &str) -> Vec<ApiScope> {
+    value.split_whitespace().filter_map(ApiScope::parse).collect()
+}
+
+/// Jeton d'API nommé, créé par l'admin pour ses scripts. Seule l'empreinte
+/// du jeton est conservée : il n'est affiché qu'à sa création.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    /// Access code (admin) au nom duquel le jeton agit.
+    pub created_by: i64,
+    pub created_at: String,
+    /// Fin de validité, au format `TIMESTAMP_FORMAT` (UTC).
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub revoked_at: Option<String>,
+    #[serde(default)]
+    pub last_used_at: Option<String>,
+}
+
+impl ApiToken {
+    /// Le jeton est-il utilisable à `now` (`TIMESTAMP_FORMAT`) ?
+    pub fn is_valid_at(&self, now: &str) -> bool {
+        self.revoked_at.is_none() && self.expires_at.as_deref().is_none_or(|until| now < until)
+    }
+
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// Nouveau jeton : préfixe `wc_` et 32 octets aléatoires en base64url.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("{TOKEN_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Empreinte stockée d'un jeton (SHA-256, en hexadécimal). Le jeton est
+/// assez long pour qu'un hachage lent soit inutile.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token() -> ApiToken {
+        ApiToken {
+            id: 1,
+            name: "ci".to_string(),
+            scopes: vec![ApiScope::ReadParticipants],
+            created_by: 1,
+            created_at: "2025-11-01 10:00:00".to_string(),
+            expires_at: None,
+            revoked_at: None,
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn test_is_valid_at() {
+        let now = "2025-12-01 12:00:00";
+        assert!(token().is_valid_at(now));
+        assert!(!ApiToken { revoked_at: Some("2025-11-02 10:00:00".into()), ..token() }.is_valid_at(now));
+        assert!(ApiToken { expires_at: Some("2025-12-01 12:00:01".into()), ..token() }.is_valid_at(now));
+        assert!(!ApiToken { expires_at: Some(now.into()), ..token() }.is_valid_at(now));
+        assert!(token().allows(ApiScope::ReadParticipants));
+        assert!(!token().allows(ApiScope::WriteParticipants));
+    }
+
+    #[test]
+    fn test_scopes_and_token() {
+        for scope in ApiScope::ALL {
+            assert_eq!(ApiScope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(split_scopes(&join_scopes(&ApiScope::ALL)), ApiScope::ALL);
+        assert_eq!(split_scopes("export  unknown participants:read"), vec![ApiScope::Export, ApiScope::ReadParticipants]);
+
+        let token = generate_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 43);
+        assert_ne!(token, generate_token());
+        assert_eq!(hash_token(&token).len(), 64);
+        assert_ne!(hash_token(&token), hash_token(&generate_token()));
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Paire donateur → destinataire d'une affectation scellée.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct Pairing {
+    pub giver_id: i64,
+    pub receiver_id: i64,
+}
+
+/// Affectation complète d'une année, fixée avant le premier tirage et
+/// engagée par `commitment` (voir `crate::commitment`). Le sel et
+/// l'affectation ne sont publiés qu'après la révélation.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DrawCommitment {
+    pub year: i32,
+    /// Racine de Merkle des paires salées, en hexadécimal.
+    pub commitment: String,
+    /// Sel secret (32 octets en hexadécimal) jusqu'à la révélation.
+    pub salt: String,
+    /// Paires triées par donateur.
+    pub assignment: Vec<Pairing>,
+    pub created_at: String,
+    #[serde(default)]
+    pub revealed_at: Option<String>,
+}
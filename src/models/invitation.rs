@@ -0,0 +1,130 @@
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Lien d'inscription partagé par l'organisateur d'un échange.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Invitation {
+    pub id: i64,
+    /// Jeton aléatoire de l'URL `/invite/<token>`.
+    pub token: String,
+    /// Nom de l'échange, affiché sur la page d'inscription.
+    pub label: String,
+    /// Nombre maximal d'inscriptions (les inscriptions refusées ne comptent pas).
+    #[serde(default)]
+    pub max_signups: Option<i64>,
+    /// Fin des inscriptions, au format `TIMESTAMP_FORMAT` (UTC).
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+    /// Inscriptions en attente ou acceptées.
+    #[serde(default)]
+    pub signup_count: i64,
+}
+
+impl Invitation {
+    /// Le lien accepte-t-il encore des inscriptions à `now` (`TIMESTAMP_FORMAT`) ?
+    pub fn is_open_at(&self, now: &str) -> bool {
+        self.revoked_at.is_none()
+            && self.expires_at.as_deref().is_none_or(|until| now <= until)
+            && self.max_signups.is_none_or(|max| self.signup_count < max)
+    }
+}
+
+/// État d'une inscription dans la file de validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SignupStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl SignupStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SignupStatus::Pending => "pending",
+            SignupStatus::Approved => "approved",
+            SignupStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(SignupStatus::Pending),
+            "approved" => Some(SignupStatus::Approved),
+            "rejected" => Some(SignupStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// Inscription faite depuis un lien d'invitation. Le code est généré à
+/// l'inscription mais ne devient un access code qu'une fois accepté.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Signup {
+    pub id: i64,
+    pub invitation_id: i64,
+    pub invitation_label: String,
+    pub name: String,
+    pub code: String,
+    pub status: SignupStatus,
+    pub created_at: String,
+    #[serde(default)]
+    pub decided_at: Option<String>,
+    /// Participant créé à la validation (absent s'il a été purgé depuis).
+    #[serde(default)]
+    pub access_code_id: Option<i64>,
+}
+
+/// Nouveau jeton d'invitation : 18 octets aléatoires en base64url (24 caractères).
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 18];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invitation() -> Invitation {
+        Invitation {
+            id: 1,
+            token: generate_token(),
+            label: "Noël 2025".to_string(),
+            max_signups: None,
+            expires_at: None,
+            revoked_at: None,
+            created_at: "2025-11-01 10:00:00".to_string(),
+            signup_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_is_open_at() {
+        let now = "2025-12-01 12:00:00";
+        assert!(invitation().is_open_at(now));
+        assert!(!Invitation { revoked_at: Some("2025-11-02 10:00:00".into()), ..invitation() }.is_open_at(now));
+        assert!(Invitation { expires_at: Some(now.into()), ..invitation() }.is_open_at(now));
+        assert!(!Invitation { expires_at: Some("2025-12-01 11:59:59".into()), ..invitation() }.is_open_at(now));
+        assert!(Invitation { max_signups: Some(2), signup_count: 1, ..invitation() }.is_open_at(now));
+        assert!(!Invitation { max_signups: Some(2), signup_count: 2, ..invitation() }.is_open_at(now));
+    }
+
+    #[test]
+    fn test_token_and_status() {
+        let token = generate_token();
+        assert_eq!(token.len(), 24);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_ne!(token, generate_token());
+        for status in [SignupStatus::Pending, SignupStatus::Approved, SignupStatus::Rejected] {
+            assert_eq!(SignupStatus::parse(status.as_str()), Some(status));
+        }
+    }
+}
@@ -1,2 +1,5 @@
 pub mod access_code;
+pub mod api_token;
+pub mod commitment;
 pub mod draw;
+pub mod invitation;
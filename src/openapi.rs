@@ -0,0 +1,206 @@
+//! Description OpenAPI 3 de l'API JSON, générée depuis les routes et les
+//! types (`#[utoipa::path]`, `ToSchema`), et interface Swagger UI embarquée.
+
+use rocket::Route;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::auth::SESSION_COOKIE;
+use crate::csrf::CSRF_HEADER;
+use crate::routes::{admin, api_tokens, commitments, events, invitations, profile, secret_santa, stats, totp};
+
+/// Chemin de la description OpenAPI.
+pub const SPEC_PATH: &str = "/api/openapi.json";
+/// Chemin de l'interface de documentation.
+pub const DOCS_PATH: &str = "/api/docs";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Winter Card",
+        description = "API JSON de l'application : gestion des participants par l'admin et tirage du Secret Santa.\n\n\
+            L'API s'appuie sur la session du navigateur (cookie posé par `POST /login`). \
+            Les requêtes qui modifient l'état doivent aussi envoyer l'en-tête `X-CSRF-Token`.\n\n\
+            Les scripts s'authentifient par un jeton d'API (`Authorization: Bearer …`, sans en-tête CSRF), \
+            limité à ses droits : `participants:read`, `participants:write`, `draws:run` ou `export` \
+            (participants et tirages, sans codes d'accès ni adresses).",
+    ),
+    paths(
+        admin::list_access_codes,
+        admin::create_access_code,
+        admin::update_access_code,
+        admin::delete_access_code,
+        admin::restore_access_code,
+        admin::purge_access_code,
+        admin::create_login_link,
+        admin::reset_draws,
+        admin::draw_on_behalf,
+        admin::reveal_draws,
+        admin::integrity_check,
+        admin::integrity_repair,
+        admin::download_backup,
+        admin::export_data,
+        admin::restore_backup,
+        secret_santa::save_address,
+        secret_santa::delete_address,
+        secret_santa::perform_draw,
+        profile::update_profile,
+        profile::rotate_code,
+        invitations::list_invitations,
+        invitations::create_invitation,
+        invitations::revoke_invitation,
+        invitations::list_signups,
+        invitations::approve_signup,
+        invitations::reject_signup,
+        totp::totp_status,
+        totp::begin_enrollment,
+        totp::confirm_enrollment,
+        totp::reset_totp,
+        api_tokens::list_api_tokens,
+        api_tokens::create_api_token,
+        api_tokens::revoke_api_token,
+        events::admin_events,
+        events::participant_events,
+        stats::get_stats,
+        commitments::seal_draw,
+        commitments::get_commitment,
+        commitments::get_proof,
+    ),
+    modifiers(&SecurityAddon),
+    tags(
+        (name = "participants", description = "Access codes des participants"),
+        (name = "draws", description = "Tirages gérés par l'admin"),
+        (name = "maintenance", description = "Intégrité, sauvegarde et restauration de la base"),
+        (name = "secret_santa", description = "Espace du participant connecté"),
+        (name = "profile", description = "Profil du participant connecté"),
+        (name = "invitations", description = "Liens d'invitation et file des inscriptions à valider"),
+        (name = "totp", description = "Double authentification TOTP des admins"),
+        (name = "api_tokens", description = "Jetons d'API des scripts d'administration"),
+        (name = "events", description = "Avancement du tirage en direct (Server-Sent Events)"),
+        (name = "stats", description = "Statistiques de l'échange pour l'organisateur"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// Instantané SQLite, envoyé ou reçu tel quel (`application/vnd.sqlite3`).
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+#[allow(dead_code)] // ne sert qu'à la description OpenAPI
+pub struct SqliteSnapshot(Vec<u8>);
+
+/// Schémas de sécurité : cookie de session, jeton CSRF et jeton d'API.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                SESSION_COOKIE,
+                "Cookie privé posé par `POST /login`",
+            ))),
+        );
+        components.add_security_scheme(
+            "csrf",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                CSRF_HEADER,
+                "Jeton CSRF du cookie `csrf`, exposé aux pages par `csrf_token`",
+            ))),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("Jeton d'API créé depuis `/admin/tokens`, limité à ses droits"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Routes servant la description OpenAPI et Swagger UI.
+pub fn routes() -> Vec<Route> {
+    SwaggerUi::new(format!("{DOCS_PATH}/<_..>"))
+        .url(SPEC_PATH, ApiDoc::openapi())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Status;
+    use rocket::local::blocking::Client;
+    use std::collections::BTreeSet;
+
+    /// Routes JSON documentées : tout ce qui est sous un préfixe `/api`.
+    fn is_api_route(path: &str) -> bool {
+        path.starts_with("/admin/api/") || path.starts_with("/secret_santa/api/") || path.starts_with("/profile/api")
+    }
+
+    // `/admin/api/codes/<id>?<params..>` -> `/admin/api/codes/{id}`
+    fn openapi_path(route: &Route) -> String {
+        route.uri.path().split('/')
+            .map(|segment| match segment.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+                Some(name) => format!("{{{name}}}"),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    #[test]
+    fn test_spec_matches_mounted_routes() {
+        let mounted: BTreeSet<(String, String)> = crate::routes::routes().iter()
+            .filter(|route| is_api_route(route.uri.path()))
+            .map(|route| (route.method.as_str().to_lowercase(), openapi_path(route)))
+            .collect();
+
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let documented: BTreeSet<(String, String)> = spec["paths"].as_object().unwrap().iter()
+            .flat_map(|(path, item)| {
+                item.as_object().unwrap().keys().map(move |method| (method.clone(), path.clone()))
+            })
+            .collect();
+
+        assert_eq!(mounted, documented, "la description OpenAPI ne correspond plus aux routes de l'API");
+    }
+
+    #[test]
+    fn test_spec_describes_parameters_and_bodies() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let paths = &spec["paths"];
+
+        let list_params: Vec<&str> = paths["/admin/api/codes"]["get"]["parameters"].as_array().unwrap().iter()
+            .map(|param| param["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(list_params, ["year", "q", "active", "drawn", "archived", "sort", "page", "per_page"]);
+        assert_eq!(paths["/admin/api/codes/{id}"]["patch"]["parameters"][0]["in"], "path");
+        assert_eq!(
+            paths["/admin/api/codes"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/CreateAccessCode"
+        );
+
+        let schemas = &spec["components"]["schemas"];
+        for schema in ["AccessCode", "AccessCodeWithDraw", "AccessCodePage", "CreateAccessCode", "DrawResult", "RestoreReport"] {
+            assert!(schemas[schema].is_object(), "schéma manquant : {schema}");
+        }
+        assert!(spec["components"]["securitySchemes"]["csrf"].is_object());
+    }
+
+    #[test]
+    fn test_serves_spec_and_docs() {
+        let client = Client::tracked(rocket::build().mount("/", routes())).expect("valid rocket instance");
+
+        let response = client.get(SPEC_PATH).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let spec: serde_json::Value = response.into_json().unwrap();
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+
+        let response = client.get(format!("{DOCS_PATH}/")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("swagger"));
+    }
+}
@@ -0,0 +1,681 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use super::{
+    pick_receiver, sealed_receiver, AccessCodeRepo, AddressRepo, ApiTokenRepo, AuditRepo, CodeQuery, CodeSort, CodeSortField,
+    CodeWithDraw, CommitmentRepo, DrawRepo, InvitationRepo, LoginLinkRepo, NewAccessCode, NewApiToken,
+    NewCommitment, NewInvitation, Profile, RepoError, RepoResult, StoredAddress, TotpCredential, TotpRepo,
+};
+use crate::audit::{AuditEntry, AuditEvent};
+use crate::models::access_code::{AccessCode, TIMESTAMP_FORMAT};
+use crate::models::api_token::ApiToken;
+use crate::models::commitment::DrawCommitment;
+use crate::models::draw::Draw;
+use crate::models::invitation::{Invitation, Signup, SignupStatus};
+use crate::totp;
+
+#[derive(Default)]
+struct Data {
+    access_codes: Vec<AccessCode>,
+    draws: Vec<Draw>,
+    audit: Vec<AuditEntry>,
+    addresses: HashMap<i64, StoredAddress>,
+    login_links: Vec<MemoryLoginLink>,
+    invitations: Vec<Invitation>,
+    signups: Vec<Signup>,
+    totp: HashMap<i64, TotpCredential>,
+    recovery_codes: Vec<MemoryRecoveryCode>,
+    api_tokens: Vec<MemoryApiToken>,
+    commitments: BTreeMap<i32, DrawCommitment>,
+}
+
+struct MemoryRecoveryCode {
+    access_code_id: i64,
+    code_hash: String,
+    used: bool,
+}
+
+struct MemoryApiToken {
+    token: ApiToken,
+    token_hash: String,
+}
+
+struct MemoryLoginLink {
+    id: i64,
+    access_code_id: i64,
+    expires_at: i64,
+    used: bool,
+}
+
+/// Repositories en mémoire, sans SQLite, pour tester les handlers.
+///
+/// Contient l'admin par défaut (id 1, `Winter2025`) comme `db::init_db`.
+pub struct MemoryRepo {
+    data: Mutex<Data>,
+}
+
+impl Default for MemoryRepo {
+    fn default() -> Self {
+        let admin = AccessCode {
+            id: 1,
+            name: "Admin".to_string(),
+            code: "Winter2025".to_string(),
+            active: true,
+            locale: None,
+            archived_at: None,
+            valid_from: None,
+            valid_until: None,
+            max_uses: None,
+            last_login_at: None,
+            login_count: 0,
+            notify_draw: false,
+            notify_reminders: false,
+        };
+        Self { data: Mutex::new(Data { access_codes: vec![admin], ..Default::default() }) }
+    }
+}
+
+impl MemoryRepo {
+    fn data(&self) -> std::sync::MutexGuard<'_, Data> {
+        self.data.lock().expect("memory repo poisoned")
+    }
+
+    fn find_where(&self, predicate: impl Fn(&AccessCode) -> bool) -> RepoResult<AccessCode> {
+        self.data().access_codes.iter().find(|c| predicate(c)).cloned().ok_or(RepoError::NotFound)
+    }
+}
+
+impl AccessCodeRepo for MemoryRepo {
+    fn list(&self, include_archived: bool) -> RepoResult<Vec<AccessCode>> {
+        Ok(self.data().access_codes.iter().filter(|c| include_archived || c.archived_at.is_none()).cloned().collect())
+    }
+
+    fn search(&self, query: &CodeQuery) -> RepoResult<(Vec<CodeWithDraw>, i64)> {
+        let data = self.data();
+        let needle = query.name.as_ref().map(|name| name.to_lowercase());
+        let mut codes: Vec<CodeWithDraw> = data.access_codes.iter()
+            .filter(|c| query.include_archived || c.archived_at.is_none())
+            .filter(|c| needle.as_ref().is_none_or(|needle| c.name.to_lowercase().contains(needle)))
+            .filter(|c| query.active.is_none_or(|active| c.active == active))
+            .map(|c| CodeWithDraw {
+                access_code: c.clone(),
+                receiver_id: data.draws.iter()
+                    .find(|d| d.giver_id == c.id && d.year == query.year)
+                    .map(|d| d.receiver_id),
+            })
+            .filter(|c| query.drawn.is_none_or(|drawn| c.receiver_id.is_some() == drawn))
+            .collect();
+        codes.sort_by(|a, b| compare_codes(query.sort, &a.access_code, &b.access_code));
+        let total = codes.len() as i64;
+        let page = codes.into_iter().skip(query.offset() as usize).take(query.per_page as usize).collect();
+        Ok((page, total))
+    }
+
+    fn list_active(&self) -> RepoResult<Vec<AccessCode>> {
+        let mut codes: Vec<AccessCode> = self.data().access_codes.iter().filter(|c| is_available(c)).cloned().collect();
+        codes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(codes)
+    }
+
+    fn find(&self, id: i64) -> RepoResult<AccessCode> {
+        self.find_where(|c| c.id == id)
+    }
+
+    fn find_active(&self, id: i64) -> RepoResult<AccessCode> {
+        self.find_where(|c| c.id == id && is_available(c))
+    }
+
+    fn find_active_by_code(&self, code: &str) -> RepoResult<AccessCode> {
+        self.find_where(|c| c.code == code && is_available(c))
+    }
+
+    fn create(&self, new: &NewAccessCode) -> RepoResult<AccessCode> {
+        let mut data = self.data();
+        if data.access_codes.iter().any(|c| c.code == new.code) {
+            return Err(RepoError::Conflict);
+        }
+        let id = data.access_codes.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        let created = AccessCode {
+            id,
+            name: new.name.clone(),
+            code: new.code.clone(),
+            active: new.active,
+            locale: None,
+            archived_at: None,
+            valid_from: new.valid_from.clone(),
+            valid_until: new.valid_until.clone(),
+            max_uses: new.max_uses,
+            last_login_at: None,
+            login_count: 0,
+            notify_draw: false,
+            notify_reminders: false,
+        };
+        data.access_codes.push(created.clone());
+        Ok(created)
+    }
+
+    fn update(&self, access_code: &AccessCode) -> RepoResult<()> {
+        let mut data = self.data();
+        if data.access_codes.iter().any(|c| c.code == access_code.code && c.id != access_code.id) {
+            return Err(RepoError::Conflict);
+        }
+        let existing = data.access_codes.iter_mut().find(|c| c.id == access_code.id).ok_or(RepoError::NotFound)?;
+        existing.name = access_code.name.clone();
+        existing.code = access_code.code.clone();
+        existing.active = access_code.active;
+        existing.valid_from = access_code.valid_from.clone();
+        existing.valid_until = access_code.valid_until.clone();
+        existing.max_uses = access_code.max_uses;
+        Ok(())
+    }
+
+    fn record_login(&self, id: i64, now: &str) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        if existing.uses_exhausted() {
+            return Err(RepoError::Conflict);
+        }
+        existing.login_count += 1;
+        existing.last_login_at = Some(now.to_string());
+        Ok(())
+    }
+
+    fn archive(&self, id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        if existing.archived_at.is_some() {
+            return Err(RepoError::Conflict);
+        }
+        existing.archived_at = Some(now_timestamp());
+        Ok(())
+    }
+
+    fn restore(&self, id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        existing.archived_at.take().ok_or(RepoError::Conflict)?;
+        Ok(())
+    }
+
+    fn purge(&self, id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        if existing.archived_at.is_none() {
+            return Err(RepoError::Conflict);
+        }
+        data.access_codes.retain(|c| c.id != id);
+        data.draws.retain(|d| d.giver_id != id && d.receiver_id != id);
+        data.addresses.remove(&id);
+        data.login_links.retain(|l| l.access_code_id != id);
+        data.totp.remove(&id);
+        data.recovery_codes.retain(|r| r.access_code_id != id);
+        data.api_tokens.retain(|t| t.token.created_by != id);
+        for signup in data.signups.iter_mut().filter(|s| s.access_code_id == Some(id)) {
+            signup.access_code_id = None;
+        }
+        Ok(())
+    }
+
+    fn set_locale(&self, id: i64, locale: &str) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        existing.locale = Some(locale.to_string());
+        Ok(())
+    }
+
+    fn update_profile(&self, id: i64, profile: &Profile) -> RepoResult<()> {
+        let mut data = self.data();
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        existing.name = profile.name.clone();
+        existing.locale = profile.locale.clone();
+        existing.notify_draw = profile.notify_draw;
+        existing.notify_reminders = profile.notify_reminders;
+        Ok(())
+    }
+
+    fn set_code(&self, id: i64, code: &str) -> RepoResult<()> {
+        let mut data = self.data();
+        if data.access_codes.iter().any(|c| c.code == code && c.id != id) {
+            return Err(RepoError::Conflict);
+        }
+        let existing = data.access_codes.iter_mut().find(|c| c.id == id).ok_or(RepoError::NotFound)?;
+        existing.code = code.to_string();
+        Ok(())
+    }
+}
+
+// Équivalent du `CURRENT_TIMESTAMP` posé par les bases SQL
+fn now_timestamp() -> String {
+    chrono::Utc::now().format(TIMESTAMP_FORMAT).to_string()
+}
+
+// Même ordre que les `ORDER BY` des implémentations SQL
+fn compare_codes(sort: CodeSort, a: &AccessCode, b: &AccessCode) -> Ordering {
+    let ordering = match sort.field {
+        CodeSortField::Id => a.id.cmp(&b.id),
+        CodeSortField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        CodeSortField::LastLogin => (a.last_login_at.is_some(), &a.last_login_at)
+            .cmp(&(b.last_login_at.is_some(), &b.last_login_at)),
+    };
+    let ordering = if sort.descending { ordering.reverse() } else { ordering };
+    ordering.then(a.id.cmp(&b.id))
+}
+
+// Actif et non archivé : peut se connecter et être tiré au sort
+fn is_available(code: &AccessCode) -> bool {
+    code.active && code.archived_at.is_none()
+}
+
+fn is_orphan(data: &Data, draw: &Draw) -> bool {
+    let exists = |id| data.access_codes.iter().any(|c| c.id == id);
+    !exists(draw.giver_id) || !exists(draw.receiver_id)
+}
+
+impl DrawRepo for MemoryRepo {
+    fn list(&self) -> RepoResult<Vec<Draw>> {
+        Ok(self.data().draws.clone())
+    }
+
+    fn has_drawn(&self, giver_id: i64, year: i32) -> RepoResult<bool> {
+        Ok(self.data().draws.iter().any(|d| d.giver_id == giver_id && d.year == year))
+    }
+
+    fn receiver_of(&self, giver_id: i64, year: i32) -> RepoResult<Option<AccessCode>> {
+        let data = self.data();
+        let receiver = data.draws.iter()
+            .find(|d| d.giver_id == giver_id && d.year == year)
+            .and_then(|d| data.access_codes.iter().find(|c| c.id == d.receiver_id))
+            .cloned();
+        Ok(receiver)
+    }
+
+    fn draw(&self, giver_id: i64, year: i32) -> RepoResult<AccessCode> {
+        let mut data = self.data();
+        if data.draws.iter().any(|d| d.giver_id == giver_id && d.year == year) {
+            return Err(RepoError::Conflict);
+        }
+        let receiver = match data.commitments.get(&year) {
+            Some(commitment) => {
+                let receiver_id = sealed_receiver(&commitment.assignment, giver_id)?;
+                data.access_codes.iter().find(|c| c.id == receiver_id).cloned().ok_or(RepoError::NotFound)?
+            }
+            None => {
+                let candidates: Vec<AccessCode> = data.access_codes.iter()
+                    .filter(|c| c.id != giver_id && is_available(c))
+                    .filter(|c| !data.draws.iter().any(|d| d.receiver_id == c.id && d.year == year))
+                    .cloned()
+                    .collect();
+                pick_receiver(candidates).ok_or(RepoError::NotFound)?
+            }
+        };
+        let id = data.draws.len() as i64 + 1;
+        data.draws.push(Draw {
+            id,
+            giver_id,
+            receiver_id: receiver.id,
+            year,
+            created_at: now_timestamp(),
+        });
+        Ok(receiver)
+    }
+
+    fn reset(&self, year: i32) -> RepoResult<usize> {
+        let mut data = self.data();
+        if data.commitments.contains_key(&year) {
+            return Err(RepoError::Conflict);
+        }
+        let before = data.draws.len();
+        data.draws.retain(|d| d.year != year);
+        Ok(before - data.draws.len())
+    }
+
+    fn orphans(&self) -> RepoResult<Vec<Draw>> {
+        let data = self.data();
+        Ok(data.draws.iter().filter(|d| is_orphan(&data, d)).cloned().collect())
+    }
+
+    fn remove_orphans(&self) -> RepoResult<Vec<Draw>> {
+        let mut data = self.data();
+        let (orphans, kept) = data.draws.iter().cloned().partition(|d| is_orphan(&data, d));
+        data.draws = kept;
+        Ok(orphans)
+    }
+
+    fn count_by_year(&self) -> RepoResult<Vec<(i32, i64)>> {
+        let mut counts = BTreeMap::new();
+        for draw in &self.data().draws {
+            *counts.entry(draw.year).or_insert(0) += 1;
+        }
+        Ok(counts.into_iter().collect())
+    }
+}
+
+impl AuditRepo for MemoryRepo {
+    fn record(&self, event: &AuditEvent) {
+        let mut data = self.data();
+        let id = data.audit.len() as i64 + 1;
+        data.audit.push(AuditEntry {
+            id,
+            created_at: now_timestamp(),
+            actor_id: event.actor_id,
+            actor_name: event.actor_name.clone(),
+            action: event.action.as_str().to_string(),
+            target_id: event.target_id,
+            before: event.before.as_ref().map(|v| v.to_string()),
+            after: event.after.as_ref().map(|v| v.to_string()),
+            detail: event.detail.clone(),
+        });
+    }
+
+    fn list(&self, page: u32, per_page: u32) -> RepoResult<(Vec<AuditEntry>, i64)> {
+        let data = self.data();
+        let offset = (page.max(1) - 1) as usize * per_page as usize;
+        let entries = data.audit.iter().rev().skip(offset).take(per_page as usize).cloned().collect();
+        Ok((entries, data.audit.len() as i64))
+    }
+}
+
+impl AddressRepo for MemoryRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<StoredAddress>> {
+        Ok(self.data().addresses.get(&access_code_id).cloned())
+    }
+
+    fn save(&self, access_code_id: i64, address: &StoredAddress) -> RepoResult<()> {
+        self.data().addresses.insert(access_code_id, address.clone());
+        Ok(())
+    }
+
+    fn delete(&self, access_code_id: i64) -> RepoResult<()> {
+        self.data().addresses.remove(&access_code_id);
+        Ok(())
+    }
+}
+
+impl LoginLinkRepo for MemoryRepo {
+    fn create(&self, access_code_id: i64, expires_at: i64) -> RepoResult<i64> {
+        let mut data = self.data();
+        let id = data.login_links.len() as i64 + 1;
+        data.login_links.push(MemoryLoginLink { id, access_code_id, expires_at, used: false });
+        Ok(id)
+    }
+
+    fn consume(&self, link_id: i64, access_code_id: i64, now: i64) -> RepoResult<bool> {
+        let mut data = self.data();
+        let link = data.login_links.iter_mut()
+            .find(|l| l.id == link_id && l.access_code_id == access_code_id && !l.used && l.expires_at > now);
+        Ok(link.map(|l| l.used = true).is_some())
+    }
+}
+
+impl Data {
+    // lien avec son nombre d'inscriptions (hors refusées) à jour
+    fn invitation(&self, predicate: impl Fn(&Invitation) -> bool) -> RepoResult<Invitation> {
+        let invitation = self.invitations.iter().find(|i| predicate(i)).ok_or(RepoError::NotFound)?;
+        let signup_count = self.signups.iter()
+            .filter(|s| s.invitation_id == invitation.id && s.status != SignupStatus::Rejected)
+            .count() as i64;
+        Ok(Invitation { signup_count, ..invitation.clone() })
+    }
+
+    // passe une inscription en attente dans l'état `status`
+    fn decide_signup(&mut self, id: i64, status: SignupStatus) -> RepoResult<Signup> {
+        let signup = self.signups.iter_mut().find(|s| s.id == id).ok_or(RepoError::NotFound)?;
+        if signup.status != SignupStatus::Pending {
+            return Err(RepoError::Conflict);
+        }
+        signup.status = status;
+        signup.decided_at = Some(now_timestamp());
+        Ok(signup.clone())
+    }
+}
+
+impl InvitationRepo for MemoryRepo {
+    fn list(&self) -> RepoResult<Vec<Invitation>> {
+        let data = self.data();
+        data.invitations.iter().rev().map(|i| data.invitation(|other| other.id == i.id)).collect()
+    }
+
+    fn create(&self, new: &NewInvitation) -> RepoResult<Invitation> {
+        let mut data = self.data();
+        if data.invitations.iter().any(|i| i.token == new.token) {
+            return Err(RepoError::Conflict);
+        }
+        let invitation = Invitation {
+            id: data.invitations.len() as i64 + 1,
+            token: new.token.clone(),
+            label: new.label.clone(),
+            max_signups: new.max_signups,
+            expires_at: new.expires_at.clone(),
+            revoked_at: None,
+            created_at: now_timestamp(),
+            signup_count: 0,
+        };
+        data.invitations.push(invitation.clone());
+        Ok(invitation)
+    }
+
+    fn find_by_token(&self, token: &str) -> RepoResult<Invitation> {
+        self.data().invitation(|i| i.token == token)
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        let invitation = data.invitations.iter_mut().find(|i| i.id == id).ok_or(RepoError::NotFound)?;
+        if invitation.revoked_at.is_some() {
+            return Err(RepoError::Conflict);
+        }
+        invitation.revoked_at = Some(now_timestamp());
+        Ok(())
+    }
+
+    fn sign_up(&self, invitation_id: i64, name: &str, code: &str, now: &str) -> RepoResult<Option<Signup>> {
+        let mut data = self.data();
+        let invitation = data.invitation(|i| i.id == invitation_id)?;
+        if !invitation.is_open_at(now) {
+            return Ok(None);
+        }
+        if data.access_codes.iter().any(|c| c.code == code) || data.signups.iter().any(|s| s.code == code) {
+            return Err(RepoError::Conflict);
+        }
+        let signup = Signup {
+            id: data.signups.len() as i64 + 1,
+            invitation_id,
+            invitation_label: invitation.label,
+            name: name.to_string(),
+            code: code.to_string(),
+            status: SignupStatus::Pending,
+            created_at: now_timestamp(),
+            decided_at: None,
+            access_code_id: None,
+        };
+        data.signups.push(signup.clone());
+        Ok(Some(signup))
+    }
+
+    fn signups(&self, status: Option<SignupStatus>) -> RepoResult<Vec<Signup>> {
+        Ok(self.data().signups.iter().filter(|s| status.is_none_or(|status| s.status == status)).cloned().collect())
+    }
+
+    fn approve(&self, signup_id: i64) -> RepoResult<AccessCode> {
+        let signup = {
+            let data = self.data();
+            let signup = data.signups.iter().find(|s| s.id == signup_id).ok_or(RepoError::NotFound)?;
+            if signup.status != SignupStatus::Pending {
+                return Err(RepoError::Conflict);
+            }
+            signup.clone()
+        };
+        let new = NewAccessCode { name: signup.name, code: signup.code, active: true, ..Default::default() };
+        let access_code = AccessCodeRepo::create(self, &new)?;
+        let mut data = self.data();
+        data.decide_signup(signup_id, SignupStatus::Approved)?;
+        if let Some(signup) = data.signups.iter_mut().find(|s| s.id == signup_id) {
+            signup.access_code_id = Some(access_code.id);
+        }
+        Ok(access_code)
+    }
+
+    fn reject(&self, signup_id: i64) -> RepoResult<Signup> {
+        self.data().decide_signup(signup_id, SignupStatus::Rejected)
+    }
+}
+
+impl TotpRepo for MemoryRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<TotpCredential>> {
+        Ok(self.data().totp.get(&access_code_id).cloned())
+    }
+
+    fn begin_enrollment(&self, access_code_id: i64, secret: &[u8]) -> RepoResult<()> {
+        let mut data = self.data();
+        if data.totp.get(&access_code_id).is_some_and(TotpCredential::is_enabled) {
+            return Err(RepoError::Conflict);
+        }
+        let credential = TotpCredential {
+            secret: secret.to_vec(),
+            confirmed_at: None,
+            last_step: None,
+            failed_attempts: 0,
+            last_failure_at: None,
+        };
+        data.totp.insert(access_code_id, credential);
+        Ok(())
+    }
+
+    fn confirm(&self, access_code_id: i64, step: i64, recovery_hashes: &[String]) -> RepoResult<()> {
+        let mut data = self.data();
+        let credential = data.totp.get_mut(&access_code_id)
+            .filter(|credential| !credential.is_enabled())
+            .ok_or(RepoError::Conflict)?;
+        credential.confirmed_at = Some(now_timestamp());
+        credential.last_step = Some(step);
+        data.recovery_codes.retain(|r| r.access_code_id != access_code_id);
+        data.recovery_codes.extend(recovery_hashes.iter().map(|hash| MemoryRecoveryCode {
+            access_code_id,
+            code_hash: hash.clone(),
+            used: false,
+        }));
+        Ok(())
+    }
+
+    fn record_step(&self, access_code_id: i64, step: i64) -> RepoResult<bool> {
+        let mut data = self.data();
+        let credential = data.totp.get_mut(&access_code_id)
+            .filter(|credential| credential.is_enabled() && credential.last_step.is_none_or(|last| last < step));
+        Ok(credential.map(|credential| credential.last_step = Some(step)).is_some())
+    }
+
+    fn use_recovery_code(&self, access_code_id: i64, code_hash: &str) -> RepoResult<bool> {
+        let mut data = self.data();
+        let code = data.recovery_codes.iter_mut()
+            .find(|r| r.access_code_id == access_code_id && r.code_hash == code_hash && !r.used);
+        Ok(code.map(|r| r.used = true).is_some())
+    }
+
+    fn record_failure(&self, access_code_id: i64, now: i64) -> RepoResult<i64> {
+        let mut data = self.data();
+        let credential = data.totp.get_mut(&access_code_id).ok_or(RepoError::NotFound)?;
+        let recent = credential.last_failure_at.is_some_and(|at| at > now - totp::LOCKOUT_SECS);
+        credential.failed_attempts = if recent { credential.failed_attempts + 1 } else { 1 };
+        credential.last_failure_at = Some(now);
+        Ok(credential.failed_attempts)
+    }
+
+    fn clear_failures(&self, access_code_id: i64) -> RepoResult<()> {
+        if let Some(credential) = self.data().totp.get_mut(&access_code_id) {
+            credential.failed_attempts = 0;
+            credential.last_failure_at = None;
+        }
+        Ok(())
+    }
+
+    fn remaining_recovery_codes(&self, access_code_id: i64) -> RepoResult<i64> {
+        let data = self.data();
+        Ok(data.recovery_codes.iter().filter(|r| r.access_code_id == access_code_id && !r.used).count() as i64)
+    }
+
+    fn reset(&self, access_code_id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        data.totp.remove(&access_code_id).ok_or(RepoError::NotFound)?;
+        data.recovery_codes.retain(|r| r.access_code_id != access_code_id);
+        Ok(())
+    }
+}
+
+impl ApiTokenRepo for MemoryRepo {
+    fn list(&self) -> RepoResult<Vec<ApiToken>> {
+        Ok(self.data().api_tokens.iter().rev().map(|t| t.token.clone()).collect())
+    }
+
+    fn create(&self, new: &NewApiToken) -> RepoResult<ApiToken> {
+        let mut data = self.data();
+        if data.api_tokens.iter().any(|t| t.token_hash == new.token_hash) {
+            return Err(RepoError::Conflict);
+        }
+        let token = ApiToken {
+            id: data.api_tokens.len() as i64 + 1,
+            name: new.name.clone(),
+            scopes: new.scopes.clone(),
+            created_by: new.created_by,
+            created_at: now_timestamp(),
+            expires_at: new.expires_at.clone(),
+            revoked_at: None,
+            last_used_at: None,
+        };
+        data.api_tokens.push(MemoryApiToken { token: token.clone(), token_hash: new.token_hash.clone() });
+        Ok(token)
+    }
+
+    fn find_by_hash(&self, token_hash: &str) -> RepoResult<ApiToken> {
+        let data = self.data();
+        data.api_tokens.iter().find(|t| t.token_hash == token_hash).map(|t| t.token.clone()).ok_or(RepoError::NotFound)
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        let mut data = self.data();
+        let token = &mut data.api_tokens.iter_mut().find(|t| t.token.id == id).ok_or(RepoError::NotFound)?.token;
+        if token.revoked_at.is_some() {
+            return Err(RepoError::Conflict);
+        }
+        token.revoked_at = Some(now_timestamp());
+        Ok(())
+    }
+
+    fn touch(&self, id: i64, now: &str) -> RepoResult<()> {
+        let mut data = self.data();
+        if let Some(t) = data.api_tokens.iter_mut().find(|t| t.token.id == id) {
+            t.token.last_used_at = Some(now.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl CommitmentRepo for MemoryRepo {
+    fn find(&self, year: i32) -> RepoResult<Option<DrawCommitment>> {
+        Ok(self.data().commitments.get(&year).cloned())
+    }
+
+    fn create(&self, new: &NewCommitment) -> RepoResult<DrawCommitment> {
+        let mut data = self.data();
+        if data.commitments.contains_key(&new.year) || data.draws.iter().any(|d| d.year == new.year) {
+            return Err(RepoError::Conflict);
+        }
+        let commitment = DrawCommitment {
+            year: new.year,
+            commitment: new.commitment.clone(),
+            salt: new.salt.clone(),
+            assignment: new.assignment.clone(),
+            created_at: now_timestamp(),
+            revealed_at: None,
+        };
+        data.commitments.insert(new.year, commitment.clone());
+        Ok(commitment)
+    }
+
+    fn reveal(&self, year: i32) -> RepoResult<DrawCommitment> {
+        let mut data = self.data();
+        let commitment = data.commitments.get_mut(&year).ok_or(RepoError::NotFound)?;
+        commitment.revealed_at.get_or_insert_with(now_timestamp);
+        Ok(commitment.clone())
+    }
+}
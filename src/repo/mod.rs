@@ -0,0 +1,471 @@
+//! Accès aux données : les handlers passent par ces traits plutôt que par du
+//! SQL en ligne. `sqlite` est l'implémentation par défaut (pool r2d2),
+//! `postgres` l'alternative activée par la feature du même nom ; `memory` est
+//! un substitut en mémoire pour les tests unitaires.
+
+use std::sync::Arc;
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use rocket::http::Status;
+
+use crate::audit::{AuditEntry, AuditEvent};
+use crate::db::SqlitePool;
+use crate::models::access_code::AccessCode;
+use crate::models::api_token::{ApiScope, ApiToken};
+use crate::models::commitment::{DrawCommitment, Pairing};
+use crate::models::draw::Draw;
+use crate::models::invitation::{Invitation, Signup, SignupStatus};
+use crate::totp;
+
+#[cfg(test)]
+pub mod memory;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod sqlite;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoError {
+    NotFound,
+    /// Contrainte d'unicité violée (code déjà utilisé, tirage déjà fait...).
+    Conflict,
+    /// Base momentanément indisponible (verrou, pool épuisé).
+    Unavailable,
+    Backend(String),
+}
+
+pub type RepoResult<T> = Result<T, RepoError>;
+
+impl From<RepoError> for Status {
+    fn from(e: RepoError) -> Self {
+        match e {
+            RepoError::NotFound => Status::NotFound,
+            RepoError::Conflict => Status::Conflict,
+            RepoError::Unavailable => Status::ServiceUnavailable,
+            RepoError::Backend(e) => {
+                log::error!("repository error={e}");
+                Status::InternalServerError
+            }
+        }
+    }
+}
+
+/// Données d'un access code à créer.
+#[derive(Debug, Clone, Default)]
+pub struct NewAccessCode {
+    pub name: String,
+    pub code: String,
+    pub active: bool,
+    pub valid_from: Option<String>,
+    pub valid_until: Option<String>,
+    pub max_uses: Option<i64>,
+}
+
+/// Lien d'invitation à créer.
+#[derive(Debug, Clone, Default)]
+pub struct NewInvitation {
+    pub token: String,
+    pub label: String,
+    pub max_signups: Option<i64>,
+    pub expires_at: Option<String>,
+}
+
+/// Jeton d'API à créer : seule son empreinte est stockée.
+#[derive(Debug, Clone)]
+pub struct NewApiToken {
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_by: i64,
+    pub expires_at: Option<String>,
+}
+
+/// Affectation scellée d'une année, avec son engagement.
+#[derive(Debug, Clone)]
+pub struct NewCommitment {
+    pub year: i32,
+    pub commitment: String,
+    pub salt: String,
+    pub assignment: Vec<Pairing>,
+}
+
+/// Affectation stockée en JSON (`[{"giver_id": …, "receiver_id": …}]`).
+fn assignment_to_json(assignment: &[Pairing]) -> String {
+    serde_json::to_string(assignment).unwrap_or_default()
+}
+
+fn assignment_from_json(json: &str) -> RepoResult<Vec<Pairing>> {
+    serde_json::from_str(json).map_err(|e| RepoError::Backend(format!("invalid assignment: {e}")))
+}
+
+/// Destinataire fixé pour `giver_id` par une affectation scellée ; `NotFound`
+/// s'il ne faisait pas partie des participants au moment du scellement.
+fn sealed_receiver(assignment: &[Pairing], giver_id: i64) -> RepoResult<i64> {
+    assignment.iter()
+        .find(|pairing| pairing.giver_id == giver_id)
+        .map(|pairing| pairing.receiver_id)
+        .ok_or(RepoError::NotFound)
+}
+
+/// Champs qu'un participant modifie lui-même depuis son profil.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub locale: Option<String>,
+    pub notify_draw: bool,
+    pub notify_reminders: bool,
+}
+
+impl From<&AccessCode> for Profile {
+    fn from(access_code: &AccessCode) -> Self {
+        Self {
+            name: access_code.name.clone(),
+            locale: access_code.locale.clone(),
+            notify_draw: access_code.notify_draw,
+            notify_reminders: access_code.notify_reminders,
+        }
+    }
+}
+
+/// Colonne de tri de la liste des participants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeSortField {
+    Id,
+    Name,
+    /// Les participants jamais connectés viennent en premier (ordre croissant).
+    LastLogin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeSort {
+    pub field: CodeSortField,
+    pub descending: bool,
+}
+
+impl CodeSort {
+    /// `id`, `name` ou `last_login`, précédé de `-` pour l'ordre décroissant.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (descending, field) = match value.strip_prefix('-') {
+            Some(field) => (true, field),
+            None => (false, value),
+        };
+        let field = match field {
+            "id" => CodeSortField::Id,
+            "name" => CodeSortField::Name,
+            "last_login" => CodeSortField::LastLogin,
+            _ => return None,
+        };
+        Some(Self { field, descending })
+    }
+}
+
+impl Default for CodeSort {
+    fn default() -> Self {
+        Self { field: CodeSortField::Id, descending: false }
+    }
+}
+
+/// Filtres, tri et page de la liste des participants (`AccessCodeRepo::search`).
+#[derive(Debug, Clone)]
+pub struct CodeQuery {
+    /// Année des tirages joints à la liste.
+    pub year: i32,
+    /// Recherche dans le nom, sans tenir compte de la casse.
+    pub name: Option<String>,
+    pub active: Option<bool>,
+    /// A tiré (ou non) son destinataire pour `year`.
+    pub drawn: Option<bool>,
+    pub include_archived: bool,
+    pub sort: CodeSort,
+    /// Page à partir de 1.
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl CodeQuery {
+    fn offset(&self) -> i64 {
+        (self.page.max(1) - 1) as i64 * self.per_page as i64
+    }
+
+    /// Motif `LIKE` de la recherche, caractères spéciaux échappés par `\`.
+    fn name_pattern(&self) -> Option<String> {
+        self.name.as_ref().map(|name| {
+            let escaped = name.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        })
+    }
+}
+
+/// Participant de la liste, avec le destinataire tiré pour l'année demandée.
+#[derive(Debug, Clone)]
+pub struct CodeWithDraw {
+    pub access_code: AccessCode,
+    pub receiver_id: Option<i64>,
+}
+
+/// Adresse postale chiffrée, telle que stockée (voir `crypto::AddressCipher`).
+#[derive(Debug, Clone)]
+pub struct StoredAddress {
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Secret TOTP d'un access code, tel que stocké. Tant que `confirmed_at` est
+/// vide, l'enrôlement n'est pas terminé et la connexion n'exige aucun code.
+#[derive(Debug, Clone)]
+pub struct TotpCredential {
+    pub secret: Vec<u8>,
+    pub confirmed_at: Option<String>,
+    /// Pas du dernier code accepté (protection contre le rejeu).
+    pub last_step: Option<i64>,
+    /// Codes erronés consécutifs, comptés côté serveur quel que soit le
+    /// navigateur qui les envoie.
+    pub failed_attempts: i64,
+    /// Dernier code erroné (timestamp Unix, en secondes).
+    pub last_failure_at: Option<i64>,
+}
+
+impl TotpCredential {
+    pub fn is_enabled(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+
+    /// Le second facteur est-il bloqué à `now` après trop de codes erronés ?
+    pub fn is_locked(&self, now: i64) -> bool {
+        self.failed_attempts >= totp::MAX_FAILURES
+            && self.last_failure_at.is_some_and(|at| at > now - totp::LOCKOUT_SECS)
+    }
+}
+
+/// Choisit uniformément un destinataire parmi `candidates`.
+///
+/// Le tirage se fait côté application (et non via `RANDOM()` en SQL) pour
+/// que toutes les implémentations aient exactement la même distribution.
+fn pick_receiver(mut candidates: Vec<AccessCode>) -> Option<AccessCode> {
+    if candidates.is_empty() {
+        return None;
+    }
+    let index = (OsRng.next_u64() % candidates.len() as u64) as usize;
+    Some(candidates.swap_remove(index))
+}
+
+/// Les variantes `*_active` ignorent les codes désactivés et les codes archivés.
+pub trait AccessCodeRepo: Send + Sync {
+    /// Tous les codes par id, archivés compris si `include_archived`.
+    fn list(&self, include_archived: bool) -> RepoResult<Vec<AccessCode>>;
+    /// Page de participants filtrés et triés, avec le nombre total de résultats.
+    fn search(&self, query: &CodeQuery) -> RepoResult<(Vec<CodeWithDraw>, i64)>;
+    /// Codes actifs, triés par nom.
+    fn list_active(&self) -> RepoResult<Vec<AccessCode>>;
+    /// Code par id, même archivé (historique des tirages).
+    fn find(&self, id: i64) -> RepoResult<AccessCode>;
+    fn find_active(&self, id: i64) -> RepoResult<AccessCode>;
+    fn find_active_by_code(&self, code: &str) -> RepoResult<AccessCode>;
+    fn create(&self, new: &NewAccessCode) -> RepoResult<AccessCode>;
+    /// Met à jour nom, code, état actif, fenêtre de validité et nombre de
+    /// connexions maximal (ni la langue ni le suivi des connexions).
+    fn update(&self, access_code: &AccessCode) -> RepoResult<()>;
+    /// Compte une connexion et en mémorise la date `now`, de façon atomique :
+    /// `Conflict` si le nombre maximal de connexions est déjà atteint.
+    fn record_login(&self, id: i64, now: &str) -> RepoResult<()>;
+    /// Archive le code ; `Conflict` s'il l'est déjà.
+    fn archive(&self, id: i64) -> RepoResult<()>;
+    /// Désarchive le code ; `Conflict` s'il n'est pas archivé.
+    fn restore(&self, id: i64) -> RepoResult<()>;
+    /// Supprime définitivement un code archivé (`Conflict` sinon) et, en
+    /// cascade : ses tirages comme donateur et comme destinataire, son
+    /// adresse, ses liens de connexion, sa double authentification et ses
+    /// jetons d'API.
+    fn purge(&self, id: i64) -> RepoResult<()>;
+    fn set_locale(&self, id: i64, locale: &str) -> RepoResult<()>;
+    /// Met à jour nom, langue et préférences de notification.
+    fn update_profile(&self, id: i64, profile: &Profile) -> RepoResult<()>;
+    /// Remplace le code d'accès ; `Conflict` s'il est déjà utilisé.
+    fn set_code(&self, id: i64, code: &str) -> RepoResult<()>;
+}
+
+pub trait DrawRepo: Send + Sync {
+    fn list(&self) -> RepoResult<Vec<Draw>>;
+    fn has_drawn(&self, giver_id: i64, year: i32) -> RepoResult<bool>;
+    /// Destinataire tiré par `giver_id` pour `year`, s'il a déjà tiré.
+    fn receiver_of(&self, giver_id: i64, year: i32) -> RepoResult<Option<AccessCode>>;
+    /// Tire au sort et enregistre le destinataire de `giver_id` pour `year`.
+    /// Si l'année est scellée, le destinataire est celui de l'affectation
+    /// engagée.
+    ///
+    /// `Conflict` si le participant a déjà tiré, `NotFound` s'il ne reste
+    /// aucun destinataire disponible (ou s'il ne faisait pas partie de
+    /// l'affectation scellée).
+    fn draw(&self, giver_id: i64, year: i32) -> RepoResult<AccessCode>;
+    /// Annule les tirages de `year` et renvoie le nombre de tirages
+    /// supprimés ; `Conflict` si l'année est scellée, son engagement ayant
+    /// été publié aux participants.
+    fn reset(&self, year: i32) -> RepoResult<usize>;
+    /// Tirages dont le donateur ou le destinataire n'existe plus.
+    fn orphans(&self) -> RepoResult<Vec<Draw>>;
+    /// Supprime les tirages orphelins et les renvoie.
+    fn remove_orphans(&self) -> RepoResult<Vec<Draw>>;
+    /// Nombre de tirages par année, par année croissante.
+    fn count_by_year(&self) -> RepoResult<Vec<(i32, i64)>>;
+}
+
+pub trait AuditRepo: Send + Sync {
+    /// Enregistre l'évènement. Un échec est journalisé sans interrompre la requête.
+    fn record(&self, event: &AuditEvent);
+    /// Page `page` (à partir de 1) du journal, du plus récent au plus ancien,
+    /// avec le nombre total d'entrées.
+    fn list(&self, page: u32, per_page: u32) -> RepoResult<(Vec<AuditEntry>, i64)>;
+}
+
+pub trait AddressRepo: Send + Sync {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<StoredAddress>>;
+    /// Crée ou remplace l'adresse du participant.
+    fn save(&self, access_code_id: i64, address: &StoredAddress) -> RepoResult<()>;
+    fn delete(&self, access_code_id: i64) -> RepoResult<()>;
+}
+
+pub trait LoginLinkRepo: Send + Sync {
+    /// Enregistre un lien (expiration en timestamp Unix) et renvoie son id.
+    fn create(&self, access_code_id: i64, expires_at: i64) -> RepoResult<i64>;
+    /// Consomme le lien de façon atomique : `false` s'il est inconnu, déjà
+    /// utilisé ou expiré à `now`.
+    fn consume(&self, link_id: i64, access_code_id: i64, now: i64) -> RepoResult<bool>;
+}
+
+pub trait InvitationRepo: Send + Sync {
+    /// Liens d'invitation, du plus récent au plus ancien.
+    fn list(&self) -> RepoResult<Vec<Invitation>>;
+    fn create(&self, new: &NewInvitation) -> RepoResult<Invitation>;
+    fn find_by_token(&self, token: &str) -> RepoResult<Invitation>;
+    /// Ferme le lien aux nouvelles inscriptions ; `Conflict` s'il l'est déjà.
+    fn revoke(&self, id: i64) -> RepoResult<()>;
+    /// Enregistre une inscription en attente, de façon atomique : `None` si
+    /// le lien n'accepte plus d'inscription à `now` (révoqué, expiré ou
+    /// complet), `Conflict` si le code est déjà utilisé.
+    fn sign_up(&self, invitation_id: i64, name: &str, code: &str, now: &str) -> RepoResult<Option<Signup>>;
+    /// Inscriptions dans l'état `status` (toutes sans filtre), les plus
+    /// anciennes d'abord.
+    fn signups(&self, status: Option<SignupStatus>) -> RepoResult<Vec<Signup>>;
+    /// Accepte l'inscription et crée l'access code actif correspondant.
+    /// `Conflict` si elle n'est plus en attente ou si son code a été donné
+    /// entre-temps à un autre participant.
+    fn approve(&self, signup_id: i64) -> RepoResult<AccessCode>;
+    /// Refuse l'inscription, qui libère sa place ; `Conflict` si elle n'est
+    /// plus en attente.
+    fn reject(&self, signup_id: i64) -> RepoResult<Signup>;
+}
+
+pub trait TotpRepo: Send + Sync {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<TotpCredential>>;
+    /// Commence (ou recommence) l'enrôlement avec un nouveau secret ;
+    /// `Conflict` si la double authentification est déjà active.
+    fn begin_enrollment(&self, access_code_id: i64, secret: &[u8]) -> RepoResult<()>;
+    /// Active la double authentification une fois un premier code vérifié
+    /// (pas `step`) et remplace les codes de secours par `recovery_hashes`.
+    /// `Conflict` si aucun enrôlement n'est en cours.
+    fn confirm(&self, access_code_id: i64, step: i64, recovery_hashes: &[String]) -> RepoResult<()>;
+    /// Mémorise le pas du code accepté, de façon atomique : `false` si un code
+    /// de ce pas ou d'un pas ultérieur a déjà servi.
+    fn record_step(&self, access_code_id: i64, step: i64) -> RepoResult<bool>;
+    /// Consomme un code de secours (par son empreinte) : `false` s'il est
+    /// inconnu ou déjà utilisé.
+    fn use_recovery_code(&self, access_code_id: i64, code_hash: &str) -> RepoResult<bool>;
+    /// Compte un code erroné saisi à `now` et renvoie le nombre d'échecs
+    /// consécutifs ; le compte repart de 1 après `totp::LOCKOUT_SECS` sans échec.
+    fn record_failure(&self, access_code_id: i64, now: i64) -> RepoResult<i64>;
+    /// Remet à zéro le compte des codes erronés après un second facteur accepté.
+    fn clear_failures(&self, access_code_id: i64) -> RepoResult<()>;
+    /// Nombre de codes de secours encore utilisables.
+    fn remaining_recovery_codes(&self, access_code_id: i64) -> RepoResult<i64>;
+    /// Supprime le secret et les codes de secours ; `NotFound` si la double
+    /// authentification n'était pas configurée.
+    fn reset(&self, access_code_id: i64) -> RepoResult<()>;
+}
+
+pub trait ApiTokenRepo: Send + Sync {
+    /// Jetons, révoqués compris, du plus récent au plus ancien.
+    fn list(&self) -> RepoResult<Vec<ApiToken>>;
+    /// `Conflict` si un jeton a déjà cette empreinte.
+    fn create(&self, new: &NewApiToken) -> RepoResult<ApiToken>;
+    /// Jeton dont l'empreinte est `token_hash`, même révoqué ou expiré.
+    fn find_by_hash(&self, token_hash: &str) -> RepoResult<ApiToken>;
+    /// Révoque le jeton ; `Conflict` s'il l'est déjà.
+    fn revoke(&self, id: i64) -> RepoResult<()>;
+    /// Mémorise la dernière utilisation du jeton (`TIMESTAMP_FORMAT`).
+    fn touch(&self, id: i64, now: &str) -> RepoResult<()>;
+}
+
+pub trait CommitmentRepo: Send + Sync {
+    /// Affectation scellée de `year`, s'il y en a une.
+    fn find(&self, year: i32) -> RepoResult<Option<DrawCommitment>>;
+    /// Scelle l'affectation de l'année ; `Conflict` si elle l'est déjà ou si
+    /// un tirage a déjà eu lieu cette année-là.
+    fn create(&self, new: &NewCommitment) -> RepoResult<DrawCommitment>;
+    /// Date la révélation (la première seulement) ; `NotFound` si l'année
+    /// n'est pas scellée.
+    fn reveal(&self, year: i32) -> RepoResult<DrawCommitment>;
+}
+
+/// Ensemble des repositories partagés par les handlers (dans `AppState`).
+#[derive(Clone)]
+pub struct Repos {
+    pub access_codes: Arc<dyn AccessCodeRepo>,
+    pub draws: Arc<dyn DrawRepo>,
+    pub audit: Arc<dyn AuditRepo>,
+    pub addresses: Arc<dyn AddressRepo>,
+    pub login_links: Arc<dyn LoginLinkRepo>,
+    pub invitations: Arc<dyn InvitationRepo>,
+    pub totp: Arc<dyn TotpRepo>,
+    pub api_tokens: Arc<dyn ApiTokenRepo>,
+    pub commitments: Arc<dyn CommitmentRepo>,
+}
+
+impl Repos {
+    pub fn sqlite(pool: SqlitePool) -> Self {
+        Self::from_repo(Arc::new(sqlite::SqliteRepo::new(pool)))
+    }
+
+    #[cfg(feature = "postgres")]
+    pub fn postgres(pool: crate::db::PgPool) -> Self {
+        Self::from_repo(Arc::new(postgres::PgRepo::new(pool)))
+    }
+
+    /// Exécute `f` sur le pool de threads bloquants de tokio.
+    ///
+    /// Les repositories sont synchrones (rusqlite, postgres, attente d'une
+    /// connexion du pool) : les handlers async passent par cette méthode pour
+    /// ne jamais bloquer les threads du runtime de Rocket.
+    pub async fn run<T, F>(&self, f: F) -> RepoResult<T>
+    where
+        F: FnOnce(&Repos) -> RepoResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let repos = self.clone();
+        rocket::tokio::task::spawn_blocking(move || f(&repos))
+            .await
+            .unwrap_or_else(|e| Err(RepoError::Backend(format!("blocking task failed: {e}"))))
+    }
+
+    #[cfg(test)]
+    pub fn memory() -> Self {
+        Self::from_repo(Arc::new(memory::MemoryRepo::default()))
+    }
+
+    fn from_repo<R>(repo: Arc<R>) -> Self
+    where
+        R: AccessCodeRepo + DrawRepo + AuditRepo + AddressRepo + LoginLinkRepo + InvitationRepo + TotpRepo + ApiTokenRepo
+            + CommitmentRepo + 'static,
+    {
+        Self {
+            access_codes: repo.clone(),
+            draws: repo.clone(),
+            audit: repo.clone(),
+            addresses: repo.clone(),
+            login_links: repo.clone(),
+            invitations: repo.clone(),
+            totp: repo.clone(),
+            api_tokens: repo.clone(),
+            commitments: repo,
+        }
+    }
+}
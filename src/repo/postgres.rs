@@ -0,0 +1,1375 @@
+use std::time::Duration;
+
+use postgres::error::SqlState;
+use postgres::{Client, GenericClient, Row, Transaction};
+
+use super::{
+    assignment_from_json, assignment_to_json, pick_receiver, sealed_receiver, AccessCodeRepo, AddressRepo, ApiTokenRepo, AuditRepo,
+    CodeQuery, CodeSort, CodeSortField, CodeWithDraw, CommitmentRepo, DrawRepo, InvitationRepo, LoginLinkRepo,
+    NewAccessCode, NewApiToken, NewCommitment, NewInvitation, Profile, RepoError, RepoResult, StoredAddress,
+    TotpCredential, TotpRepo,
+};
+use crate::audit::{AuditEntry, AuditEvent};
+use crate::db::{blocking, PgPool};
+use crate::models::access_code::AccessCode;
+use crate::models::api_token::{join_scopes, split_scopes, ApiToken};
+use crate::models::commitment::DrawCommitment;
+use crate::models::draw::Draw;
+use crate::models::invitation::{Invitation, Signup, SignupStatus};
+use crate::totp;
+
+/// Nombre maximal de tentatives d'un tirage en cas de contention sur la base.
+const DRAW_ATTEMPTS: u32 = 20;
+
+const ACCESS_CODE_COLUMNS: &str = "
+    access_codes.id, access_codes.name, access_codes.code, access_codes.active, access_codes.locale,
+    access_codes.archived_at, access_codes.valid_from, access_codes.valid_until, access_codes.max_uses,
+    access_codes.last_login_at, access_codes.login_count, access_codes.notify_draw, access_codes.notify_reminders
+";
+
+impl From<postgres::Error> for RepoError {
+    fn from(e: postgres::Error) -> Self {
+        match e.code() {
+            Some(code) if *code == SqlState::UNIQUE_VIOLATION || *code == SqlState::FOREIGN_KEY_VIOLATION => {
+                RepoError::Conflict
+            }
+            Some(code) if *code == SqlState::T_R_SERIALIZATION_FAILURE || *code == SqlState::T_R_DEADLOCK_DETECTED => {
+                RepoError::Unavailable
+            }
+            _ => RepoError::Backend(e.to_string()),
+        }
+    }
+}
+
+// Participants de `CodeQuery`, joints à leur tirage de l'année
+const CODE_SEARCH_FROM: &str = "
+    FROM access_codes
+    LEFT JOIN draws ON draws.giver_id = access_codes.id AND draws.year = $1
+    WHERE ($2 OR access_codes.archived_at IS NULL)
+    AND ($3::TEXT IS NULL OR access_codes.name ILIKE $3 ESCAPE '\\')
+    AND ($4::BOOLEAN IS NULL OR access_codes.active = $4)
+    AND ($5::BOOLEAN IS NULL OR (draws.id IS NOT NULL) = $5)
+";
+
+fn code_order_by(sort: CodeSort) -> String {
+    let dir = if sort.descending { "DESC" } else { "ASC" };
+    match sort.field {
+        CodeSortField::Id => format!("access_codes.id {dir}"),
+        CodeSortField::Name => format!("lower(access_codes.name) {dir}, access_codes.id"),
+        CodeSortField::LastLogin => format!(
+            "access_codes.last_login_at IS NOT NULL {dir}, access_codes.last_login_at {dir}, access_codes.id"
+        ),
+    }
+}
+
+fn access_code_from_row(row: &Row) -> AccessCode {
+    AccessCode {
+        id: row.get(0),
+        name: row.get(1),
+        code: row.get(2),
+        active: row.get(3),
+        locale: row.get(4),
+        archived_at: row.get(5),
+        valid_from: row.get(6),
+        valid_until: row.get(7),
+        max_uses: row.get(8),
+        last_login_at: row.get(9),
+        login_count: row.get(10),
+        notify_draw: row.get(11),
+        notify_reminders: row.get(12),
+    }
+}
+
+/// Repositories PostgreSQL sur un pool r2d2 (feature `postgres`).
+pub struct PgRepo {
+    pool: PgPool,
+}
+
+impl PgRepo {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Exécute `f` avec une connexion du pool, hors des threads du runtime async.
+    fn with_conn<T>(&self, f: impl FnOnce(&mut Client) -> RepoResult<T>) -> RepoResult<T> {
+        blocking(|| {
+            let mut conn = self.pool.get().map_err(|e| {
+                log::warn!("db pool unavailable error={e}");
+                RepoError::Unavailable
+            })?;
+            f(&mut conn)
+        })
+    }
+
+    fn query_access_codes(&self, filter: &str, params: &[&(dyn postgres::types::ToSql + Sync)]) -> RepoResult<Vec<AccessCode>> {
+        self.with_conn(|conn| {
+            let rows = conn.query(&format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes {filter}"), params)?;
+            Ok(rows.iter().map(access_code_from_row).collect())
+        })
+    }
+
+    fn query_access_code(&self, filter: &str, params: &[&(dyn postgres::types::ToSql + Sync)]) -> RepoResult<AccessCode> {
+        self.query_access_codes(filter, params)?.into_iter().next().ok_or(RepoError::NotFound)
+    }
+}
+
+impl AccessCodeRepo for PgRepo {
+    fn list(&self, include_archived: bool) -> RepoResult<Vec<AccessCode>> {
+        self.query_access_codes("WHERE $1 OR archived_at IS NULL ORDER BY id", &[&include_archived])
+    }
+
+    fn search(&self, query: &CodeQuery) -> RepoResult<(Vec<CodeWithDraw>, i64)> {
+        let name = query.name_pattern();
+        self.with_conn(|conn| {
+            let filter: [&(dyn postgres::types::ToSql + Sync); 5] =
+                [&query.year, &query.include_archived, &name, &query.active, &query.drawn];
+            let total: i64 = conn.query_one(&format!("SELECT COUNT(*) {CODE_SEARCH_FROM}"), &filter)?.get(0);
+
+            let (limit, offset) = (query.per_page as i64, query.offset());
+            let mut page = filter.to_vec();
+            page.extend([&limit as &(dyn postgres::types::ToSql + Sync), &offset]);
+            let rows = conn.query(
+                &format!(
+                    "SELECT {ACCESS_CODE_COLUMNS}, draws.receiver_id {CODE_SEARCH_FROM} ORDER BY {} LIMIT $6 OFFSET $7",
+                    code_order_by(query.sort)
+                ),
+                &page,
+            )?;
+            let codes = rows.iter()
+                .map(|row| CodeWithDraw { access_code: access_code_from_row(row), receiver_id: row.get(13) })
+                .collect();
+            Ok((codes, total))
+        })
+    }
+
+    fn list_active(&self) -> RepoResult<Vec<AccessCode>> {
+        self.query_access_codes("WHERE active AND archived_at IS NULL ORDER BY name", &[])
+    }
+
+    fn find(&self, id: i64) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE id = $1", &[&id])
+    }
+
+    fn find_active(&self, id: i64) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE id = $1 AND active AND archived_at IS NULL", &[&id])
+    }
+
+    fn find_active_by_code(&self, code: &str) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE code = $1 AND active AND archived_at IS NULL", &[&code])
+    }
+
+    fn create(&self, new: &NewAccessCode) -> RepoResult<AccessCode> {
+        self.with_conn(|conn| {
+            let row = conn.query_one(
+                "
+                INSERT INTO access_codes (name, code, active, valid_from, valid_until, max_uses)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id
+                ",
+                &[&new.name, &new.code, &new.active, &new.valid_from, &new.valid_until, &new.max_uses],
+            )?;
+            Ok(AccessCode {
+                id: row.get(0),
+                name: new.name.clone(),
+                code: new.code.clone(),
+                active: new.active,
+                locale: None,
+                archived_at: None,
+                valid_from: new.valid_from.clone(),
+                valid_until: new.valid_until.clone(),
+                max_uses: new.max_uses,
+                last_login_at: None,
+                login_count: 0,
+                notify_draw: false,
+                notify_reminders: false,
+            })
+        })
+    }
+
+    fn update(&self, access_code: &AccessCode) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE access_codes
+                SET name = $1, code = $2, active = $3, valid_from = $4, valid_until = $5, max_uses = $6
+                WHERE id = $7
+                ",
+                &[
+                    &access_code.name,
+                    &access_code.code,
+                    &access_code.active,
+                    &access_code.valid_from,
+                    &access_code.valid_until,
+                    &access_code.max_uses,
+                    &access_code.id,
+                ],
+            )?;
+            if rows == 0 {
+                return Err(RepoError::NotFound);
+            }
+            Ok(())
+        })
+    }
+
+    fn record_login(&self, id: i64, now: &str) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE access_codes SET login_count = login_count + 1, last_login_at = $2
+                WHERE id = $1 AND (max_uses IS NULL OR login_count < max_uses)
+                ",
+                &[&id, &now],
+            )?;
+            if rows == 0 {
+                return Err(missing_or_conflict(conn, id)?);
+            }
+            Ok(())
+        })
+    }
+
+    fn archive(&self, id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE access_codes SET archived_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE id = $1 AND archived_at IS NULL
+                ",
+                &[&id],
+            )?;
+            if rows == 0 {
+                return Err(missing_or_conflict(conn, id)?);
+            }
+            Ok(())
+        })
+    }
+
+    fn restore(&self, id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "UPDATE access_codes SET archived_at = NULL WHERE id = $1 AND archived_at IS NOT NULL",
+                &[&id],
+            )?;
+            if rows == 0 {
+                return Err(missing_or_conflict(conn, id)?);
+            }
+            Ok(())
+        })
+    }
+
+    fn purge(&self, id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            let row = tx
+                .query_opt("SELECT archived_at FROM access_codes WHERE id = $1 FOR UPDATE", &[&id])?
+                .ok_or(RepoError::NotFound)?;
+            if row.get::<_, Option<String>>(0).is_none() {
+                return Err(RepoError::Conflict);
+            }
+            tx.execute("DELETE FROM draws WHERE giver_id = $1 OR receiver_id = $1", &[&id])?;
+            tx.execute("DELETE FROM shipping_addresses WHERE access_code_id = $1", &[&id])?;
+            tx.execute("DELETE FROM login_links WHERE access_code_id = $1", &[&id])?;
+            tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = $1", &[&id])?;
+            tx.execute("DELETE FROM totp_credentials WHERE access_code_id = $1", &[&id])?;
+            tx.execute("DELETE FROM api_tokens WHERE created_by = $1", &[&id])?;
+            tx.execute("UPDATE signups SET access_code_id = NULL WHERE access_code_id = $1", &[&id])?;
+            tx.execute("DELETE FROM access_codes WHERE id = $1", &[&id])?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn set_locale(&self, id: i64, locale: &str) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute("UPDATE access_codes SET locale = $1 WHERE id = $2", &[&locale, &id])?;
+            if rows == 0 {
+                return Err(RepoError::NotFound);
+            }
+            Ok(())
+        })
+    }
+
+    fn update_profile(&self, id: i64, profile: &Profile) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE access_codes SET name = $1, locale = $2, notify_draw = $3, notify_reminders = $4
+                WHERE id = $5
+                ",
+                &[&profile.name, &profile.locale, &profile.notify_draw, &profile.notify_reminders, &id],
+            )?;
+            if rows == 0 {
+                return Err(RepoError::NotFound);
+            }
+            Ok(())
+        })
+    }
+
+    fn set_code(&self, id: i64, code: &str) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute("UPDATE access_codes SET code = $1 WHERE id = $2", &[&code, &id])?;
+            if rows == 0 {
+                return Err(RepoError::NotFound);
+            }
+            Ok(())
+        })
+    }
+}
+
+// Mise à jour sans effet : code inexistant, ou déjà dans l'état demandé
+fn missing_or_conflict(conn: &mut Client, id: i64) -> RepoResult<RepoError> {
+    let exists = conn.query_opt("SELECT 1 FROM access_codes WHERE id = $1", &[&id])?.is_some();
+    Ok(if exists { RepoError::Conflict } else { RepoError::NotFound })
+}
+
+// Erreurs dues à un tirage concurrent : la transaction peut être rejouée
+fn is_retryable(e: &postgres::Error) -> bool {
+    e.code().is_some_and(|code| {
+        *code == SqlState::UNIQUE_VIOLATION
+            || *code == SqlState::T_R_SERIALIZATION_FAILURE
+            || *code == SqlState::T_R_DEADLOCK_DETECTED
+    })
+}
+
+fn try_draw(tx: &mut Transaction<'_>, giver_id: i64, year: i32) -> Result<RepoResult<AccessCode>, postgres::Error> {
+    // verrou exclusif entre tirages (les lectures restent possibles),
+    // l'équivalent du `BEGIN IMMEDIATE` de SQLite
+    tx.batch_execute("LOCK TABLE draws IN SHARE ROW EXCLUSIVE MODE")?;
+
+    let already_drawn = tx
+        .query_opt("SELECT 1 FROM draws WHERE giver_id = $1 AND year = $2", &[&giver_id, &year])?
+        .is_some();
+    if already_drawn {
+        return Ok(Err(RepoError::Conflict));
+    }
+
+    // année scellée : le destinataire est celui de l'affectation engagée,
+    // même s'il a été désactivé ou archivé depuis
+    if let Some(row) = tx.query_opt("SELECT assignment FROM draw_commitments WHERE year = $1", &[&year])? {
+        let receiver_id = match assignment_from_json(row.get(0)).and_then(|assignment| sealed_receiver(&assignment, giver_id)) {
+            Ok(receiver_id) => receiver_id,
+            Err(e) => return Ok(Err(e)),
+        };
+        let Some(row) = tx.query_opt(&format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes WHERE id = $1"), &[&receiver_id])? else {
+            return Ok(Err(RepoError::NotFound));
+        };
+        let receiver = access_code_from_row(&row);
+        tx.execute(
+            "INSERT INTO draws (giver_id, receiver_id, year) VALUES ($1, $2, $3)",
+            &[&giver_id, &receiver.id, &year],
+        )?;
+        return Ok(Ok(receiver));
+    }
+
+    // destinataires possibles : actifs, non archivés, autres que le demandeur, pas encore tirés
+    let rows = tx.query(
+        &format!("
+        SELECT {ACCESS_CODE_COLUMNS}
+        FROM access_codes
+        WHERE
+            access_codes.id != $1
+            AND access_codes.active
+            AND access_codes.archived_at IS NULL
+            AND access_codes.id NOT IN (SELECT receiver_id FROM draws WHERE year = $2)
+        "),
+        &[&giver_id, &year],
+    )?;
+    let Some(receiver) = pick_receiver(rows.iter().map(access_code_from_row).collect()) else {
+        return Ok(Err(RepoError::NotFound));
+    };
+
+    tx.execute(
+        "INSERT INTO draws (giver_id, receiver_id, year) VALUES ($1, $2, $3)",
+        &[&giver_id, &receiver.id, &year],
+    )?;
+
+    Ok(Ok(receiver))
+}
+
+const ORPHAN_DRAWS_FILTER: &str = "
+    WHERE giver_id NOT IN (SELECT id FROM access_codes)
+    OR receiver_id NOT IN (SELECT id FROM access_codes)
+";
+
+fn query_draws(conn: &mut impl postgres::GenericClient, filter: &str) -> Result<Vec<Draw>, postgres::Error> {
+    let rows = conn.query(&format!("SELECT id, giver_id, receiver_id, year, created_at FROM draws {filter} ORDER BY id"), &[])?;
+    Ok(rows.iter().map(|row| Draw {
+        id: row.get(0),
+        giver_id: row.get(1),
+        receiver_id: row.get(2),
+        year: row.get(3),
+        created_at: row.get(4),
+    }).collect())
+}
+
+impl DrawRepo for PgRepo {
+    fn list(&self) -> RepoResult<Vec<Draw>> {
+        self.with_conn(|conn| Ok(query_draws(conn, "")?))
+    }
+
+    fn has_drawn(&self, giver_id: i64, year: i32) -> RepoResult<bool> {
+        self.with_conn(|conn| {
+            let row = conn.query_opt("SELECT 1 FROM draws WHERE giver_id = $1 AND year = $2", &[&giver_id, &year])?;
+            Ok(row.is_some())
+        })
+    }
+
+    fn receiver_of(&self, giver_id: i64, year: i32) -> RepoResult<Option<AccessCode>> {
+        self.with_conn(|conn| {
+            let row = conn.query_opt(
+                &format!("
+                SELECT {ACCESS_CODE_COLUMNS}
+                FROM draws
+                INNER JOIN access_codes ON draws.receiver_id = access_codes.id
+                WHERE draws.giver_id = $1 AND draws.year = $2
+                "),
+                &[&giver_id, &year],
+            )?;
+            Ok(row.as_ref().map(access_code_from_row))
+        })
+    }
+
+    /// Même protocole que SQLite : choix et insertion dans une transaction qui
+    /// verrouille `draws`, rejouée en cas de conflit.
+    fn draw(&self, giver_id: i64, year: i32) -> RepoResult<AccessCode> {
+        self.with_conn(|conn| {
+            for attempt in 1..=DRAW_ATTEMPTS {
+                let result = conn.transaction().and_then(|mut tx| {
+                    let result = try_draw(&mut tx, giver_id, year)?;
+                    if result.is_ok() {
+                        tx.commit()?;
+                    }
+                    Ok(result)
+                });
+                match result {
+                    Ok(result) => return result,
+                    Err(e) if is_retryable(&e) => {
+                        log::debug!("draw retry giver={giver_id} attempt={attempt} error={e}");
+                        std::thread::sleep(Duration::from_millis(2 * attempt as u64));
+                    }
+                    Err(e) => return Err(RepoError::Backend(e.to_string())),
+                }
+            }
+            log::warn!("draw giving up giver={giver_id} attempts={DRAW_ATTEMPTS}");
+            Err(RepoError::Unavailable)
+        })
+    }
+
+    fn reset(&self, year: i32) -> RepoResult<usize> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            if tx.query_opt("SELECT 1 FROM draw_commitments WHERE year = $1 FOR SHARE", &[&year])?.is_some() {
+                return Err(RepoError::Conflict);
+            }
+            let deleted = tx.execute("DELETE FROM draws WHERE year = $1", &[&year])? as usize;
+            tx.commit()?;
+            Ok(deleted)
+        })
+    }
+
+    // les clés étrangères de PostgreSQL empêchent les orphelins ; la
+    // vérification reste utile pour une base importée depuis SQLite
+    fn orphans(&self) -> RepoResult<Vec<Draw>> {
+        self.with_conn(|conn| Ok(query_draws(conn, ORPHAN_DRAWS_FILTER)?))
+    }
+
+    fn remove_orphans(&self) -> RepoResult<Vec<Draw>> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            tx.batch_execute("LOCK TABLE draws IN SHARE ROW EXCLUSIVE MODE")?;
+            let orphans = query_draws(&mut tx, ORPHAN_DRAWS_FILTER)?;
+            tx.execute(&format!("DELETE FROM draws {ORPHAN_DRAWS_FILTER}"), &[])?;
+            tx.commit()?;
+            Ok(orphans)
+        })
+    }
+
+    fn count_by_year(&self) -> RepoResult<Vec<(i32, i64)>> {
+        self.with_conn(|conn| {
+            let rows = conn.query("SELECT year, COUNT(*) FROM draws GROUP BY year ORDER BY year", &[])?;
+            Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+        })
+    }
+}
+
+impl AuditRepo for PgRepo {
+    fn record(&self, event: &AuditEvent) {
+        let result = self.with_conn(|conn| {
+            conn.execute(
+                "
+                INSERT INTO audit_log (actor_id, actor_name, action, target_id, before, after, detail)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ",
+                &[
+                    &event.actor_id,
+                    &event.actor_name,
+                    &event.action.as_str(),
+                    &event.target_id,
+                    &event.before.as_ref().map(|v| v.to_string()),
+                    &event.after.as_ref().map(|v| v.to_string()),
+                    &event.detail,
+                ],
+            )?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            log::error!("audit action={} error={e:?}", event.action.as_str());
+        }
+    }
+
+    fn list(&self, page: u32, per_page: u32) -> RepoResult<(Vec<AuditEntry>, i64)> {
+        self.with_conn(|conn| {
+            let total: i64 = conn.query_one("SELECT COUNT(*) FROM audit_log", &[])?.get(0);
+            let offset = (page.max(1) - 1) as i64 * per_page as i64;
+            let rows = conn.query(
+                "
+                SELECT id, created_at, actor_id, actor_name, action, target_id, before, after, detail
+                FROM audit_log
+                ORDER BY id DESC
+                LIMIT $1 OFFSET $2
+                ",
+                &[&(per_page as i64), &offset],
+            )?;
+            let entries = rows.iter().map(|row| AuditEntry {
+                id: row.get(0),
+                created_at: row.get(1),
+                actor_id: row.get(2),
+                actor_name: row.get(3),
+                action: row.get(4),
+                target_id: row.get(5),
+                before: row.get(6),
+                after: row.get(7),
+                detail: row.get(8),
+            }).collect();
+            Ok((entries, total))
+        })
+    }
+}
+
+impl AddressRepo for PgRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<StoredAddress>> {
+        self.with_conn(|conn| {
+            let row = conn.query_opt(
+                "SELECT nonce, ciphertext FROM shipping_addresses WHERE access_code_id = $1",
+                &[&access_code_id],
+            )?;
+            Ok(row.map(|row| StoredAddress { nonce: row.get(0), ciphertext: row.get(1) }))
+        })
+    }
+
+    fn save(&self, access_code_id: i64, address: &StoredAddress) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "
+                INSERT INTO shipping_addresses (access_code_id, nonce, ciphertext)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (access_code_id) DO UPDATE SET
+                    nonce = excluded.nonce,
+                    ciphertext = excluded.ciphertext,
+                    updated_at = excluded.updated_at
+                ",
+                &[&access_code_id, &address.nonce, &address.ciphertext],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn delete(&self, access_code_id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            conn.execute("DELETE FROM shipping_addresses WHERE access_code_id = $1", &[&access_code_id])?;
+            Ok(())
+        })
+    }
+}
+
+impl LoginLinkRepo for PgRepo {
+    fn create(&self, access_code_id: i64, expires_at: i64) -> RepoResult<i64> {
+        self.with_conn(|conn| {
+            let row = conn.query_one(
+                "INSERT INTO login_links (access_code_id, expires_at) VALUES ($1, $2) RETURNING id",
+                &[&access_code_id, &expires_at],
+            )?;
+            Ok(row.get(0))
+        })
+    }
+
+    fn consume(&self, link_id: i64, access_code_id: i64, now: i64) -> RepoResult<bool> {
+        self.with_conn(|conn| {
+            let consumed = conn.execute(
+                "
+                UPDATE login_links SET used_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE id = $1 AND access_code_id = $2 AND used_at IS NULL AND expires_at > $3
+                ",
+                &[&link_id, &access_code_id, &now],
+            )?;
+            Ok(consumed > 0)
+        })
+    }
+}
+
+// Les inscriptions refusées ne comptent pas dans le plafond d'un lien
+const INVITATION_COLUMNS: &str = "
+    invitations.id, invitations.token, invitations.label, invitations.max_signups, invitations.expires_at,
+    invitations.revoked_at, invitations.created_at,
+    (SELECT COUNT(*) FROM signups WHERE signups.invitation_id = invitations.id AND signups.status != 'rejected')
+";
+
+const SIGNUP_SELECT: &str = "
+    SELECT signups.id, signups.invitation_id, invitations.label, signups.name, signups.code, signups.status,
+        signups.created_at, signups.decided_at, signups.access_code_id
+    FROM signups
+    INNER JOIN invitations ON invitations.id = signups.invitation_id
+";
+
+fn invitation_from_row(row: &Row) -> Invitation {
+    Invitation {
+        id: row.get(0),
+        token: row.get(1),
+        label: row.get(2),
+        max_signups: row.get(3),
+        expires_at: row.get(4),
+        revoked_at: row.get(5),
+        created_at: row.get(6),
+        signup_count: row.get(7),
+    }
+}
+
+fn signup_from_row(row: &Row) -> RepoResult<Signup> {
+    let status: String = row.get(5);
+    Ok(Signup {
+        id: row.get(0),
+        invitation_id: row.get(1),
+        invitation_label: row.get(2),
+        name: row.get(3),
+        code: row.get(4),
+        status: SignupStatus::parse(&status).ok_or_else(|| RepoError::Backend(format!("unknown signup status {status}")))?,
+        created_at: row.get(6),
+        decided_at: row.get(7),
+        access_code_id: row.get(8),
+    })
+}
+
+fn find_invitation(conn: &mut impl GenericClient, filter: &str, param: &(dyn postgres::types::ToSql + Sync)) -> RepoResult<Invitation> {
+    let row = conn
+        .query_opt(&format!("SELECT {INVITATION_COLUMNS} FROM invitations {filter}"), &[param])?
+        .ok_or(RepoError::NotFound)?;
+    Ok(invitation_from_row(&row))
+}
+
+fn find_signup(conn: &mut impl GenericClient, id: i64) -> RepoResult<Signup> {
+    let row = conn
+        .query_opt(&format!("{SIGNUP_SELECT} WHERE signups.id = $1"), &[&id])?
+        .ok_or(RepoError::NotFound)?;
+    signup_from_row(&row)
+}
+
+// Passe une inscription en attente dans l'état `status`
+fn decide_signup(conn: &mut impl GenericClient, id: i64, status: SignupStatus) -> RepoResult<()> {
+    let rows = conn.execute(
+        "
+        UPDATE signups SET status = $1, decided_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+        WHERE id = $2 AND status = 'pending'
+        ",
+        &[&status.as_str(), &id],
+    )?;
+    if rows == 0 {
+        // inconnue (NotFound) ou déjà traitée
+        find_signup(conn, id)?;
+        return Err(RepoError::Conflict);
+    }
+    Ok(())
+}
+
+impl InvitationRepo for PgRepo {
+    fn list(&self) -> RepoResult<Vec<Invitation>> {
+        self.with_conn(|conn| {
+            let rows = conn.query(&format!("SELECT {INVITATION_COLUMNS} FROM invitations ORDER BY id DESC"), &[])?;
+            Ok(rows.iter().map(invitation_from_row).collect())
+        })
+    }
+
+    fn create(&self, new: &NewInvitation) -> RepoResult<Invitation> {
+        self.with_conn(|conn| {
+            let id: i64 = conn.query_one(
+                "INSERT INTO invitations (token, label, max_signups, expires_at) VALUES ($1, $2, $3, $4) RETURNING id",
+                &[&new.token, &new.label, &new.max_signups, &new.expires_at],
+            )?.get(0);
+            find_invitation(conn, "WHERE id = $1", &id)
+        })
+    }
+
+    fn find_by_token(&self, token: &str) -> RepoResult<Invitation> {
+        self.with_conn(|conn| find_invitation(conn, "WHERE token = $1", &token))
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE invitations SET revoked_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE id = $1 AND revoked_at IS NULL
+                ",
+                &[&id],
+            )?;
+            if rows == 0 {
+                find_invitation(conn, "WHERE id = $1", &id)?;
+                return Err(RepoError::Conflict);
+            }
+            Ok(())
+        })
+    }
+
+    /// La ligne du lien est verrouillée (`FOR UPDATE`) le temps de compter
+    /// et d'insérer : deux inscriptions simultanées ne peuvent pas dépasser
+    /// `max_signups`.
+    fn sign_up(&self, invitation_id: i64, name: &str, code: &str, now: &str) -> RepoResult<Option<Signup>> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            tx.query_opt("SELECT id FROM invitations WHERE id = $1 FOR UPDATE", &[&invitation_id])?
+                .ok_or(RepoError::NotFound)?;
+            if !find_invitation(&mut tx, "WHERE id = $1", &invitation_id)?.is_open_at(now) {
+                return Ok(None);
+            }
+            if tx.query_opt("SELECT 1 FROM access_codes WHERE code = $1", &[&code])?.is_some() {
+                return Err(RepoError::Conflict);
+            }
+            let id: i64 = tx.query_one(
+                "INSERT INTO signups (invitation_id, name, code) VALUES ($1, $2, $3) RETURNING id",
+                &[&invitation_id, &name, &code],
+            )?.get(0);
+            let signup = find_signup(&mut tx, id)?;
+            tx.commit()?;
+            Ok(Some(signup))
+        })
+    }
+
+    fn signups(&self, status: Option<SignupStatus>) -> RepoResult<Vec<Signup>> {
+        let status = status.map(SignupStatus::as_str);
+        self.with_conn(|conn| {
+            let rows = conn.query(
+                &format!("{SIGNUP_SELECT} WHERE $1::TEXT IS NULL OR signups.status = $1 ORDER BY signups.id"),
+                &[&status],
+            )?;
+            rows.iter().map(signup_from_row).collect()
+        })
+    }
+
+    fn approve(&self, signup_id: i64) -> RepoResult<AccessCode> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            decide_signup(&mut tx, signup_id, SignupStatus::Approved)?;
+            let signup = find_signup(&mut tx, signup_id)?;
+            let row = tx.query_one(
+                &format!("INSERT INTO access_codes (name, code, active) VALUES ($1, $2, TRUE) RETURNING {ACCESS_CODE_COLUMNS}"),
+                &[&signup.name, &signup.code],
+            )?;
+            let access_code = access_code_from_row(&row);
+            tx.execute("UPDATE signups SET access_code_id = $1 WHERE id = $2", &[&access_code.id, &signup_id])?;
+            tx.commit()?;
+            Ok(access_code)
+        })
+    }
+
+    fn reject(&self, signup_id: i64) -> RepoResult<Signup> {
+        self.with_conn(|conn| {
+            decide_signup(conn, signup_id, SignupStatus::Rejected)?;
+            find_signup(conn, signup_id)
+        })
+    }
+}
+
+/// Tests sur une base PostgreSQL locale, désignée par la variable
+/// d'environnement `WINTER_CARD_TEST_POSTGRES_URL` ; ignorés sans elle.
+/// Chaque test travaille dans son propre schéma, supprimé à la fin.
+impl TotpRepo for PgRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<TotpCredential>> {
+        self.with_conn(|conn| {
+            let row = conn.query_opt(
+                "
+                SELECT secret, confirmed_at, last_step, failed_attempts, last_failure_at
+                FROM totp_credentials WHERE access_code_id = $1
+                ",
+                &[&access_code_id],
+            )?;
+            Ok(row.map(|row| TotpCredential {
+                secret: row.get(0),
+                confirmed_at: row.get(1),
+                last_step: row.get(2),
+                failed_attempts: row.get(3),
+                last_failure_at: row.get(4),
+            }))
+        })
+    }
+
+    fn begin_enrollment(&self, access_code_id: i64, secret: &[u8]) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                INSERT INTO totp_credentials (access_code_id, secret) VALUES ($1, $2)
+                ON CONFLICT (access_code_id) DO UPDATE SET
+                    secret = excluded.secret,
+                    last_step = NULL,
+                    failed_attempts = 0,
+                    last_failure_at = NULL,
+                    created_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE totp_credentials.confirmed_at IS NULL
+                ",
+                &[&access_code_id, &secret],
+            )?;
+            if rows == 0 {
+                return Err(RepoError::Conflict);
+            }
+            Ok(())
+        })
+    }
+
+    fn confirm(&self, access_code_id: i64, step: i64, recovery_hashes: &[String]) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            let rows = tx.execute(
+                "
+                UPDATE totp_credentials
+                SET confirmed_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS'), last_step = $2
+                WHERE access_code_id = $1 AND confirmed_at IS NULL
+                ",
+                &[&access_code_id, &step],
+            )?;
+            if rows == 0 {
+                return Err(RepoError::Conflict);
+            }
+            tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = $1", &[&access_code_id])?;
+            for hash in recovery_hashes {
+                tx.execute(
+                    "INSERT INTO totp_recovery_codes (access_code_id, code_hash) VALUES ($1, $2)",
+                    &[&access_code_id, hash],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    fn record_step(&self, access_code_id: i64, step: i64) -> RepoResult<bool> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE totp_credentials SET last_step = $2
+                WHERE access_code_id = $1 AND confirmed_at IS NOT NULL AND (last_step IS NULL OR last_step < $2)
+                ",
+                &[&access_code_id, &step],
+            )?;
+            Ok(rows > 0)
+        })
+    }
+
+    fn use_recovery_code(&self, access_code_id: i64, code_hash: &str) -> RepoResult<bool> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE totp_recovery_codes SET used_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE id = (
+                    SELECT id FROM totp_recovery_codes
+                    WHERE access_code_id = $1 AND code_hash = $2 AND used_at IS NULL
+                    LIMIT 1
+                    FOR UPDATE
+                )
+                ",
+                &[&access_code_id, &code_hash],
+            )?;
+            Ok(rows > 0)
+        })
+    }
+
+    fn record_failure(&self, access_code_id: i64, now: i64) -> RepoResult<i64> {
+        self.with_conn(|conn| {
+            let row = conn.query_opt(
+                "
+                UPDATE totp_credentials SET
+                    failed_attempts = CASE WHEN last_failure_at > $3 THEN failed_attempts + 1 ELSE 1 END,
+                    last_failure_at = $2
+                WHERE access_code_id = $1
+                RETURNING failed_attempts
+                ",
+                &[&access_code_id, &now, &(now - totp::LOCKOUT_SECS)],
+            )?;
+            row.map(|row| row.get(0)).ok_or(RepoError::NotFound)
+        })
+    }
+
+    fn clear_failures(&self, access_code_id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "UPDATE totp_credentials SET failed_attempts = 0, last_failure_at = NULL WHERE access_code_id = $1",
+                &[&access_code_id],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn remaining_recovery_codes(&self, access_code_id: i64) -> RepoResult<i64> {
+        self.with_conn(|conn| {
+            let row = conn.query_one(
+                "SELECT COUNT(*) FROM totp_recovery_codes WHERE access_code_id = $1 AND used_at IS NULL",
+                &[&access_code_id],
+            )?;
+            Ok(row.get(0))
+        })
+    }
+
+    fn reset(&self, access_code_id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = $1", &[&access_code_id])?;
+            let rows = tx.execute("DELETE FROM totp_credentials WHERE access_code_id = $1", &[&access_code_id])?;
+            if rows == 0 {
+                return Err(RepoError::NotFound);
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+}
+
+const API_TOKEN_COLUMNS: &str = "id, name, scopes, created_by, created_at, expires_at, revoked_at, last_used_at";
+
+fn api_token_from_row(row: &Row) -> ApiToken {
+    let scopes: String = row.get(2);
+    ApiToken {
+        id: row.get(0),
+        name: row.get(1),
+        scopes: split_scopes(&scopes),
+        created_by: row.get(3),
+        created_at: row.get(4),
+        expires_at: row.get(5),
+        revoked_at: row.get(6),
+        last_used_at: row.get(7),
+    }
+}
+
+fn find_api_token(conn: &mut impl GenericClient, filter: &str, param: &(dyn postgres::types::ToSql + Sync)) -> RepoResult<ApiToken> {
+    let row = conn
+        .query_opt(&format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens {filter}"), &[param])?
+        .ok_or(RepoError::NotFound)?;
+    Ok(api_token_from_row(&row))
+}
+
+impl ApiTokenRepo for PgRepo {
+    fn list(&self) -> RepoResult<Vec<ApiToken>> {
+        self.with_conn(|conn| {
+            let rows = conn.query(&format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens ORDER BY id DESC"), &[])?;
+            Ok(rows.iter().map(api_token_from_row).collect())
+        })
+    }
+
+    fn create(&self, new: &NewApiToken) -> RepoResult<ApiToken> {
+        self.with_conn(|conn| {
+            let id: i64 = conn.query_one(
+                "INSERT INTO api_tokens (name, token_hash, scopes, created_by, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+                &[&new.name, &new.token_hash, &join_scopes(&new.scopes), &new.created_by, &new.expires_at],
+            )?.get(0);
+            find_api_token(conn, "WHERE id = $1", &id)
+        })
+    }
+
+    fn find_by_hash(&self, token_hash: &str) -> RepoResult<ApiToken> {
+        self.with_conn(|conn| find_api_token(conn, "WHERE token_hash = $1", &token_hash))
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            let rows = conn.execute(
+                "
+                UPDATE api_tokens SET revoked_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE id = $1 AND revoked_at IS NULL
+                ",
+                &[&id],
+            )?;
+            if rows == 0 {
+                find_api_token(conn, "WHERE id = $1", &id)?;
+                return Err(RepoError::Conflict);
+            }
+            Ok(())
+        })
+    }
+
+    fn touch(&self, id: i64, now: &str) -> RepoResult<()> {
+        self.with_conn(|conn| {
+            conn.execute("UPDATE api_tokens SET last_used_at = $1 WHERE id = $2", &[&now, &id])?;
+            Ok(())
+        })
+    }
+}
+
+const COMMITMENT_COLUMNS: &str = "year, commitment, salt, assignment, created_at, revealed_at";
+
+fn find_commitment(conn: &mut impl GenericClient, year: i32) -> RepoResult<Option<DrawCommitment>> {
+    let Some(row) = conn.query_opt(&format!("SELECT {COMMITMENT_COLUMNS} FROM draw_commitments WHERE year = $1"), &[&year])? else {
+        return Ok(None);
+    };
+    Ok(Some(DrawCommitment {
+        year: row.get(0),
+        commitment: row.get(1),
+        salt: row.get(2),
+        assignment: assignment_from_json(row.get(3))?,
+        created_at: row.get(4),
+        revealed_at: row.get(5),
+    }))
+}
+
+impl CommitmentRepo for PgRepo {
+    fn find(&self, year: i32) -> RepoResult<Option<DrawCommitment>> {
+        self.with_conn(|conn| find_commitment(conn, year))
+    }
+
+    fn create(&self, new: &NewCommitment) -> RepoResult<DrawCommitment> {
+        self.with_conn(|conn| {
+            let mut tx = conn.transaction()?;
+            // pas de tirage concurrent entre la vérification et le scellement
+            tx.batch_execute("LOCK TABLE draws IN SHARE ROW EXCLUSIVE MODE")?;
+            if tx.query_opt("SELECT 1 FROM draws WHERE year = $1", &[&new.year])?.is_some() {
+                return Err(RepoError::Conflict);
+            }
+            tx.execute(
+                "INSERT INTO draw_commitments (year, commitment, salt, assignment) VALUES ($1, $2, $3, $4)",
+                &[&new.year, &new.commitment, &new.salt, &assignment_to_json(&new.assignment)],
+            )?;
+            let commitment = find_commitment(&mut tx, new.year)?.ok_or(RepoError::NotFound)?;
+            tx.commit()?;
+            Ok(commitment)
+        })
+    }
+
+    fn reveal(&self, year: i32) -> RepoResult<DrawCommitment> {
+        self.with_conn(|conn| {
+            conn.execute(
+                "
+                UPDATE draw_commitments SET revealed_at = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+                WHERE year = $1 AND revealed_at IS NULL
+                ",
+                &[&year],
+            )?;
+            find_commitment(conn, year)?.ok_or(RepoError::NotFound)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{init_pg_db, test_pg_pool};
+    use crate::models::api_token::ApiScope;
+    use crate::repo::Repos;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+
+    const YEAR: i32 = 2025;
+
+    struct TestSchema {
+        url: String,
+        name: String,
+        pool: PgPool,
+    }
+
+    impl Drop for TestSchema {
+        fn drop(&mut self) {
+            if let Ok(mut client) = Client::connect(&self.url, postgres::NoTls) {
+                let _ = client.batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", self.name));
+            }
+        }
+    }
+
+    fn setup() -> Option<TestSchema> {
+        let Ok(url) = std::env::var("WINTER_CARD_TEST_POSTGRES_URL") else {
+            eprintln!("WINTER_CARD_TEST_POSTGRES_URL non défini : test PostgreSQL ignoré");
+            return None;
+        };
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("winter_card_test_{}_{}", std::process::id(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+        let mut client = Client::connect(&url, postgres::NoTls).expect("connect to test database");
+        client.batch_execute(&format!("DROP SCHEMA IF EXISTS {name} CASCADE; CREATE SCHEMA {name}")).unwrap();
+
+        let mut config: postgres::Config = url.parse().unwrap();
+        config.options(&format!("-c search_path={name}"));
+        let pool = test_pg_pool(config);
+        init_pg_db(&pool);
+        Some(TestSchema { url, name, pool })
+    }
+
+    fn add_participants(repos: &Repos, count: i64) -> Vec<i64> {
+        for id in 0..count {
+            let new = NewAccessCode { name: format!("P{id}"), code: format!("CODE{id}"), active: true, ..Default::default() };
+            repos.access_codes.create(&new).unwrap();
+        }
+        repos.access_codes.list(false).unwrap().iter().map(|c| c.id).collect()
+    }
+
+    #[test]
+    fn test_access_codes() {
+        let Some(schema) = setup() else { return };
+        let codes = Repos::postgres(schema.pool.clone()).access_codes;
+        // migrations idempotentes
+        init_pg_db(&schema.pool);
+
+        let admin = codes.find_active_by_code("Winter2025").unwrap();
+        assert_eq!(admin.id, 1);
+
+        let alice = codes.create(&NewAccessCode { name: "Alice".into(), code: "ALICE".into(), active: true, ..Default::default() }).unwrap();
+        let duplicate = codes.create(&NewAccessCode { name: "Bis".into(), code: "ALICE".into(), active: true, ..Default::default() });
+        assert_eq!(duplicate.err(), Some(RepoError::Conflict));
+
+        codes.set_locale(alice.id, "de").unwrap();
+        codes.update(&AccessCode { active: false, ..alice.clone() }).unwrap();
+        assert_eq!(codes.find_active(alice.id).err(), Some(RepoError::NotFound));
+        assert_eq!(codes.find(alice.id).unwrap().locale.as_deref(), Some("de"));
+
+        assert_eq!(codes.purge(alice.id).err(), Some(RepoError::Conflict));
+        codes.archive(alice.id).unwrap();
+        assert_eq!(codes.archive(alice.id).err(), Some(RepoError::Conflict));
+        assert!(codes.find(alice.id).unwrap().archived_at.is_some());
+        assert!(codes.list(false).unwrap().iter().all(|c| c.id != alice.id));
+        codes.restore(alice.id).unwrap();
+        assert_eq!(codes.restore(alice.id).err(), Some(RepoError::Conflict));
+
+        codes.archive(alice.id).unwrap();
+        codes.purge(alice.id).unwrap();
+        assert_eq!(codes.find(alice.id).err(), Some(RepoError::NotFound));
+        assert_eq!(codes.purge(alice.id).err(), Some(RepoError::NotFound));
+        assert_eq!(codes.archive(alice.id).err(), Some(RepoError::NotFound));
+    }
+
+    #[test]
+    fn test_search() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+        let ids = add_participants(&repos, 4);
+        repos.draws.draw(ids[1], YEAR).unwrap();
+        repos.draws.draw(ids[2], YEAR - 1).unwrap();
+        repos.access_codes.record_login(ids[3], "2025-12-24 10:00:00").unwrap();
+
+        let search = |query: CodeQuery| {
+            let (codes, total) = repos.access_codes.search(&query).unwrap();
+            (codes.iter().map(|c| c.access_code.id).collect::<Vec<_>>(), total)
+        };
+        let all = CodeQuery {
+            year: YEAR,
+            name: None,
+            active: None,
+            drawn: None,
+            include_archived: false,
+            sort: CodeSort::default(),
+            page: 1,
+            per_page: 10,
+        };
+        assert_eq!(search(CodeQuery { drawn: Some(true), ..all.clone() }), (vec![ids[1]], 1));
+        assert_eq!(search(CodeQuery { name: Some("p2".into()), ..all.clone() }), (vec![ids[3]], 1));
+        assert_eq!(search(CodeQuery { name: Some("%".into()), ..all.clone() }).1, 0);
+        let sort = CodeSort::parse("-last_login").unwrap();
+        assert_eq!(search(CodeQuery { sort, per_page: 1, ..all.clone() }), (vec![ids[3]], 5));
+        let (page, total) = search(CodeQuery { sort: CodeSort::parse("-id").unwrap(), page: 2, per_page: 2, ..all });
+        assert_eq!((page, total), (vec![ids[2], ids[1]], 5));
+    }
+
+    #[test]
+    fn test_draws_are_unique() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+        let ids = add_participants(&repos, 5);
+
+        let mut receivers = HashSet::new();
+        for &giver in &ids[..ids.len() - 1] {
+            let receiver = repos.draws.draw(giver, YEAR).unwrap();
+            assert_ne!(receiver.id, giver);
+            assert!(receivers.insert(receiver.id));
+            assert_eq!(repos.draws.draw(giver, YEAR).err(), Some(RepoError::Conflict));
+            assert_eq!(repos.draws.receiver_of(giver, YEAR).unwrap().map(|c| c.id), Some(receiver.id));
+        }
+        // le dernier ne peut tirer que lui-même, s'il n'a pas encore été tiré
+        let last = *ids.last().unwrap();
+        match repos.draws.draw(last, YEAR) {
+            Ok(receiver) => assert_ne!(receiver.id, last),
+            Err(e) => assert_eq!(e, RepoError::NotFound),
+        }
+
+        let drawn = repos.draws.list().unwrap().len() as i64;
+        assert_eq!(repos.draws.count_by_year().unwrap(), vec![(YEAR, drawn)]);
+        assert!(repos.draws.reset(YEAR).unwrap() >= ids.len() - 1);
+        assert!(repos.draws.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_draws_are_valid() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+        let ids = add_participants(&repos, 8);
+
+        let barrier = Arc::new(Barrier::new(ids.len()));
+        let handles: Vec<_> = ids.iter().map(|&giver| {
+            let draws = repos.draws.clone();
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                barrier.wait();
+                match draws.draw(giver, YEAR) {
+                    Ok(_) | Err(RepoError::NotFound) => {}
+                    Err(e) => panic!("unexpected draw error {e:?}"),
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let draws = repos.draws.list().unwrap();
+        let givers: HashSet<i64> = draws.iter().map(|d| d.giver_id).collect();
+        let receivers: HashSet<i64> = draws.iter().map(|d| d.receiver_id).collect();
+        assert!(draws.len() >= ids.len() - 1);
+        assert_eq!(givers.len(), draws.len());
+        assert_eq!(receivers.len(), draws.len());
+        assert!(draws.iter().all(|d| d.giver_id != d.receiver_id));
+    }
+
+    #[test]
+    fn test_addresses_and_login_links() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+
+        let address = StoredAddress { nonce: vec![1, 2, 3], ciphertext: vec![4, 5] };
+        repos.addresses.save(1, &address).unwrap();
+        repos.addresses.save(1, &StoredAddress { ciphertext: vec![6], ..address }).unwrap();
+        assert_eq!(repos.addresses.find(1).unwrap().unwrap().ciphertext, vec![6]);
+        repos.addresses.delete(1).unwrap();
+        assert!(repos.addresses.find(1).unwrap().is_none());
+
+        let link = repos.login_links.create(1, 2_000).unwrap();
+        assert!(!repos.login_links.consume(link, 1, 3_000).unwrap());
+        assert!(repos.login_links.consume(link, 1, 1_000).unwrap());
+        assert!(!repos.login_links.consume(link, 1, 1_000).unwrap());
+    }
+
+    #[test]
+    fn test_invitations() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+        let now = "2025-12-01 12:00:00";
+
+        let new = NewInvitation { token: "TOKEN".into(), label: "Noël".into(), max_signups: Some(1), ..Default::default() };
+        let invitation = repos.invitations.create(&new).unwrap();
+        assert_eq!(repos.invitations.find_by_token("TOKEN").unwrap().id, invitation.id);
+
+        let signup = repos.invitations.sign_up(invitation.id, "Alice", "ALICE", now).unwrap().unwrap();
+        assert!(repos.invitations.sign_up(invitation.id, "Bob", "BOB", now).unwrap().is_none());
+        assert_eq!(repos.invitations.signups(Some(SignupStatus::Pending)).unwrap().len(), 1);
+
+        let access_code = repos.invitations.approve(signup.id).unwrap();
+        assert_eq!((access_code.name.as_str(), access_code.code.as_str(), access_code.active), ("Alice", "ALICE", true));
+        let approved = repos.invitations.signups(Some(SignupStatus::Approved)).unwrap();
+        assert_eq!(approved[0].access_code_id, Some(access_code.id));
+        assert!(matches!(repos.invitations.reject(signup.id), Err(RepoError::Conflict)));
+        assert!(matches!(repos.invitations.sign_up(invitation.id, "Bob", "BOB", now), Ok(None)));
+
+        repos.invitations.revoke(invitation.id).unwrap();
+        assert!(matches!(repos.invitations.revoke(invitation.id), Err(RepoError::Conflict)));
+    }
+
+    #[test]
+    fn test_totp() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+
+        assert!(repos.totp.find(1).unwrap().is_none());
+        repos.totp.begin_enrollment(1, &[1; 20]).unwrap();
+        repos.totp.begin_enrollment(1, &[2; 20]).unwrap();
+        assert!(!repos.totp.record_step(1, 10).unwrap());
+        repos.totp.confirm(1, 10, &["A".into(), "B".into()]).unwrap();
+        let credential = repos.totp.find(1).unwrap().unwrap();
+        assert!(credential.is_enabled());
+        assert_eq!((credential.secret, credential.last_step), (vec![2; 20], Some(10)));
+        assert!(matches!(repos.totp.begin_enrollment(1, &[3; 20]), Err(RepoError::Conflict)));
+
+        assert!(!repos.totp.record_step(1, 10).unwrap());
+        assert!(repos.totp.record_step(1, 11).unwrap());
+        assert!(repos.totp.use_recovery_code(1, "A").unwrap());
+        assert!(!repos.totp.use_recovery_code(1, "A").unwrap());
+        assert_eq!(repos.totp.remaining_recovery_codes(1).unwrap(), 1);
+
+        // les échecs se cumulent dans la fenêtre de blocage, puis repartent de 1
+        let now = 1_766_534_400;
+        for expected in 1..=totp::MAX_FAILURES {
+            assert_eq!(repos.totp.record_failure(1, now).unwrap(), expected);
+        }
+        assert!(repos.totp.find(1).unwrap().unwrap().is_locked(now));
+        assert!(!repos.totp.find(1).unwrap().unwrap().is_locked(now + totp::LOCKOUT_SECS));
+        assert_eq!(repos.totp.record_failure(1, now + totp::LOCKOUT_SECS).unwrap(), 1);
+        repos.totp.clear_failures(1).unwrap();
+        assert_eq!(repos.totp.find(1).unwrap().unwrap().failed_attempts, 0);
+        assert!(matches!(repos.totp.record_failure(2, now), Err(RepoError::NotFound)));
+
+        repos.totp.reset(1).unwrap();
+        assert!(matches!(repos.totp.reset(1), Err(RepoError::NotFound)));
+        assert_eq!(repos.totp.remaining_recovery_codes(1).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_api_tokens() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+
+        let new = NewApiToken {
+            name: "ci".to_string(),
+            token_hash: "HASH".to_string(),
+            scopes: vec![ApiScope::ReadParticipants, ApiScope::Export],
+            created_by: 1,
+            expires_at: Some("2030-01-01 00:00:00".to_string()),
+        };
+        let token = repos.api_tokens.create(&new).unwrap();
+        assert_eq!(token.scopes, vec![ApiScope::ReadParticipants, ApiScope::Export]);
+        assert!(matches!(repos.api_tokens.create(&new), Err(RepoError::Conflict)));
+        assert!(matches!(repos.api_tokens.find_by_hash("OTHER"), Err(RepoError::NotFound)));
+
+        repos.api_tokens.touch(token.id, "2025-12-01 12:00:00").unwrap();
+        let found = repos.api_tokens.find_by_hash("HASH").unwrap();
+        assert_eq!(found.last_used_at.as_deref(), Some("2025-12-01 12:00:00"));
+
+        repos.api_tokens.revoke(token.id).unwrap();
+        assert!(matches!(repos.api_tokens.revoke(token.id), Err(RepoError::Conflict)));
+        assert!(matches!(repos.api_tokens.revoke(42), Err(RepoError::NotFound)));
+        assert!(repos.api_tokens.list().unwrap()[0].revoked_at.is_some());
+    }
+
+    #[test]
+    fn test_sealed_draws() {
+        let Some(schema) = setup() else { return };
+        let repos = Repos::postgres(schema.pool.clone());
+        // l'admin ne participe pas
+        let ids: Vec<i64> = add_participants(&repos, 3).into_iter().filter(|id| *id != 1).collect();
+
+        let assignment = crate::commitment::random_assignment(&ids);
+        let new = NewCommitment { year: YEAR, commitment: "ROOT".to_string(), salt: "00".repeat(32), assignment: assignment.clone() };
+        assert_eq!(repos.commitments.create(&new).unwrap().assignment, assignment);
+        assert!(matches!(repos.commitments.create(&new), Err(RepoError::Conflict)));
+        assert!(repos.commitments.find(YEAR + 1).unwrap().is_none());
+
+        // le destinataire est celui de l'affectation, même désactivé depuis
+        let pairing = assignment[0];
+        let receiver = repos.access_codes.find(pairing.receiver_id).unwrap();
+        repos.access_codes.update(&AccessCode { active: false, ..receiver }).unwrap();
+        assert_eq!(repos.draws.draw(pairing.giver_id, YEAR).unwrap().id, pairing.receiver_id);
+        // hors affectation : aucun destinataire
+        assert!(matches!(repos.draws.draw(1, YEAR), Err(RepoError::NotFound)));
+
+        let revealed = repos.commitments.reveal(YEAR).unwrap();
+        assert!(revealed.revealed_at.is_some());
+        assert_eq!(repos.commitments.reveal(YEAR).unwrap().revealed_at, revealed.revealed_at);
+        assert!(matches!(repos.commitments.reveal(YEAR + 1), Err(RepoError::NotFound)));
+
+        // une année scellée ne s'annule pas ; un tirage commencé ne peut plus être scellé
+        assert!(matches!(repos.draws.reset(YEAR), Err(RepoError::Conflict)));
+        assert!(repos.commitments.find(YEAR).unwrap().is_some());
+        assert!(repos.draws.has_drawn(pairing.giver_id, YEAR).unwrap());
+        repos.draws.draw(pairing.giver_id, YEAR + 1).unwrap();
+        let next = NewCommitment { year: YEAR + 1, ..new };
+        assert!(matches!(repos.commitments.create(&next), Err(RepoError::Conflict)));
+        assert_eq!(repos.draws.reset(YEAR + 1).unwrap(), 1);
+        repos.commitments.create(&next).unwrap();
+    }
+}
@@ -0,0 +1,1052 @@
+use std::time::Duration;
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{named_params, params, Connection, ErrorCode, OptionalExtension, Row, ToSql, TransactionBehavior};
+
+use super::{
+    assignment_from_json, assignment_to_json, pick_receiver, sealed_receiver, AccessCodeRepo, AddressRepo, ApiTokenRepo, AuditRepo,
+    CodeQuery, CodeSort, CodeSortField, CodeWithDraw, CommitmentRepo, DrawRepo, InvitationRepo, LoginLinkRepo,
+    NewAccessCode, NewApiToken, NewCommitment, NewInvitation, Profile, RepoError, RepoResult, StoredAddress,
+    TotpCredential, TotpRepo,
+};
+use crate::audit::{AuditEntry, AuditEvent};
+use crate::db::SqlitePool;
+use crate::models::access_code::AccessCode;
+use crate::models::api_token::{join_scopes, split_scopes, ApiToken};
+use crate::models::commitment::DrawCommitment;
+use crate::models::draw::Draw;
+use crate::models::invitation::{Invitation, Signup, SignupStatus};
+use crate::totp;
+
+/// Nombre maximal de tentatives d'un tirage en cas de contention sur la base.
+const DRAW_ATTEMPTS: u32 = 20;
+
+const ACCESS_CODE_COLUMNS: &str = "
+    access_codes.id, access_codes.name, access_codes.code, access_codes.active, access_codes.locale,
+    access_codes.archived_at, access_codes.valid_from, access_codes.valid_until, access_codes.max_uses,
+    access_codes.last_login_at, access_codes.login_count, access_codes.notify_draw, access_codes.notify_reminders
+";
+
+impl From<rusqlite::Error> for RepoError {
+    fn from(e: rusqlite::Error) -> Self {
+        if let rusqlite::Error::QueryReturnedNoRows = e {
+            return RepoError::NotFound;
+        }
+        match e.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => RepoError::Conflict,
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => RepoError::Unavailable,
+            _ => RepoError::Backend(e.to_string()),
+        }
+    }
+}
+
+// Participants de `CodeQuery`, joints à leur tirage de l'année (index
+// `UNIQUE(giver_id, year)` de `draws`)
+const CODE_SEARCH_FROM: &str = "
+    FROM access_codes
+    LEFT JOIN draws ON draws.giver_id = access_codes.id AND draws.year = :year
+    WHERE (:include_archived OR access_codes.archived_at IS NULL)
+    AND (:name IS NULL OR access_codes.name LIKE :name ESCAPE '\\')
+    AND (:active IS NULL OR access_codes.active = :active)
+    AND (:drawn IS NULL OR (draws.id IS NOT NULL) = :drawn)
+";
+
+fn code_order_by(sort: CodeSort) -> String {
+    let dir = if sort.descending { "DESC" } else { "ASC" };
+    match sort.field {
+        CodeSortField::Id => format!("access_codes.id {dir}"),
+        CodeSortField::Name => format!("access_codes.name COLLATE NOCASE {dir}, access_codes.id"),
+        CodeSortField::LastLogin => format!(
+            "access_codes.last_login_at IS NOT NULL {dir}, access_codes.last_login_at {dir}, access_codes.id"
+        ),
+    }
+}
+
+fn access_code_from_row(row: &Row<'_>) -> rusqlite::Result<AccessCode> {
+    Ok(AccessCode {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        code: row.get(2)?,
+        active: row.get::<_, i64>(3)? != 0,
+        locale: row.get(4)?,
+        archived_at: row.get(5)?,
+        valid_from: row.get(6)?,
+        valid_until: row.get(7)?,
+        max_uses: row.get(8)?,
+        last_login_at: row.get(9)?,
+        login_count: row.get(10)?,
+        notify_draw: row.get::<_, i64>(11)? != 0,
+        notify_reminders: row.get::<_, i64>(12)? != 0,
+    })
+}
+
+/// Repositories SQLite sur le pool r2d2 existant.
+pub struct SqliteRepo {
+    pool: SqlitePool,
+}
+
+impl SqliteRepo {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    fn conn(&self) -> RepoResult<PooledConnection<SqliteConnectionManager>> {
+        self.pool.get().map_err(|e| {
+            log::warn!("db pool unavailable error={e}");
+            RepoError::Unavailable
+        })
+    }
+
+    fn query_access_codes(&self, filter: &str, params: impl rusqlite::Params) -> RepoResult<Vec<AccessCode>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes {filter}"))?;
+        let codes = stmt.query_map(params, access_code_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(codes)
+    }
+
+    fn query_access_code(&self, filter: &str, params: impl rusqlite::Params) -> RepoResult<AccessCode> {
+        let conn = self.conn()?;
+        let access_code = conn.query_row(
+            &format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes {filter}"),
+            params,
+            access_code_from_row,
+        )?;
+        Ok(access_code)
+    }
+}
+
+impl AccessCodeRepo for SqliteRepo {
+    fn list(&self, include_archived: bool) -> RepoResult<Vec<AccessCode>> {
+        self.query_access_codes("WHERE ?1 OR archived_at IS NULL ORDER BY id", params![include_archived])
+    }
+
+    fn search(&self, query: &CodeQuery) -> RepoResult<(Vec<CodeWithDraw>, i64)> {
+        let conn = self.conn()?;
+        let name = query.name_pattern();
+        let filter = named_params! {
+            ":year": query.year,
+            ":include_archived": query.include_archived,
+            ":name": name,
+            ":active": query.active,
+            ":drawn": query.drawn,
+        };
+        let total: i64 = conn.query_row(&format!("SELECT COUNT(*) {CODE_SEARCH_FROM}"), filter, |row| row.get(0))?;
+
+        let mut page: Vec<(&str, &dyn ToSql)> = filter.to_vec();
+        let offset = query.offset();
+        page.extend(named_params! { ":limit": query.per_page, ":offset": offset });
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ACCESS_CODE_COLUMNS}, draws.receiver_id {CODE_SEARCH_FROM} ORDER BY {} LIMIT :limit OFFSET :offset",
+            code_order_by(query.sort)
+        ))?;
+        let codes = stmt.query_map(page.as_slice(), |row| {
+            Ok(CodeWithDraw { access_code: access_code_from_row(row)?, receiver_id: row.get(13)? })
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok((codes, total))
+    }
+
+    fn list_active(&self) -> RepoResult<Vec<AccessCode>> {
+        self.query_access_codes("WHERE active = 1 AND archived_at IS NULL ORDER BY name", [])
+    }
+
+    fn find(&self, id: i64) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE id = ?1", params![id])
+    }
+
+    fn find_active(&self, id: i64) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE id = ?1 AND active = 1 AND archived_at IS NULL", params![id])
+    }
+
+    fn find_active_by_code(&self, code: &str) -> RepoResult<AccessCode> {
+        self.query_access_code("WHERE code = ?1 AND active = 1 AND archived_at IS NULL", params![code])
+    }
+
+    fn create(&self, new: &NewAccessCode) -> RepoResult<AccessCode> {
+        let conn = self.conn()?;
+        conn.execute(
+            "
+            INSERT INTO access_codes (name, code, active, valid_from, valid_until, max_uses)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ",
+            params![new.name, new.code, new.active, new.valid_from, new.valid_until, new.max_uses],
+        )?;
+        Ok(AccessCode {
+            id: conn.last_insert_rowid(),
+            name: new.name.clone(),
+            code: new.code.clone(),
+            active: new.active,
+            locale: None,
+            archived_at: None,
+            valid_from: new.valid_from.clone(),
+            valid_until: new.valid_until.clone(),
+            max_uses: new.max_uses,
+            last_login_at: None,
+            login_count: 0,
+            notify_draw: false,
+            notify_reminders: false,
+        })
+    }
+
+    fn update(&self, access_code: &AccessCode) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            UPDATE access_codes
+            SET name = ?1, code = ?2, active = ?3, valid_from = ?4, valid_until = ?5, max_uses = ?6
+            WHERE id = ?7
+            ",
+            params![
+                access_code.name,
+                access_code.code,
+                access_code.active,
+                access_code.valid_from,
+                access_code.valid_until,
+                access_code.max_uses,
+                access_code.id,
+            ],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn record_login(&self, id: i64, now: &str) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            UPDATE access_codes SET login_count = login_count + 1, last_login_at = ?2
+            WHERE id = ?1 AND (max_uses IS NULL OR login_count < max_uses)
+            ",
+            params![id, now],
+        )?;
+        if rows == 0 {
+            return Err(missing_or_conflict(&conn, id)?);
+        }
+        Ok(())
+    }
+
+    fn archive(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "UPDATE access_codes SET archived_at = CURRENT_TIMESTAMP WHERE id = ?1 AND archived_at IS NULL",
+            params![id],
+        )?;
+        if rows == 0 {
+            return Err(missing_or_conflict(&conn, id)?);
+        }
+        Ok(())
+    }
+
+    fn restore(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "UPDATE access_codes SET archived_at = NULL WHERE id = ?1 AND archived_at IS NOT NULL",
+            params![id],
+        )?;
+        if rows == 0 {
+            return Err(missing_or_conflict(&conn, id)?);
+        }
+        Ok(())
+    }
+
+    fn purge(&self, id: i64) -> RepoResult<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let archived: Option<String> = tx.query_row(
+            "SELECT archived_at FROM access_codes WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if archived.is_none() {
+            return Err(RepoError::Conflict);
+        }
+        tx.execute("DELETE FROM draws WHERE giver_id = ?1 OR receiver_id = ?1", params![id])?;
+        tx.execute("DELETE FROM shipping_addresses WHERE access_code_id = ?1", params![id])?;
+        tx.execute("DELETE FROM login_links WHERE access_code_id = ?1", params![id])?;
+        tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = ?1", params![id])?;
+        tx.execute("DELETE FROM totp_credentials WHERE access_code_id = ?1", params![id])?;
+        tx.execute("DELETE FROM api_tokens WHERE created_by = ?1", params![id])?;
+        tx.execute("UPDATE signups SET access_code_id = NULL WHERE access_code_id = ?1", params![id])?;
+        tx.execute("DELETE FROM access_codes WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_locale(&self, id: i64, locale: &str) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "UPDATE access_codes SET locale = ?1 WHERE id = ?2",
+            params![locale, id],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn update_profile(&self, id: i64, profile: &Profile) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            UPDATE access_codes SET name = ?1, locale = ?2, notify_draw = ?3, notify_reminders = ?4
+            WHERE id = ?5
+            ",
+            params![profile.name, profile.locale, profile.notify_draw, profile.notify_reminders, id],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn set_code(&self, id: i64, code: &str) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute("UPDATE access_codes SET code = ?1 WHERE id = ?2", params![code, id])?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        Ok(())
+    }
+}
+
+// Mise à jour sans effet : code inexistant, ou déjà dans l'état demandé
+fn missing_or_conflict(conn: &Connection, id: i64) -> RepoResult<RepoError> {
+    let exists = conn.query_row("SELECT COUNT(*) FROM access_codes WHERE id = ?1", params![id], |row| row.get::<_, i64>(0))? > 0;
+    Ok(if exists { RepoError::Conflict } else { RepoError::NotFound })
+}
+
+// Erreurs dues à un tirage concurrent : la transaction peut être rejouée
+fn is_retryable(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked | ErrorCode::ConstraintViolation)
+    )
+}
+
+fn try_draw(conn: &mut Connection, giver_id: i64, year: i32) -> rusqlite::Result<RepoResult<AccessCode>> {
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let already_drawn = tx.query_row(
+        "SELECT COUNT(*) FROM draws WHERE giver_id = ?1 AND year = ?2",
+        params![giver_id, year],
+        |row| row.get::<_, i64>(0),
+    )? > 0;
+    if already_drawn {
+        return Ok(Err(RepoError::Conflict));
+    }
+
+    // année scellée : le destinataire est celui de l'affectation engagée,
+    // même s'il a été désactivé ou archivé depuis
+    let sealed = tx.query_row(
+        "SELECT assignment FROM draw_commitments WHERE year = ?1",
+        params![year],
+        |row| row.get::<_, String>(0),
+    ).optional()?;
+    if let Some(assignment) = sealed {
+        let receiver_id = match assignment_from_json(&assignment).and_then(|assignment| sealed_receiver(&assignment, giver_id)) {
+            Ok(receiver_id) => receiver_id,
+            Err(e) => return Ok(Err(e)),
+        };
+        let receiver = tx.query_row(
+            &format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes WHERE id = ?1"),
+            params![receiver_id],
+            access_code_from_row,
+        )?;
+        tx.execute(
+            "INSERT INTO draws (giver_id, receiver_id, year) VALUES (?1, ?2, ?3)",
+            params![giver_id, receiver.id, year],
+        )?;
+        tx.commit()?;
+        return Ok(Ok(receiver));
+    }
+
+    // destinataires possibles : actifs, non archivés, autres que le demandeur, pas encore tirés
+    let candidates = {
+        let mut stmt = tx.prepare(&format!("
+        SELECT {ACCESS_CODE_COLUMNS}
+        FROM access_codes
+        WHERE
+            access_codes.id != ?1
+            AND access_codes.active == 1
+            AND access_codes.archived_at IS NULL
+            AND access_codes.id NOT IN (SELECT receiver_id FROM draws WHERE year == ?2)
+        "))?;
+        stmt.query_map(params![giver_id, year], access_code_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    let Some(receiver) = pick_receiver(candidates) else {
+        return Ok(Err(RepoError::NotFound));
+    };
+
+    tx.execute(
+        "INSERT INTO draws (giver_id, receiver_id, year) VALUES (?1, ?2, ?3)",
+        params![giver_id, receiver.id, year],
+    )?;
+    tx.commit()?;
+
+    Ok(Ok(receiver))
+}
+
+const ORPHAN_DRAWS_FILTER: &str = "
+    WHERE giver_id NOT IN (SELECT id FROM access_codes)
+    OR receiver_id NOT IN (SELECT id FROM access_codes)
+";
+
+fn query_draws(conn: &Connection, filter: &str) -> rusqlite::Result<Vec<Draw>> {
+    let mut stmt = conn.prepare(&format!("SELECT id, giver_id, receiver_id, year, created_at FROM draws {filter} ORDER BY id"))?;
+    let draws = stmt.query_map([], |row| {
+        Ok(Draw {
+            id: row.get(0)?,
+            giver_id: row.get(1)?,
+            receiver_id: row.get(2)?,
+            year: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    })?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(draws)
+}
+
+impl DrawRepo for SqliteRepo {
+    fn list(&self) -> RepoResult<Vec<Draw>> {
+        let conn = self.conn()?;
+        Ok(query_draws(&conn, "")?)
+    }
+
+    fn has_drawn(&self, giver_id: i64, year: i32) -> RepoResult<bool> {
+        let conn = self.conn()?;
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM draws WHERE giver_id = ?1 AND year = ?2",
+            params![giver_id, year],
+            |row| row.get::<_, i64>(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    fn receiver_of(&self, giver_id: i64, year: i32) -> RepoResult<Option<AccessCode>> {
+        let conn = self.conn()?;
+        let receiver = conn.query_row(
+            &format!("
+            SELECT {ACCESS_CODE_COLUMNS}
+            FROM draws
+            INNER JOIN access_codes ON draws.receiver_id = access_codes.id
+            WHERE draws.giver_id = ?1 AND draws.year = ?2
+            "),
+            params![giver_id, year],
+            access_code_from_row,
+        ).optional()?;
+        Ok(receiver)
+    }
+
+    /// Le choix et l'insertion forment une seule transaction `BEGIN IMMEDIATE` :
+    /// deux tirages simultanés ne peuvent pas choisir le même destinataire. Une
+    /// transaction refusée (base verrouillée, contrainte violée) est rejouée.
+    fn draw(&self, giver_id: i64, year: i32) -> RepoResult<AccessCode> {
+        let mut conn = self.conn()?;
+        for attempt in 1..=DRAW_ATTEMPTS {
+            match try_draw(&mut conn, giver_id, year) {
+                Ok(result) => return result,
+                Err(e) if is_retryable(&e) => {
+                    log::debug!("draw retry giver={giver_id} attempt={attempt} error={e}");
+                    std::thread::sleep(Duration::from_millis(2 * attempt as u64));
+                }
+                Err(e) => return Err(RepoError::Backend(e.to_string())),
+            }
+        }
+        log::warn!("draw giving up giver={giver_id} attempts={DRAW_ATTEMPTS}");
+        Err(RepoError::Unavailable)
+    }
+
+    fn reset(&self, year: i32) -> RepoResult<usize> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let sealed = tx.query_row("SELECT 1 FROM draw_commitments WHERE year = ?1", params![year], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if sealed {
+            return Err(RepoError::Conflict);
+        }
+        let deleted = tx.execute("DELETE FROM draws WHERE year = ?1", params![year])?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    fn orphans(&self) -> RepoResult<Vec<Draw>> {
+        let conn = self.conn()?;
+        Ok(query_draws(&conn, ORPHAN_DRAWS_FILTER)?)
+    }
+
+    fn remove_orphans(&self) -> RepoResult<Vec<Draw>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let orphans = query_draws(&tx, ORPHAN_DRAWS_FILTER)?;
+        tx.execute(&format!("DELETE FROM draws {ORPHAN_DRAWS_FILTER}"), [])?;
+        tx.commit()?;
+        Ok(orphans)
+    }
+
+    fn count_by_year(&self) -> RepoResult<Vec<(i32, i64)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT year, COUNT(*) FROM draws GROUP BY year ORDER BY year")?;
+        let counts = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    }
+}
+
+impl AuditRepo for SqliteRepo {
+    fn record(&self, event: &AuditEvent) {
+        let result = self.conn().map_err(|e| format!("{e:?}")).and_then(|conn| {
+            conn.execute(
+                "
+                INSERT INTO audit_log (actor_id, actor_name, action, target_id, before, after, detail)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ",
+                params![
+                    event.actor_id,
+                    event.actor_name,
+                    event.action.as_str(),
+                    event.target_id,
+                    event.before.as_ref().map(|v| v.to_string()),
+                    event.after.as_ref().map(|v| v.to_string()),
+                    event.detail,
+                ],
+            ).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            log::error!("audit action={} error={e}", event.action.as_str());
+        }
+    }
+
+    fn list(&self, page: u32, per_page: u32) -> RepoResult<(Vec<AuditEntry>, i64)> {
+        let conn = self.conn()?;
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))?;
+        let offset = (page.max(1) - 1) as i64 * per_page as i64;
+        let mut stmt = conn.prepare(
+            "
+            SELECT id, created_at, actor_id, actor_name, action, target_id, before, after, detail
+            FROM audit_log
+            ORDER BY id DESC
+            LIMIT ?1 OFFSET ?2
+            ",
+        )?;
+        let entries = stmt.query_map(params![per_page, offset], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                actor_id: row.get(2)?,
+                actor_name: row.get(3)?,
+                action: row.get(4)?,
+                target_id: row.get(5)?,
+                before: row.get(6)?,
+                after: row.get(7)?,
+                detail: row.get(8)?,
+            })
+        })?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok((entries, total))
+    }
+}
+
+impl AddressRepo for SqliteRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<StoredAddress>> {
+        let conn = self.conn()?;
+        let address = conn.query_row(
+            "SELECT nonce, ciphertext FROM shipping_addresses WHERE access_code_id = ?1",
+            params![access_code_id],
+            |row| Ok(StoredAddress { nonce: row.get(0)?, ciphertext: row.get(1)? }),
+        ).optional()?;
+        Ok(address)
+    }
+
+    fn save(&self, access_code_id: i64, address: &StoredAddress) -> RepoResult<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "
+            INSERT INTO shipping_addresses (access_code_id, nonce, ciphertext, updated_at)
+            VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+            ON CONFLICT(access_code_id) DO UPDATE SET
+                nonce = excluded.nonce,
+                ciphertext = excluded.ciphertext,
+                updated_at = excluded.updated_at
+            ",
+            params![access_code_id, address.nonce, address.ciphertext],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, access_code_id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM shipping_addresses WHERE access_code_id = ?1", params![access_code_id])?;
+        Ok(())
+    }
+}
+
+impl LoginLinkRepo for SqliteRepo {
+    fn create(&self, access_code_id: i64, expires_at: i64) -> RepoResult<i64> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO login_links (access_code_id, expires_at) VALUES (?1, ?2)",
+            params![access_code_id, expires_at],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn consume(&self, link_id: i64, access_code_id: i64, now: i64) -> RepoResult<bool> {
+        let conn = self.conn()?;
+        let consumed = conn.execute(
+            "
+            UPDATE login_links SET used_at = CURRENT_TIMESTAMP
+            WHERE id = ?1 AND access_code_id = ?2 AND used_at IS NULL AND expires_at > ?3
+            ",
+            params![link_id, access_code_id, now],
+        )?;
+        Ok(consumed > 0)
+    }
+}
+
+// Les inscriptions refusées ne comptent pas dans le plafond d'un lien
+const INVITATION_COLUMNS: &str = "
+    invitations.id, invitations.token, invitations.label, invitations.max_signups, invitations.expires_at,
+    invitations.revoked_at, invitations.created_at,
+    (SELECT COUNT(*) FROM signups WHERE signups.invitation_id = invitations.id AND signups.status != 'rejected')
+";
+
+const SIGNUP_SELECT: &str = "
+    SELECT signups.id, signups.invitation_id, invitations.label, signups.name, signups.code, signups.status,
+        signups.created_at, signups.decided_at, signups.access_code_id
+    FROM signups
+    INNER JOIN invitations ON invitations.id = signups.invitation_id
+";
+
+fn invitation_from_row(row: &Row<'_>) -> rusqlite::Result<Invitation> {
+    Ok(Invitation {
+        id: row.get(0)?,
+        token: row.get(1)?,
+        label: row.get(2)?,
+        max_signups: row.get(3)?,
+        expires_at: row.get(4)?,
+        revoked_at: row.get(5)?,
+        created_at: row.get(6)?,
+        signup_count: row.get(7)?,
+    })
+}
+
+fn signup_from_row(row: &Row<'_>) -> rusqlite::Result<Signup> {
+    let status: String = row.get(5)?;
+    Ok(Signup {
+        id: row.get(0)?,
+        invitation_id: row.get(1)?,
+        invitation_label: row.get(2)?,
+        name: row.get(3)?,
+        code: row.get(4)?,
+        status: SignupStatus::parse(&status).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, format!("unknown signup status {status}").into())
+        })?,
+        created_at: row.get(6)?,
+        decided_at: row.get(7)?,
+        access_code_id: row.get(8)?,
+    })
+}
+
+fn find_signup(conn: &Connection, id: i64) -> rusqlite::Result<Signup> {
+    conn.query_row(&format!("{SIGNUP_SELECT} WHERE signups.id = ?1"), params![id], signup_from_row)
+}
+
+// Passe une inscription en attente dans l'état `status`
+fn decide_signup(conn: &Connection, id: i64, status: SignupStatus) -> RepoResult<()> {
+    let rows = conn.execute(
+        "UPDATE signups SET status = ?1, decided_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'pending'",
+        params![status.as_str(), id],
+    )?;
+    if rows == 0 {
+        // inconnue (NotFound) ou déjà traitée
+        find_signup(conn, id)?;
+        return Err(RepoError::Conflict);
+    }
+    Ok(())
+}
+
+impl InvitationRepo for SqliteRepo {
+    fn list(&self) -> RepoResult<Vec<Invitation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT {INVITATION_COLUMNS} FROM invitations ORDER BY id DESC"))?;
+        let invitations = stmt.query_map([], invitation_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(invitations)
+    }
+
+    fn create(&self, new: &NewInvitation) -> RepoResult<Invitation> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO invitations (token, label, max_signups, expires_at) VALUES (?1, ?2, ?3, ?4)",
+            params![new.token, new.label, new.max_signups, new.expires_at],
+        )?;
+        let invitation = conn.query_row(
+            &format!("SELECT {INVITATION_COLUMNS} FROM invitations WHERE id = ?1"),
+            params![conn.last_insert_rowid()],
+            invitation_from_row,
+        )?;
+        Ok(invitation)
+    }
+
+    fn find_by_token(&self, token: &str) -> RepoResult<Invitation> {
+        let conn = self.conn()?;
+        let invitation = conn.query_row(
+            &format!("SELECT {INVITATION_COLUMNS} FROM invitations WHERE token = ?1"),
+            params![token],
+            invitation_from_row,
+        )?;
+        Ok(invitation)
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "UPDATE invitations SET revoked_at = CURRENT_TIMESTAMP WHERE id = ?1 AND revoked_at IS NULL",
+            params![id],
+        )?;
+        if rows == 0 {
+            let exists = conn.query_row("SELECT COUNT(*) FROM invitations WHERE id = ?1", params![id], |row| row.get::<_, i64>(0))? > 0;
+            return Err(if exists { RepoError::Conflict } else { RepoError::NotFound });
+        }
+        Ok(())
+    }
+
+    /// Le contrôle du plafond et l'insertion forment une seule transaction
+    /// `BEGIN IMMEDIATE` : deux inscriptions simultanées ne peuvent pas
+    /// dépasser `max_signups`.
+    fn sign_up(&self, invitation_id: i64, name: &str, code: &str, now: &str) -> RepoResult<Option<Signup>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let invitation = tx.query_row(
+            &format!("SELECT {INVITATION_COLUMNS} FROM invitations WHERE id = ?1"),
+            params![invitation_id],
+            invitation_from_row,
+        )?;
+        if !invitation.is_open_at(now) {
+            return Ok(None);
+        }
+        let taken = tx.query_row("SELECT COUNT(*) FROM access_codes WHERE code = ?1", params![code], |row| row.get::<_, i64>(0))? > 0;
+        if taken {
+            return Err(RepoError::Conflict);
+        }
+        tx.execute(
+            "INSERT INTO signups (invitation_id, name, code) VALUES (?1, ?2, ?3)",
+            params![invitation_id, name, code],
+        )?;
+        let signup = find_signup(&tx, tx.last_insert_rowid())?;
+        tx.commit()?;
+        Ok(Some(signup))
+    }
+
+    fn signups(&self, status: Option<SignupStatus>) -> RepoResult<Vec<Signup>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("{SIGNUP_SELECT} WHERE ?1 IS NULL OR signups.status = ?1 ORDER BY signups.id"))?;
+        let signups = stmt.query_map(params![status.map(SignupStatus::as_str)], signup_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(signups)
+    }
+
+    fn approve(&self, signup_id: i64) -> RepoResult<AccessCode> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        decide_signup(&tx, signup_id, SignupStatus::Approved)?;
+        let signup = find_signup(&tx, signup_id)?;
+        tx.execute(
+            "INSERT INTO access_codes (name, code, active) VALUES (?1, ?2, 1)",
+            params![signup.name, signup.code],
+        )?;
+        let access_code = tx.query_row(
+            &format!("SELECT {ACCESS_CODE_COLUMNS} FROM access_codes WHERE id = ?1"),
+            params![tx.last_insert_rowid()],
+            access_code_from_row,
+        )?;
+        tx.execute("UPDATE signups SET access_code_id = ?1 WHERE id = ?2", params![access_code.id, signup_id])?;
+        tx.commit()?;
+        Ok(access_code)
+    }
+
+    fn reject(&self, signup_id: i64) -> RepoResult<Signup> {
+        let conn = self.conn()?;
+        decide_signup(&conn, signup_id, SignupStatus::Rejected)?;
+        Ok(find_signup(&conn, signup_id)?)
+    }
+}
+
+impl TotpRepo for SqliteRepo {
+    fn find(&self, access_code_id: i64) -> RepoResult<Option<TotpCredential>> {
+        let conn = self.conn()?;
+        let credential = conn.query_row(
+            "
+            SELECT secret, confirmed_at, last_step, failed_attempts, last_failure_at
+            FROM totp_credentials WHERE access_code_id = ?1
+            ",
+            params![access_code_id],
+            |row| Ok(TotpCredential {
+                secret: row.get(0)?,
+                confirmed_at: row.get(1)?,
+                last_step: row.get(2)?,
+                failed_attempts: row.get(3)?,
+                last_failure_at: row.get(4)?,
+            }),
+        ).optional()?;
+        Ok(credential)
+    }
+
+    fn begin_enrollment(&self, access_code_id: i64, secret: &[u8]) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            INSERT INTO totp_credentials (access_code_id, secret) VALUES (?1, ?2)
+            ON CONFLICT(access_code_id) DO UPDATE SET
+                secret = excluded.secret,
+                last_step = NULL,
+                failed_attempts = 0,
+                last_failure_at = NULL,
+                created_at = CURRENT_TIMESTAMP
+            WHERE totp_credentials.confirmed_at IS NULL
+            ",
+            params![access_code_id, secret],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::Conflict);
+        }
+        Ok(())
+    }
+
+    fn confirm(&self, access_code_id: i64, step: i64, recovery_hashes: &[String]) -> RepoResult<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let rows = tx.execute(
+            "
+            UPDATE totp_credentials SET confirmed_at = CURRENT_TIMESTAMP, last_step = ?2
+            WHERE access_code_id = ?1 AND confirmed_at IS NULL
+            ",
+            params![access_code_id, step],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::Conflict);
+        }
+        tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = ?1", params![access_code_id])?;
+        for hash in recovery_hashes {
+            tx.execute(
+                "INSERT INTO totp_recovery_codes (access_code_id, code_hash) VALUES (?1, ?2)",
+                params![access_code_id, hash],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn record_step(&self, access_code_id: i64, step: i64) -> RepoResult<bool> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            UPDATE totp_credentials SET last_step = ?2
+            WHERE access_code_id = ?1 AND confirmed_at IS NOT NULL AND (last_step IS NULL OR last_step < ?2)
+            ",
+            params![access_code_id, step],
+        )?;
+        Ok(rows > 0)
+    }
+
+    fn use_recovery_code(&self, access_code_id: i64, code_hash: &str) -> RepoResult<bool> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "
+            UPDATE totp_recovery_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE id = (
+                SELECT id FROM totp_recovery_codes
+                WHERE access_code_id = ?1 AND code_hash = ?2 AND used_at IS NULL
+                LIMIT 1
+            )
+            ",
+            params![access_code_id, code_hash],
+        )?;
+        Ok(rows > 0)
+    }
+
+    fn record_failure(&self, access_code_id: i64, now: i64) -> RepoResult<i64> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let rows = tx.execute(
+            "
+            UPDATE totp_credentials SET
+                failed_attempts = CASE WHEN last_failure_at > ?3 THEN failed_attempts + 1 ELSE 1 END,
+                last_failure_at = ?2
+            WHERE access_code_id = ?1
+            ",
+            params![access_code_id, now, now - totp::LOCKOUT_SECS],
+        )?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        let failures = tx.query_row(
+            "SELECT failed_attempts FROM totp_credentials WHERE access_code_id = ?1",
+            params![access_code_id],
+            |row| row.get(0),
+        )?;
+        tx.commit()?;
+        Ok(failures)
+    }
+
+    fn clear_failures(&self, access_code_id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE totp_credentials SET failed_attempts = 0, last_failure_at = NULL WHERE access_code_id = ?1",
+            params![access_code_id],
+        )?;
+        Ok(())
+    }
+
+    fn remaining_recovery_codes(&self, access_code_id: i64) -> RepoResult<i64> {
+        let conn = self.conn()?;
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM totp_recovery_codes WHERE access_code_id = ?1 AND used_at IS NULL",
+            params![access_code_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    fn reset(&self, access_code_id: i64) -> RepoResult<()> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute("DELETE FROM totp_recovery_codes WHERE access_code_id = ?1", params![access_code_id])?;
+        let rows = tx.execute("DELETE FROM totp_credentials WHERE access_code_id = ?1", params![access_code_id])?;
+        if rows == 0 {
+            return Err(RepoError::NotFound);
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+const API_TOKEN_COLUMNS: &str = "id, name, scopes, created_by, created_at, expires_at, revoked_at, last_used_at";
+
+fn api_token_from_row(row: &Row<'_>) -> rusqlite::Result<ApiToken> {
+    let scopes: String = row.get(2)?;
+    Ok(ApiToken {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        scopes: split_scopes(&scopes),
+        created_by: row.get(3)?,
+        created_at: row.get(4)?,
+        expires_at: row.get(5)?,
+        revoked_at: row.get(6)?,
+        last_used_at: row.get(7)?,
+    })
+}
+
+impl ApiTokenRepo for SqliteRepo {
+    fn list(&self) -> RepoResult<Vec<ApiToken>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens ORDER BY id DESC"))?;
+        let tokens = stmt.query_map([], api_token_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tokens)
+    }
+
+    fn create(&self, new: &NewApiToken) -> RepoResult<ApiToken> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO api_tokens (name, token_hash, scopes, created_by, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![new.name, new.token_hash, join_scopes(&new.scopes), new.created_by, new.expires_at],
+        )?;
+        let token = conn.query_row(
+            &format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE id = ?1"),
+            params![conn.last_insert_rowid()],
+            api_token_from_row,
+        )?;
+        Ok(token)
+    }
+
+    fn find_by_hash(&self, token_hash: &str) -> RepoResult<ApiToken> {
+        let conn = self.conn()?;
+        let token = conn.query_row(
+            &format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE token_hash = ?1"),
+            params![token_hash],
+            api_token_from_row,
+        )?;
+        Ok(token)
+    }
+
+    fn revoke(&self, id: i64) -> RepoResult<()> {
+        let conn = self.conn()?;
+        let rows = conn.execute(
+            "UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP WHERE id = ?1 AND revoked_at IS NULL",
+            params![id],
+        )?;
+        if rows == 0 {
+            let exists = conn.query_row("SELECT COUNT(*) FROM api_tokens WHERE id = ?1", params![id], |row| row.get::<_, i64>(0))? > 0;
+            return Err(if exists { RepoError::Conflict } else { RepoError::NotFound });
+        }
+        Ok(())
+    }
+
+    fn touch(&self, id: i64, now: &str) -> RepoResult<()> {
+        let conn = self.conn()?;
+        conn.execute("UPDATE api_tokens SET last_used_at = ?1 WHERE id = ?2", params![now, id])?;
+        Ok(())
+    }
+}
+
+const COMMITMENT_COLUMNS: &str = "year, commitment, salt, assignment, created_at, revealed_at";
+
+fn commitment_from_row(row: &Row<'_>) -> rusqlite::Result<(DrawCommitment, String)> {
+    Ok((
+        DrawCommitment {
+            year: row.get(0)?,
+            commitment: row.get(1)?,
+            salt: row.get(2)?,
+            assignment: Vec::new(),
+            created_at: row.get(4)?,
+            revealed_at: row.get(5)?,
+        },
+        row.get(3)?,
+    ))
+}
+
+fn find_commitment(conn: &Connection, year: i32) -> RepoResult<Option<DrawCommitment>> {
+    let row = conn.query_row(
+        &format!("SELECT {COMMITMENT_COLUMNS} FROM draw_commitments WHERE year = ?1"),
+        params![year],
+        commitment_from_row,
+    ).optional()?;
+    row.map(|(commitment, assignment)| Ok(DrawCommitment { assignment: assignment_from_json(&assignment)?, ..commitment }))
+        .transpose()
+}
+
+impl CommitmentRepo for SqliteRepo {
+    fn find(&self, year: i32) -> RepoResult<Option<DrawCommitment>> {
+        let conn = self.conn()?;
+        find_commitment(&conn, year)
+    }
+
+    fn create(&self, new: &NewCommitment) -> RepoResult<DrawCommitment> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let drawn = tx.query_row("SELECT COUNT(*) FROM draws WHERE year = ?1", params![new.year], |row| row.get::<_, i64>(0))? > 0;
+        if drawn {
+            return Err(RepoError::Conflict);
+        }
+        tx.execute(
+            "INSERT INTO draw_commitments (year, commitment, salt, assignment) VALUES (?1, ?2, ?3, ?4)",
+            params![new.year, new.commitment, new.salt, assignment_to_json(&new.assignment)],
+        )?;
+        let commitment = find_commitment(&tx, new.year)?.ok_or(RepoError::NotFound)?;
+        tx.commit()?;
+        Ok(commitment)
+    }
+
+    fn reveal(&self, year: i32) -> RepoResult<DrawCommitment> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE draw_commitments SET revealed_at = CURRENT_TIMESTAMP WHERE year = ?1 AND revealed_at IS NULL",
+            params![year],
+        )?;
+        find_commitment(&conn, year)?.ok_or(RepoError::NotFound)
+    }
+}
@@ -1,244 +1,842 @@
-use rocket::response::content::RawHtml;
 use rocket::serde::json::Json;
-use rocket::response::Redirect;
 use rocket::response::status::Created;
-use rocket::http::Status;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{Header, Status};
+use rocket::response::status::Custom;
 use rocket::State;
-use rusqlite::params;
+use std::path::Path;
 use rocket_dyn_templates::{Template, context};
 use serde_json::json;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::auth::AuthenticatedUser;
+use crate::audit::{AuditAction, AuditEvent};
+use crate::backup::{self, BackupError, RestoreReport, TempFile};
+use crate::auth::{scope, AdminAccess, AdminUser, AuthenticatedUser};
+use crate::card::render_qr_svg;
+use crate::csrf::{CsrfToken, CsrfVerified};
+use crate::events::{LiveEvent, LiveEventKind};
+use crate::i18n::Locale;
+use crate::login_link::LinkClaims;
+use crate::openapi::SqliteSnapshot;
 use crate::state::AppState;
-use crate::models::access_code::AccessCode;
+use crate::models::access_code::{parse_timestamp, AccessCode};
 use crate::models::draw::Draw;
+use crate::repo::{CodeQuery, CodeSort, CodeWithDraw, NewAccessCode, RepoError};
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
 pub struct AccessCodeWithDraw {
     pub id: i64,
     pub name: String,
-    pub code: String,
+    /// Absent pour un jeton d'API : les codes d'accès ne sont remis qu'à la
+    /// session de l'admin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     pub active: bool,
     pub drawn: bool,
     pub receiver_id: Option<i64>,
     pub year: Option<i32>,
+    #[serde(default)]
+    pub archived_at: Option<String>,
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    #[serde(default)]
+    pub max_uses: Option<i64>,
+    #[serde(default)]
+    pub last_login_at: Option<String>,
+    #[serde(default)]
+    pub login_count: i64,
+    /// Hors de sa fenêtre de validité ou nombre de connexions atteint.
+    #[serde(default)]
+    pub expired: bool,
 }
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, ToSchema)]
 pub struct CreateAccessCode {
     pub name: String,
     pub code: String,
     pub active: bool,
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<String>,
+    #[serde(default)]
+    pub max_uses: Option<i64>,
 }
 
-#[get("/admin")]
-pub fn admin_panel(_auth: AuthenticatedUser, state: &State<AppState>) -> Template {
-    let current_access_name = state
-        .current_access_code
-        .lock()
-        .unwrap()
-        .as_ref()
-        .map(|ac| ac.name.clone());
-    let conn: r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager> = state.db_pool.get().expect("db connection");
-    let user_id = state.current_access_code.lock().unwrap().as_ref().unwrap().id;
-    let access_code_res = conn.query_row(
-        "SELECT * FROM access_codes WHERE id = ?1 AND active = 1",
-        params![user_id],
-        |row| {
-            Ok(AccessCode {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                code: row.get(2)?,
-                active: row.get::<_, i64>(3)? != 0,
-            })
-        },
-    );
+// Fenêtre de validité saisie par l'admin, normalisée ; `422` si elle est
+// illisible ou incohérente
+fn parse_validity(
+    valid_from: Option<&str>,
+    valid_until: Option<&str>,
+    max_uses: Option<i64>,
+) -> Result<(Option<String>, Option<String>), Status> {
+    let parse = |input: Option<&str>| match input.map(str::trim).filter(|input| !input.is_empty()) {
+        Some(input) => parse_timestamp(input).map(Some).ok_or(Status::UnprocessableEntity),
+        None => Ok(None),
+    };
+    let (from, until) = (parse(valid_from)?, parse(valid_until)?);
+    if matches!((&from, &until), (Some(from), Some(until)) if from > until) || max_uses.is_some_and(|max| max < 1) {
+        return Err(Status::UnprocessableEntity);
+    }
+    Ok((from, until))
+}
 
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct LoginLink {
+    pub url: String,
+    pub expires_at: String,
+    pub qr_svg: String,
+}
+
+#[get("/admin")]
+pub fn admin_panel(auth: AuthenticatedUser, locale: Locale, csrf: CsrfToken) -> Template {
     // Redirect to home if not admin
-    if user_id != 1 {
+    if auth.access_code.id != 1 {
         return Template::render("index", context! {
+            lang: locale.0,
+            csrf_token: csrf.0,
             is_authenticated: true,
-            current_access_code: access_code_res.ok()
+            current_access_code: auth.access_code
         });
     }
 
     Template::render("admin", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
         is_authenticated: true,
-        current_access_code_name: current_access_name,
-        current_access_code: access_code_res.ok()
+        current_access_code_name: &auth.access_code.name,
+        current_access_code: &auth.access_code
     })
 }
 
-#[get("/admin/api/codes")]
-pub fn list_access_codes(_auth: AuthenticatedUser, state: &State<AppState>) -> Result<Json<Vec<AccessCodeWithDraw>>, Status> {
-    let conn = state.db_pool.get().map_err(|_| Status::InternalServerError)?;
-    let mut stmt_access_codes = conn.prepare("SELECT id, name, code, active FROM access_codes")
-        .map_err(|_| Status::InternalServerError)?;
+/// Taille de page par défaut et maximale de la liste des participants.
+const CODES_PAGE_SIZE: u32 = 50;
+const CODES_MAX_PAGE_SIZE: u32 = 200;
 
-    let codes_iter = stmt_access_codes.query_map([], |row| {
-        let id: i64 = row.get(0)?;
-        let name: String = row.get(1)?;
-        let code: String = row.get(2)?;
-        // read stored integer and convert to bool
-        let active_int: i64 = row.get(3)?;
-        Ok(AccessCode {
-            id: id,
-            name,
-            code,
-            active: active_int != 0,
-        })
-    }).map_err(|_| Status::InternalServerError)?;
-
-    let mut stmt_draws = conn.prepare("SELECT id, giver_id, receiver_id, year, created_at FROM draws")
-        .map_err(|_| Status::InternalServerError)?;
+/// Paramètres de `GET /admin/api/codes`.
+#[derive(Debug, FromForm, IntoParams)]
+pub struct CodeListParams {
+    /// Année des tirages affichés (année en cours par défaut).
+    year: Option<i32>,
+    /// Recherche dans le nom.
+    q: Option<String>,
+    active: Option<bool>,
+    drawn: Option<bool>,
+    /// Inclure les participants archivés.
+    archived: Option<bool>,
+    /// `id`, `name` ou `last_login`, préfixé de `-` pour l'ordre décroissant.
+    sort: Option<String>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+}
 
-    let draws_iter = stmt_draws.query_map([], |row| {
-        let id: i64 = row.get(0)?;
-        let giver_id: i64 = row.get(1)?;
-        let receiver_id: i64 = row.get(2)?;
-        let year: i32 = row.get(3)?;
-        let created_at: String = row.get(4)?;
-        Ok(Draw {
-            id: id,
-            giver_id: giver_id,
-            receiver_id: receiver_id,
-            year: year,
-            created_at: created_at,
-        })
-    }).map_err(|_| Status::InternalServerError)?;
-
-
-    let codes: Vec<AccessCode> = codes_iter.filter_map(Result::ok).collect();
-    let draws: Vec<Draw> = draws_iter.filter_map(Result::ok).collect();
-
-    let codes_with_draws: Vec<AccessCodeWithDraw> = codes.into_iter().map(|code| {
-        let draw_opt = draws.iter().find(|draw| draw.giver_id == code.id);
-        if let Some(draw) = draw_opt {
-            AccessCodeWithDraw {
-                id: code.id,
-                name: code.name,
-                code: code.code,
-                active: code.active,
-                drawn: true,
-                receiver_id: Some(draw.receiver_id),
-                year: Some(draw.year),
-            }
-        } else {
-            AccessCodeWithDraw {
-                id: code.id,
-                name: code.name,
-                code: code.code,
-                active: code.active,
-                drawn: false,
-                receiver_id: None,
-                year: None,
-            }
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct AccessCodePage {
+    pub codes: Vec<AccessCodeWithDraw>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub pages: u32,
+    pub year: i32,
+}
+
+/// Liste paginée des participants, avec leur tirage de l'année demandée.
+///
+/// Filtres, tri et jointure avec les tirages de l'année sont faits en base.
+#[utoipa::path(
+    tag = "participants",
+    params(CodeListParams),
+    responses(
+        (status = 200, description = "Page de participants", body = AccessCodePage),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, ou droit du jeton d'API manquant"),
+        (status = 422, description = "Tri inconnu"),
+    ),
+    security(("session" = []), ("api_token" = ["participants:read"])),
+)]
+#[get("/admin/api/codes?<params..>")]
+pub async fn list_access_codes(admin: AdminAccess<scope::ReadParticipants>, params: CodeListParams, state: &State<AppState>) -> Result<Json<AccessCodePage>, Status> {
+    let sort = match params.sort.as_deref() {
+        Some(sort) => CodeSort::parse(sort).ok_or(Status::UnprocessableEntity)?,
+        None => CodeSort::default(),
+    };
+    let query = CodeQuery {
+        year: params.year.unwrap_or_else(|| state.clock.year()),
+        name: params.q.map(|q| q.trim().to_string()).filter(|q| !q.is_empty()),
+        active: params.active,
+        drawn: params.drawn,
+        include_archived: params.archived.unwrap_or(false),
+        sort,
+        page: params.page.unwrap_or(1).max(1),
+        per_page: params.per_page.unwrap_or(CODES_PAGE_SIZE).clamp(1, CODES_MAX_PAGE_SIZE),
+    };
+    let (page, per_page, year) = (query.page, query.per_page, query.year);
+    let (rows, total) = state.repos.run(move |repos| repos.access_codes.search(&query)).await?;
+
+    let now = state.clock.timestamp();
+    let with_codes = admin.api_token.is_none();
+    let codes = rows.into_iter().map(|CodeWithDraw { access_code: code, receiver_id }| {
+        let expired = !code.is_valid_at(&now) || code.uses_exhausted();
+        AccessCodeWithDraw {
+            id: code.id,
+            name: code.name,
+            code: with_codes.then_some(code.code),
+            active: code.active,
+            drawn: receiver_id.is_some(),
+            receiver_id,
+            year: receiver_id.map(|_| year),
+            archived_at: code.archived_at,
+            valid_from: code.valid_from,
+            valid_until: code.valid_until,
+            max_uses: code.max_uses,
+            last_login_at: code.last_login_at,
+            login_count: code.login_count,
+            expired,
         }
     }).collect();
 
-    Ok(Json(codes_with_draws))
+    Ok(Json(AccessCodePage {
+        codes,
+        total,
+        page,
+        per_page,
+        pages: (total as u32).div_ceil(per_page).max(1),
+        year,
+    }))
 }
 
+/// Crée un participant.
+#[utoipa::path(
+    tag = "participants",
+    request_body = CreateAccessCode,
+    responses(
+        (status = 201, description = "Participant créé", body = AccessCode),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 409, description = "Code déjà utilisé"),
+        (status = 422, description = "Fenêtre de validité ou nombre de connexions incohérent"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
 #[post("/admin/api/codes", data = "<code>")]
-pub fn create_access_code(_auth: AuthenticatedUser, code: Json<CreateAccessCode>, state: &State<AppState>) -> Result<Created<Json<AccessCode>>, Status> {
-    let conn = state.db_pool.get().map_err(|_| Status::InternalServerError)?;
-    
-    conn.execute(
-        "INSERT INTO access_codes (name, code, active) VALUES (?1, ?2, ?3)",
-        params![code.name, code.code, if code.active { 1 } else { 0 }],
-    ).map_err(|_| Status::InternalServerError)?;
-
-    let id = conn.last_insert_rowid();
-    let created_code = AccessCode {
-        id: id,
+pub async fn create_access_code(admin: AdminAccess<scope::WriteParticipants>, _csrf: CsrfVerified, code: Json<CreateAccessCode>, state: &State<AppState>) -> Result<Created<Json<AccessCode>>, Status> {
+    let (valid_from, valid_until) = parse_validity(code.valid_from.as_deref(), code.valid_until.as_deref(), code.max_uses)?;
+    let new = NewAccessCode {
         name: code.name.clone(),
         code: code.code.clone(),
         active: code.active,
+        valid_from,
+        valid_until,
+        max_uses: code.max_uses,
     };
+    let created_code = state.repos.run(move |repos| repos.access_codes.create(&new)).await?;
+    let id = created_code.id;
+    AuditEvent::new(AuditAction::CodeCreated)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(id)
+        .after(&created_code)
+        .record(&state.repos).await;
+    if created_code.active {
+        LiveEvent::new(LiveEventKind::Joined, state.clock.year())
+            .subject(&created_code)
+            .publish(state)
+            .await;
+    }
 
     Ok(Created::new("/admin/api/codes").body(Json(created_code)))
 }
 
+/// Remplace le nom, le code, l'état et la validité d'un participant.
+#[utoipa::path(
+    tag = "participants",
+    request_body = AccessCode,
+    responses(
+        (status = 200, description = "Participant modifié (`status`, `message`, `toast`)", body = serde_json::Value),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, jeton CSRF invalide, ou compte administrateur visé par un jeton d'API"),
+        (status = 404, description = "Participant inconnu"),
+        (status = 409, description = "Code déjà utilisé"),
+        (status = 422, description = "Fenêtre de validité ou nombre de connexions incohérent"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
 #[patch("/admin/api/codes/<id>", data = "<code>")]
-pub fn update_access_code(
-    _auth: AuthenticatedUser,
+pub async fn update_access_code(
+    admin: AdminAccess<scope::WriteParticipants>,
+    _csrf: CsrfVerified,
     id: i64,
     code: Json<AccessCode>,
+    locale: Locale,
     state: &State<AppState>
 ) -> Result<Json<serde_json::Value>, Status> {
-    let conn = state.db_pool.get().map_err(|_| Status::InternalServerError)?;
-    
-    let rows_affected = conn.execute(
-        "UPDATE access_codes SET name = ?1, code = ?2, active = ?3 WHERE id = ?4",
-        params![
-            code.name,
-            code.code,
-            if code.active { 1 } else { 0 },
-            id
-        ],
-    ).map_err(|_| Status::InternalServerError)?;
-
-    if rows_affected == 0 {
-        return Err(Status::NotFound);
+    // seule la session de l'admin peut modifier son propre compte
+    if admin.api_token.is_some() && id == ADMIN_ID {
+        return Err(Status::Forbidden);
+    }
+    let code = code.into_inner();
+    let (valid_from, valid_until) = parse_validity(code.valid_from.as_deref(), code.valid_until.as_deref(), code.max_uses)?;
+    let (before, after) = state.repos.run(move |repos| {
+        let before = repos.access_codes.find(id)?;
+        repos.access_codes.update(&AccessCode { id, locale: before.locale.clone(), valid_from, valid_until, ..code })?;
+        Ok((before, repos.access_codes.find(id).ok()))
+    }).await?;
+    AuditEvent::new(AuditAction::CodeUpdated)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(id)
+        .before(&before)
+        .after(&after)
+        .record(&state.repos).await;
+    if let Some(after) = after.as_ref().filter(|after| after.active != before.active && after.archived_at.is_none()) {
+        let kind = if after.active { LiveEventKind::Joined } else { LiveEventKind::Deactivated };
+        LiveEvent::new(kind, state.clock.year())
+            .subject(after)
+            .publish(state)
+            .await;
     }
 
+    let message = locale.t("api.code_updated");
     Ok(Json(json!({
         "status": "success",
-        "message": "Code mis à jour avec succès",
+        "message": message,
         "toast": {
             "type": "success",
-            "message": "Code mis à jour avec succès"
+            "message": message
         }
     })))
 }
 
+/// Id de l'access code administrateur, qui ne peut être ni archivé ni purgé.
+const ADMIN_ID: i64 = 1;
+
+/// Archive le participant : il disparaît de la connexion et du tirage, mais
+/// ses tirages passés sont conservés.
+#[utoipa::path(
+    tag = "participants",
+    responses(
+        (status = 204, description = "Participant archivé"),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, jeton CSRF invalide, ou participant administrateur"),
+        (status = 404, description = "Participant inconnu"),
+        (status = 409, description = "Participant déjà archivé"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
 #[delete("/admin/api/codes/<id>")]
-pub fn delete_access_code(_auth: AuthenticatedUser, id: i64, state: &State<AppState>) -> Result<Status, Status> {
-    let conn: r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager> = state.db_pool.get().map_err(|_| Status::InternalServerError)?;
-    conn.execute(
-        "DELETE FROM draws WHERE giver_id = ?1",
-        params![id],
-    ).map_err(|_| Status::InternalServerError)?;
-    let rows_affected: usize = conn.execute(
-        "DELETE FROM access_codes WHERE id = ?1",
-        params![id],
-    ).map_err(|_| Status::InternalServerError)?;
-    if rows_affected == 0 {
-        return Err(Status::NotFound);
+pub async fn delete_access_code(admin: AdminAccess<scope::WriteParticipants>, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Status, Status> {
+    if id == ADMIN_ID {
+        return Err(Status::Forbidden);
+    }
+    let before = state.repos.run(move |repos| {
+        let before = repos.access_codes.find(id)?;
+        repos.access_codes.archive(id)?;
+        Ok(before)
+    }).await?;
+    AuditEvent::new(AuditAction::CodeArchived)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(id)
+        .before(&before)
+        .record(&state.repos).await;
+    if before.active {
+        LiveEvent::new(LiveEventKind::Deactivated, state.clock.year())
+            .subject(&before)
+            .publish(state)
+            .await;
+    }
+    Ok(Status::NoContent)
+}
+
+/// Désarchive un participant.
+#[utoipa::path(
+    tag = "participants",
+    responses(
+        (status = 200, description = "Participant restauré", body = AccessCode),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 404, description = "Participant inconnu"),
+        (status = 409, description = "Participant non archivé"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
+#[post("/admin/api/codes/<id>/restore")]
+pub async fn restore_access_code(admin: AdminAccess<scope::WriteParticipants>, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Json<AccessCode>, Status> {
+    let restored = state.repos.run(move |repos| {
+        repos.access_codes.restore(id)?;
+        repos.access_codes.find(id)
+    }).await?;
+    AuditEvent::new(AuditAction::CodeRestored)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(id)
+        .after(&restored)
+        .record(&state.repos).await;
+    if restored.active {
+        LiveEvent::new(LiveEventKind::Joined, state.clock.year())
+            .subject(&restored)
+            .publish(state)
+            .await;
+    }
+    Ok(Json(restored))
+}
+
+/// Suppression définitive d'un participant archivé, avec ses tirages.
+#[utoipa::path(
+    tag = "participants",
+    responses(
+        (status = 204, description = "Participant supprimé"),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 404, description = "Participant inconnu"),
+        (status = 409, description = "Participant non archivé"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
+#[delete("/admin/api/codes/<id>/purge")]
+pub async fn purge_access_code(admin: AdminAccess<scope::WriteParticipants>, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Status, Status> {
+    if id == ADMIN_ID {
+        return Err(Status::Forbidden);
     }
+    let before = state.repos.run(move |repos| {
+        let before = repos.access_codes.find(id)?;
+        repos.access_codes.purge(id)?;
+        Ok(before)
+    }).await?;
+    AuditEvent::new(AuditAction::CodePurged)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(id)
+        .before(&before)
+        .record(&state.repos).await;
     Ok(Status::NoContent)
 }
 
+/// Annule tous les tirages d'une année (pour relancer le Secret Santa).
+///
+/// Une année scellée ne peut plus être annulée : les participants ont reçu
+/// son engagement.
+#[utoipa::path(
+    tag = "draws",
+    responses(
+        (status = 200, description = "Tirages supprimés (`year`, `deleted`)", body = serde_json::Value),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 409, description = "Année scellée"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["draws:run"])),
+)]
+#[delete("/admin/api/draws/<year>")]
+pub async fn reset_draws(admin: AdminAccess<scope::RunDraw>, _csrf: CsrfVerified, year: i32, state: &State<AppState>) -> Result<Json<serde_json::Value>, Status> {
+    let deleted = state.repos.run(move |repos| repos.draws.reset(year)).await?;
+    AuditEvent::new(AuditAction::DrawReset)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .before(&json!({ "year": year, "draws": deleted }))
+        .record(&state.repos).await;
+    LiveEvent::new(LiveEventKind::Progress, year).publish(state).await;
+    Ok(Json(json!({ "year": year, "deleted": deleted })))
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct DrawOnBehalf {
+    pub giver_id: i64,
+}
+
+/// Tirage effectué par l'admin pour un participant (ex. sans accès à l'application).
+///
+/// Le destinataire n'est pas renvoyé.
+#[utoipa::path(
+    tag = "draws",
+    request_body = DrawOnBehalf,
+    responses(
+        (status = 200, description = "Tirage effectué (`giver_id`, `year`)", body = serde_json::Value),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 404, description = "Participant inconnu ou inactif, ou aucun destinataire disponible"),
+        (status = 409, description = "Tirage déjà fait cette année"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["draws:run"])),
+)]
+#[post("/admin/api/draws", data = "<req>")]
+pub async fn draw_on_behalf(admin: AdminAccess<scope::RunDraw>, _csrf: CsrfVerified, req: Json<DrawOnBehalf>, state: &State<AppState>) -> Result<Json<serde_json::Value>, Status> {
+    let giver_id = req.giver_id;
+    let year = state.clock.year();
+
+    let giver = state.repos.run(move |repos| {
+        let giver = repos.access_codes.find_active(giver_id)?;
+        repos.draws.draw(giver.id, year)?;
+        Ok(giver)
+    }).await?;
+
+    // comme pour un tirage normal, le destinataire n'est ni journalisé ni renvoyé à l'admin
+    AuditEvent::new(AuditAction::DrawOnBehalf)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .target(giver.id)
+        .after(&json!({ "giver_id": giver.id, "year": year }))
+        .record(&state.repos).await;
+    LiveEvent::new(LiveEventKind::Drew, year)
+        .subject(&giver)
+        .publish(state)
+        .await;
+    Ok(Json(json!({ "giver_id": giver.id, "year": year })))
+}
+
+/// Annonce la révélation des tirages de l'année aux pages ouvertes
+/// (admin et participants), par exemple au moment d'ouvrir les cadeaux.
+///
+/// Si l'année est scellée, le sel et l'affectation deviennent publics
+/// (`GET /secret_santa/api/commitment`) pour vérifier l'engagement.
+#[utoipa::path(
+    tag = "draws",
+    responses(
+        (status = 204, description = "Révélation diffusée"),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["draws:run"])),
+)]
+#[post("/admin/api/draws/<year>/reveal")]
+pub async fn reveal_draws(admin: AdminAccess<scope::RunDraw>, _csrf: CsrfVerified, year: i32, state: &State<AppState>) -> Result<Status, Status> {
+    let commitment = state.repos.run(move |repos| match repos.commitments.reveal(year) {
+        Ok(commitment) => Ok(Some(commitment.commitment)),
+        Err(RepoError::NotFound) => Ok(None),
+        Err(e) => Err(e),
+    }).await?;
+    AuditEvent::new(AuditAction::DrawRevealed)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .after(&json!({ "year": year, "commitment": commitment }))
+        .record(&state.repos).await;
+    LiveEvent::new(LiveEventKind::Reveal, year).publish(state).await;
+    Ok(Status::NoContent)
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct IntegrityReport {
+    pub orphan_draws: Vec<Draw>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, ToSchema)]
+pub struct IntegrityRepair {
+    pub removed_draws: Vec<Draw>,
+}
+
+/// Tirages qui référencent un participant supprimé (bases antérieures à
+/// l'activation des clés étrangères).
+#[utoipa::path(
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Tirages orphelins", body = IntegrityReport),
+        (status = 403, description = "Réservé à l'admin"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/integrity")]
+pub async fn integrity_check(_admin: AdminUser, state: &State<AppState>) -> Result<Json<IntegrityReport>, Status> {
+    let orphan_draws = state.repos.run(|repos| repos.draws.orphans()).await?;
+    Ok(Json(IntegrityReport { orphan_draws }))
+}
+
+/// Supprime les tirages orphelins.
+#[utoipa::path(
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Tirages supprimés", body = IntegrityRepair),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/integrity/repair")]
+pub async fn integrity_repair(admin: AdminUser, _csrf: CsrfVerified, state: &State<AppState>) -> Result<Json<IntegrityRepair>, Status> {
+    let removed_draws = state.repos.run(|repos| repos.draws.remove_orphans()).await?;
+    if !removed_draws.is_empty() {
+        AuditEvent::new(AuditAction::IntegrityRepair)
+            .actor(&admin.access_code)
+            .before(&removed_draws)
+            .record(&state.repos).await;
+    }
+    Ok(Json(IntegrityRepair { removed_draws }))
+}
+
+/// Instantané de la base, proposé en téléchargement.
+#[derive(Responder)]
+#[response(content_type = "application/vnd.sqlite3")]
+pub struct BackupFile {
+    body: Vec<u8>,
+    disposition: Header<'static>,
+}
+
+/// Sauvegarde en ligne (API backup de SQLite) : les requêtes continuent pendant la copie.
+#[utoipa::path(
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Instantané de la base", content_type = "application/vnd.sqlite3", body = SqliteSnapshot),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Réservé à l'admin"),
+        (status = 501, description = "Base PostgreSQL"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/backup")]
+pub async fn download_backup(admin: AdminUser, state: &State<AppState>) -> Result<BackupFile, Status> {
+    let pool = state.db.sqlite().cloned().ok_or(Status::NotImplemented)?;
+    let body = rocket::tokio::task::spawn_blocking(move || {
+        let file = TempFile::new()?;
+        backup::snapshot(&pool, file.path())?;
+        Ok::<_, BackupError>(std::fs::read(file.path())?)
+    }).await.map_err(|_| Status::InternalServerError)??;
+
+    AuditEvent::new(AuditAction::BackupCreated)
+        .actor(&admin.access_code)
+        .detail(format!("bytes={}", body.len()))
+        .record(&state.repos).await;
+    let file_name = backup::file_name(chrono::Utc::now());
+    Ok(BackupFile {
+        body,
+        disposition: Header::new("Content-Disposition", format!("attachment; filename=\"{file_name}\"")),
+    })
+}
+
+/// Participant tel qu'exporté : sans son code d'accès ni sa langue.
+#[derive(Debug, Clone, serde::Serialize, ToSchema)]
+pub struct ExportedParticipant {
+    pub id: i64,
+    pub name: String,
+    pub active: bool,
+    pub archived_at: Option<String>,
+    pub valid_from: Option<String>,
+    pub valid_until: Option<String>,
+    pub max_uses: Option<i64>,
+    pub last_login_at: Option<String>,
+    pub login_count: i64,
+}
+
+impl From<AccessCode> for ExportedParticipant {
+    fn from(code: AccessCode) -> Self {
+        Self {
+            id: code.id,
+            name: code.name,
+            active: code.active,
+            archived_at: code.archived_at,
+            valid_from: code.valid_from,
+            valid_until: code.valid_until,
+            max_uses: code.max_uses,
+            last_login_at: code.last_login_at,
+            login_count: code.login_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, ToSchema)]
+pub struct DataExport {
+    pub generated_at: String,
+    /// Participants, archivés compris, par id.
+    pub participants: Vec<ExportedParticipant>,
+    /// Tirages de toutes les années.
+    pub draws: Vec<Draw>,
+}
+
+/// Export des participants et des tirages pour les scripts.
+///
+/// Contrairement à la sauvegarde, il ne contient ni codes d'accès, ni
+/// adresses, ni secrets TOTP, ni jetons d'API.
+#[utoipa::path(
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Participants et tirages", body = DataExport),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, ou droit du jeton d'API manquant"),
+    ),
+    security(("session" = []), ("api_token" = ["export"])),
+)]
+#[get("/admin/api/export")]
+pub async fn export_data(admin: AdminAccess<scope::Export>, state: &State<AppState>) -> Result<Json<DataExport>, Status> {
+    let (participants, draws) = state.repos.run(|repos| {
+        Ok((repos.access_codes.list(true)?, repos.draws.list()?))
+    }).await?;
+    let export = DataExport {
+        generated_at: state.clock.timestamp(),
+        participants: participants.into_iter().map(ExportedParticipant::from).collect(),
+        draws,
+    };
+
+    AuditEvent::new(AuditAction::DataExported)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .detail(format!("participants={} draws={}", export.participants.len(), export.draws.len()))
+        .record(&state.repos).await;
+    Ok(Json(export))
+}
+
+/// Restauration depuis un instantané envoyé tel quel dans le corps de la requête.
+///
+/// `dry_run=true` valide le fichier sans toucher aux données.
+#[utoipa::path(
+    tag = "maintenance",
+    request_body(content = SqliteSnapshot, content_type = "application/vnd.sqlite3"),
+    responses(
+        (status = 200, description = "Fichier valide (restauré sauf en `dry_run`)", body = RestoreReport),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 413, description = "Fichier trop volumineux"),
+        (status = 422, description = "Fichier invalide", body = RestoreReport),
+        (status = 501, description = "Base PostgreSQL"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/backup/restore?<dry_run>", data = "<snapshot>")]
+pub async fn restore_backup(
+    admin: AdminUser,
+    _csrf: CsrfVerified,
+    dry_run: Option<bool>,
+    snapshot: Data<'_>,
+    state: &State<AppState>,
+) -> Result<Custom<Json<RestoreReport>>, Status> {
+    let pool = state.db.sqlite().cloned().ok_or(Status::NotImplemented)?;
+    let dry_run = dry_run.unwrap_or(false);
+
+    let upload = TempFile::new().map_err(BackupError::from)?;
+    let written = snapshot
+        .open(state.config.backup_max_upload_mb.mebibytes())
+        .into_file(upload.path())
+        .await
+        .map_err(|_| Status::InternalServerError)?;
+    if !written.is_complete() {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let config = state.config.clone();
+    let report = rocket::tokio::task::spawn_blocking(move || {
+        let mut report = backup::validate(upload.path());
+        report.dry_run = dry_run;
+        if report.valid && !dry_run {
+            // les données actuelles sont d'abord sauvegardées
+            if let Some(dir) = &config.backup_dir {
+                backup::rotate(&pool, Path::new(dir), config.backup_keep)?;
+            }
+            backup::restore(&pool, upload.path())?;
+            report.restored = true;
+        }
+        Ok::<_, BackupError>(report)
+    }).await.map_err(|_| Status::InternalServerError)??;
+
+    if report.restored {
+        AuditEvent::new(AuditAction::BackupRestored)
+            .actor(&admin.access_code)
+            .after(&json!({
+                "schema_version": report.schema_version,
+                "access_codes": report.access_codes,
+                "draws": report.draws,
+            }))
+            .record(&state.repos).await;
+    }
+    let status = if report.valid { Status::Ok } else { Status::UnprocessableEntity };
+    Ok(Custom(status, Json(report)))
+}
+
+const AUDIT_PAGE_SIZE: u32 = 50;
+
+#[get("/admin/audit?<page>")]
+pub async fn audit_log(_admin: AdminUser, page: Option<u32>, locale: Locale, csrf: CsrfToken, state: &State<AppState>) -> Result<Template, Status> {
+    let page = page.unwrap_or(1).max(1);
+    let (entries, total) = state.repos.run(move |repos| repos.audit.list(page, AUDIT_PAGE_SIZE)).await?;
+    let pages = ((total as u32).div_ceil(AUDIT_PAGE_SIZE)).max(1);
+
+    Ok(Template::render("audit", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+        entries,
+        page,
+        pages,
+        total,
+    }))
+}
+
+/// Génère un lien de connexion signé, à usage unique, et son QR code.
+#[utoipa::path(
+    tag = "participants",
+    responses(
+        (status = 200, description = "Lien de connexion", body = LoginLink),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, jeton CSRF invalide, ou compte administrateur visé par un jeton d'API"),
+        (status = 404, description = "Participant inconnu"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["participants:write"])),
+)]
+#[post("/admin/api/codes/<id>/login-link")]
+pub async fn create_login_link(admin: AdminAccess<scope::WriteParticipants>, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Json<LoginLink>, Status> {
+    // un lien vers le compte administrateur ouvrirait sa session : pas par jeton
+    if admin.api_token.is_some() && id == ADMIN_ID {
+        return Err(Status::Forbidden);
+    }
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(state.config.login_link_ttl_hours);
+    let link_id = state.repos.run(move |repos| {
+        repos.access_codes.find(id)?;
+        repos.login_links.create(id, expires_at.timestamp())
+    }).await?;
+
+    let token = state.link_signer.sign(&LinkClaims {
+        link_id,
+        access_code_id: id,
+        expires_at: expires_at.timestamp(),
+    });
+    let url = state.config.url(&format!("/login/link?token={token}"));
+    let qr_svg = render_qr_svg(&url).map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(LoginLink {
+        url,
+        expires_at: expires_at.to_rfc3339(),
+        qr_svg,
+    }))
+}
+
 // Test code
 #[cfg(test)]
 mod tests {
+    use crate::csrf::testing::{header, token};
     use super::*;
     use crate::state::AppState;
     use rocket::local::blocking::Client;
     use rocket::http::{Status, ContentType};
-    use crate::db::{init_pool, init_db};
-    use std::sync::Mutex;
+    use crate::db::{init_pool, init_db, Database};
+    use crate::config::AppConfig;
+    use crate::repo::Repos;
+    use crate::clock::Clock;
     use rocket::serde::json::serde_json;
 
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
     fn setup_rocket() -> rocket::Rocket<rocket::Build> {
         let pool = init_pool(":memory:");
         init_db(&pool);
-        let state = AppState {
-            db_pool: pool,
-            is_authenticated: Mutex::new(true),
-            current_user: Mutex::new(None),
-            current_access_code: Mutex::new(None),
-        };
+        let state = AppState { clock: Clock::fixed(NOW), ..AppState::new(Database::Sqlite(pool), AppConfig::default()) };
 
         rocket::build()
             .manage(state)
             .mount("/", routes![
+                crate::routes::index::index,
+                crate::routes::index::login,
                 admin_panel,
                 list_access_codes,
                 create_access_code,
                 update_access_code,
                 delete_access_code,
+                restore_access_code,
+                purge_access_code,
+                reset_draws,
+                draw_on_behalf,
+                audit_log,
+                integrity_check,
+                integrity_repair,
+                download_backup,
+                restore_backup,
             ])
-            .attach(Template::fairing())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing)
 
     }
 
@@ -247,12 +845,15 @@ mod tests {
     fn test_create_access_code() {
         let rocket = setup_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as_admin(&client);
         let new_code = CreateAccessCode {
             name: "Test Code".to_string(),
             code: "TESTCODE".to_string(),
             active: true,
+            ..Default::default()
         };
         let response = client.post("/admin/api/codes")
+            .header(header(&client))
             .header(ContentType::JSON)
             .body(serde_json::to_string(&new_code).unwrap())
             .dispatch();
@@ -260,7 +861,7 @@ mod tests {
         let created_code: AccessCode = response.into_json().expect("valid json");
         assert_eq!(created_code.name, "Test Code");
         assert_eq!(created_code.code, "TESTCODE");
-        assert_eq!(created_code.active, true);
+        assert!(created_code.active);
         assert!(created_code.id > 0);
     }
 
@@ -269,19 +870,22 @@ mod tests {
     fn test_list_access_codes() {
         let rocket = setup_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as_admin(&client);
         let new_code = CreateAccessCode {
             name: "Test Code".to_string(),
             code: "TESTCODE".to_string(),
             active: true,
+            ..Default::default()
         };
         let _ = client.post("/admin/api/codes")
+            .header(header(&client))
             .header(ContentType::JSON)
             .body(serde_json::to_string(&new_code).unwrap())
             .dispatch();
         let response = client.get("/admin/api/codes").dispatch();
         assert_eq!(response.status(), Status::Ok);
-        let codes: Vec<AccessCodeWithDraw> = response.into_json().expect("valid json");
-        assert!(codes.iter().any(|c| c.code == "TESTCODE"));
+        let codes = response.into_json::<AccessCodePage>().expect("valid json").codes;
+        assert!(codes.iter().any(|c| c.code.as_deref() == Some("TESTCODE")));
     }
 
     // Test admin route update_access_code
@@ -289,14 +893,17 @@ mod tests {
     fn test_update_access_code() {
         let rocket = setup_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as_admin(&client);
         
         // First create a code
         let new_code = CreateAccessCode {
             name: "Test Code".to_string(),
             code: "TESTCODE".to_string(),
             active: true,
+            ..Default::default()
         };
         let response = client.post("/admin/api/codes")
+            .header(header(&client))
             .header(ContentType::JSON)
             .body(serde_json::to_string(&new_code).unwrap())
             .dispatch();
@@ -308,9 +915,19 @@ mod tests {
             name: "Updated Code".to_string(),
             code: "UPDATEDCODE".to_string(),
             active: false,
+            locale: None,
+            archived_at: None,
+            valid_from: None,
+            valid_until: None,
+            max_uses: None,
+            last_login_at: None,
+            login_count: 0,
+            notify_draw: false,
+            notify_reminders: false,
         };
         
         let response = client.patch(format!("/admin/api/codes/{}", created_code.id))
+            .header(header(&client))
             .header(ContentType::JSON)
             .body(serde_json::to_string(&update_code).unwrap())
             .dispatch();
@@ -319,14 +936,14 @@ mod tests {
         
         // Verify the update
         let response = client.get("/admin/api/codes").dispatch();
-        let codes: Vec<AccessCodeWithDraw> = response.into_json().expect("valid json");
+        let codes = response.into_json::<AccessCodePage>().expect("valid json").codes;
         let updated_code = codes.iter()
             .find(|c| c.id == created_code.id)
             .expect("code exists");
             
         assert_eq!(updated_code.name, "Updated Code");
-        assert_eq!(updated_code.code, "UPDATEDCODE");
-        assert_eq!(updated_code.active, false);
+        assert_eq!(updated_code.code.as_deref(), Some("UPDATEDCODE"));
+        assert!(!updated_code.active);
     }
 
     // Test admin route delete_access_code
@@ -334,18 +951,557 @@ mod tests {
     fn test_delete_access_code() {
         let rocket = setup_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as_admin(&client);
         let new_code = CreateAccessCode {
             name: "Test Code".to_string(),
             code: "TESTCODE".to_string(),
             active: true,
+            ..Default::default()
         };
         let response = client.post("/admin/api/codes")
+            .header(header(&client))
             .header(ContentType::JSON)
             .body(serde_json::to_string(&new_code).unwrap())
             .dispatch();
         let created_code: AccessCode = response.into_json().expect("valid json");
         let delete_resp = client.delete(format!("/admin/api/codes/{}", created_code.id))
+            .header(header(&client))
             .dispatch();
         assert_eq!(delete_resp.status(), Status::NoContent);
     }
-}
\ No newline at end of file
+
+    fn login(client: &Client, code: &str) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    fn login_as_admin(client: &Client) {
+        login(client, "Winter2025");
+    }
+
+    fn audit_actions(client: &Client) -> Vec<(String, Option<String>, Option<String>)> {
+        let state = client.rocket().state::<AppState>().unwrap();
+        let conn = state.db.sqlite().unwrap().get().unwrap();
+        let mut stmt = conn.prepare("SELECT action, before, after FROM audit_log WHERE action NOT LIKE 'login%' ORDER BY id").unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    // Test audit trail of create / update / delete
+    #[test]
+    fn test_audit_trail_records_code_changes() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        login_as_admin(&client);
+        let response = client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "name": "Alice", "code": "ALICE", "active": true }"#)
+            .dispatch();
+        let created: AccessCode = response.into_json().expect("valid json");
+        client.patch(format!("/admin/api/codes/{}", created.id))
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(format!(r#"{{ "id": {}, "name": "Alice B.", "code": "ALICE", "active": true }}"#, created.id))
+            .dispatch();
+        client.delete(format!("/admin/api/codes/{}", created.id)).header(header(&client)).dispatch();
+
+        let actions = audit_actions(&client);
+        let names: Vec<&str> = actions.iter().map(|(action, _, _)| action.as_str()).collect();
+        assert_eq!(names, vec!["code.create", "code.update", "code.archive"]);
+
+        let (_, before, after) = &actions[1];
+        assert!(before.as_deref().unwrap().contains(r#""name":"Alice""#));
+        assert!(after.as_deref().unwrap().contains(r#""name":"Alice B.""#));
+    }
+
+    // Test admin route reset_draws
+    #[test]
+    fn test_reset_draws() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        login_as_admin(&client);
+        {
+            let state = client.rocket().state::<AppState>().unwrap();
+            let conn = state.db.sqlite().unwrap().get().unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code) VALUES (2, 'Alice', 'ALICE')", []).unwrap();
+            conn.execute("INSERT INTO draws (giver_id, receiver_id, year) VALUES (1, 2, 2024), (2, 1, 2025)", []).unwrap();
+        }
+
+        let response = client.delete("/admin/api/draws/2025").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().expect("valid json");
+        assert_eq!(body["deleted"], 1);
+
+        let actions = audit_actions(&client);
+        assert_eq!(actions.last().unwrap().0, "draw.reset");
+    }
+
+    // Test admin route audit_log
+    #[test]
+    fn test_audit_view() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        assert_eq!(client.get("/admin/audit").dispatch().status(), Status::Unauthorized);
+
+        login_as_admin(&client);
+        client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "name": "Alice", "code": "ALICE", "active": true }"#)
+            .dispatch();
+        let response = client.get("/admin/audit?page=1").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("code.create"));
+    }
+
+    fn insert_participants(client: &Client) {
+        let state = client.rocket().state::<AppState>().unwrap();
+        let conn = state.db.sqlite().unwrap().get().unwrap();
+        conn.execute("INSERT INTO access_codes (id, name, code) VALUES (2, 'Alice', 'ALICE'), (3, 'Bob', 'BOB')", []).unwrap();
+    }
+
+    // Test admin route draw_on_behalf
+    #[test]
+    fn test_draw_on_behalf() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login_as_admin(&client);
+
+        let response = client.post("/admin/api/draws")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "giver_id": 2 }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        // le destinataire n'est pas révélé à l'admin
+        let body: serde_json::Value = response.into_json().expect("valid json");
+        assert_eq!(body["giver_id"], 2);
+        assert!(body.get("receiver_id").is_none());
+        // l'année est celle de l'horloge de l'application, comme pour les autres tirages
+        assert_eq!(body["year"], 2025);
+
+        let again = client.post("/admin/api/draws")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "giver_id": 2 }"#)
+            .dispatch();
+        assert_eq!(again.status(), Status::Conflict);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let (action, actor_id, target_id, after): (String, i64, i64, String) = state.db.sqlite().unwrap().get().unwrap()
+            .query_row(
+                "SELECT action, actor_id, target_id, after FROM audit_log WHERE action = 'draw.on_behalf'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!((action.as_str(), actor_id, target_id), ("draw.on_behalf", 1, 2));
+        assert!(!after.contains("receiver"));
+    }
+
+    #[test]
+    fn test_list_follows_clock() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap()
+            .execute("UPDATE access_codes SET valid_until = '2025-12-31 00:00:00' WHERE id = 2", [])
+            .unwrap();
+        login_as_admin(&client);
+        let response = client.post("/admin/api/draws")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "giver_id": 2 }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let alice = |client: &Client| {
+            let page: AccessCodePage = client.get("/admin/api/codes").dispatch().into_json().unwrap();
+            let alice = page.codes.into_iter().find(|code| code.id == 2).unwrap();
+            (page.year, alice.drawn, alice.expired)
+        };
+        assert_eq!(alice(&client), (2025, true, false));
+        // l'année suivante : pas encore tiré, et hors de sa fenêtre de validité
+        state.clock.advance(30 * 24 * 3600);
+        assert_eq!(alice(&client), (2026, false, true));
+    }
+
+    #[test]
+    fn test_draw_on_behalf_requires_admin() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login(&client, "ALICE");
+        let response = client.post("/admin/api/draws")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "giver_id": 3 }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn test_foreign_keys_are_enforced() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        let state = client.rocket().state::<AppState>().unwrap();
+        let conn = state.db.sqlite().unwrap().get().unwrap();
+        let orphan = conn.execute("INSERT INTO draws (giver_id, receiver_id, year) VALUES (1, 99, 2025)", []);
+        assert!(orphan.is_err());
+    }
+
+    #[test]
+    fn test_validity_window_is_normalized_and_checked() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        login_as_admin(&client);
+        let create = |body: &'static str| client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(body)
+            .dispatch();
+
+        let response = create(r#"{ "name": "Alice", "code": "ALICE", "active": true,
+            "valid_from": "2025-12-01T00:00:00.000Z", "valid_until": "2025-12-23", "max_uses": 3 }"#);
+        assert_eq!(response.status(), Status::Created);
+        let created: AccessCode = response.into_json().unwrap();
+        assert_eq!(created.valid_from.as_deref(), Some("2025-12-01 00:00:00"));
+        assert_eq!(created.valid_until.as_deref(), Some("2025-12-23 00:00:00"));
+        assert_eq!(created.max_uses, Some(3));
+
+        let inverted = create(r#"{ "name": "Bob", "code": "BOB", "active": true,
+            "valid_from": "2025-12-24", "valid_until": "2025-12-01" }"#);
+        assert_eq!(inverted.status(), Status::UnprocessableEntity);
+        let unreadable = create(r#"{ "name": "Bob", "code": "BOB", "active": true, "valid_until": "Noël" }"#);
+        assert_eq!(unreadable.status(), Status::UnprocessableEntity);
+        let no_use = create(r#"{ "name": "Bob", "code": "BOB", "active": true, "max_uses": 0 }"#);
+        assert_eq!(no_use.status(), Status::UnprocessableEntity);
+
+        let codes = client.get("/admin/api/codes").dispatch().into_json::<AccessCodePage>().unwrap().codes;
+        let admin = codes.iter().find(|c| c.id == 1).unwrap();
+        assert_eq!(admin.login_count, 1);
+        assert!(admin.last_login_at.is_some() && !admin.expired);
+        let alice = codes.iter().find(|c| c.id == created.id).unwrap();
+        assert!(alice.expired && alice.last_login_at.is_none());
+    }
+
+    #[test]
+    fn test_list_codes_filters_sorts_and_pages() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap().execute_batch("
+            INSERT INTO access_codes (id, name, code, active) VALUES (4, 'alicia_b', 'ALICIA', 0);
+            INSERT INTO draws (giver_id, receiver_id, year) VALUES (2, 3, 2024), (3, 1, 2025);
+        ").unwrap();
+        login_as_admin(&client);
+        let page = |query: &str| -> AccessCodePage {
+            let response = client.get(format!("/admin/api/codes?{query}")).dispatch();
+            assert_eq!(response.status(), Status::Ok, "{query}");
+            response.into_json().unwrap()
+        };
+        let ids = |page: AccessCodePage| page.codes.iter().map(|c| c.id).collect::<Vec<_>>();
+
+        // le tirage affiché est celui de l'année demandée
+        let codes = page("year=2025").codes;
+        assert!(!codes.iter().find(|c| c.id == 2).unwrap().drawn);
+        let bob = codes.iter().find(|c| c.id == 3).unwrap();
+        assert_eq!((bob.drawn, bob.receiver_id, bob.year), (true, Some(1), Some(2025)));
+        assert_eq!(ids(page("year=2024&drawn=true")), vec![2]);
+        assert_eq!(ids(page("year=2025&drawn=false")), vec![1, 2, 4]);
+
+        assert_eq!(ids(page("q=ALI")), vec![2, 4]);
+        assert_eq!(ids(page("q=_")), vec![4]);
+        assert_eq!(ids(page("q=%25")), Vec::<i64>::new());
+        assert_eq!(ids(page("active=false")), vec![4]);
+        assert_eq!(ids(page("sort=-name")), vec![3, 4, 2, 1]);
+        assert_eq!(ids(page("sort=last_login")), vec![2, 3, 4, 1]);
+
+        let second = page("sort=name&per_page=3&page=2");
+        assert_eq!((second.total, second.pages, second.page), (4, 2, 2));
+        assert_eq!(ids(second), vec![3]);
+
+        let response = client.get("/admin/api/codes?sort=code").dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    // Purger un participant supprime aussi les tirages où il est destinataire
+    #[test]
+    fn test_purge_cascades_to_draws() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap()
+            .execute_batch("INSERT INTO draws (giver_id, receiver_id, year) VALUES (2, 3, 2025), (3, 1, 2025), (1, 2, 2025);")
+            .unwrap();
+        login_as_admin(&client);
+
+        let response = client.delete("/admin/api/codes/3").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(state.repos.draws.list().unwrap().len(), 3);
+        let response = client.delete("/admin/api/codes/3/purge").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let remaining: Vec<(i64, i64)> = state.repos.draws.list().unwrap().iter()
+            .map(|d| (d.giver_id, d.receiver_id))
+            .collect();
+        assert_eq!(remaining, vec![(1, 2)]);
+    }
+
+    // Un participant archivé ne peut plus se connecter ni être tiré, mais
+    // reste le destinataire des tirages passés
+    #[test]
+    fn test_archive_hides_participant_and_keeps_history() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap()
+            .execute("INSERT INTO draws (giver_id, receiver_id, year) VALUES (2, 3, 2024)", [])
+            .unwrap();
+        login_as_admin(&client);
+
+        let response = client.delete("/admin/api/codes/3").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let codes = client.get("/admin/api/codes").dispatch().into_json::<AccessCodePage>().unwrap().codes;
+        assert!(codes.iter().all(|c| c.id != 3));
+        let codes = client.get("/admin/api/codes?archived=true").dispatch().into_json::<AccessCodePage>().unwrap().codes;
+        assert!(codes.iter().find(|c| c.id == 3).unwrap().archived_at.is_some());
+
+        let receiver = state.repos.draws.receiver_of(2, 2024).unwrap().unwrap();
+        assert_eq!(receiver.name, "Bob");
+        assert!(state.repos.access_codes.find_active_by_code("BOB").is_err());
+        // seul destinataire possible pour Alice : l'admin
+        for _ in 0..10 {
+            assert_eq!(state.repos.draws.draw(2, 2025).unwrap().id, 1);
+            state.repos.draws.reset(2025).unwrap();
+        }
+
+        // connexion refusée : le formulaire est réaffiché au lieu d'une redirection
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=BOB&csrf_token={}", token(&client)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_restore_and_purge_archived_code() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login_as_admin(&client);
+
+        // seul un code archivé peut être purgé ou restauré
+        let response = client.delete("/admin/api/codes/3/purge").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.post("/admin/api/codes/3/restore").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.delete("/admin/api/codes/1").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        client.delete("/admin/api/codes/2").header(header(&client)).dispatch();
+        client.delete("/admin/api/codes/3").header(header(&client)).dispatch();
+        let forged = client.post("/admin/api/codes/2/restore").dispatch();
+        assert_eq!(forged.status(), Status::Forbidden);
+        let response = client.post("/admin/api/codes/2/restore").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let restored: AccessCode = response.into_json().unwrap();
+        assert!(restored.archived_at.is_none());
+        let response = client.delete("/admin/api/codes/3/purge").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let response = client.delete("/admin/api/codes/3/purge").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let ids: Vec<i64> = state.repos.access_codes.list(true).unwrap().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        let actions: Vec<String> = audit_actions(&client).into_iter().map(|(action, _, _)| action).collect();
+        assert_eq!(actions, vec!["code.archive", "code.archive", "code.restore", "code.purge"]);
+    }
+
+    #[test]
+    fn test_integrity_check_and_repair() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        let state = client.rocket().state::<AppState>().unwrap();
+        {
+            // tirage orphelin laissé par une ancienne version (clés étrangères inactives)
+            let conn = state.db.sqlite().unwrap().get().unwrap();
+            conn.execute_batch("
+                PRAGMA foreign_keys = OFF;
+                INSERT INTO draws (giver_id, receiver_id, year) VALUES (2, 99, 2025), (3, 2, 2025);
+                PRAGMA foreign_keys = ON;
+            ").unwrap();
+        }
+        login_as_admin(&client);
+
+        let report: IntegrityReport = client.get("/admin/api/integrity").dispatch().into_json().unwrap();
+        assert_eq!(report.orphan_draws.len(), 1);
+        assert_eq!(report.orphan_draws[0].receiver_id, 99);
+
+        let forged = client.post("/admin/api/integrity/repair").dispatch();
+        assert_eq!(forged.status(), Status::Forbidden);
+
+        let response = client.post("/admin/api/integrity/repair").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let repair: IntegrityRepair = response.into_json().unwrap();
+        assert_eq!(repair.removed_draws.len(), 1);
+
+        let report: IntegrityReport = client.get("/admin/api/integrity").dispatch().into_json().unwrap();
+        assert!(report.orphan_draws.is_empty());
+        assert_eq!(state.repos.draws.list().unwrap().len(), 1);
+        let actions: Vec<String> = audit_actions(&client).into_iter().map(|(action, _, _)| action).collect();
+        assert_eq!(actions, vec!["integrity.repair"]);
+    }
+
+    #[test]
+    fn test_integrity_requires_admin() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login(&client, "ALICE");
+        assert_eq!(client.get("/admin/api/integrity").dispatch().status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn test_backup_restore_round_trip() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login_as_admin(&client);
+
+        let response = client.get("/admin/api/backup").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let disposition = response.headers().get_one("Content-Disposition").unwrap().to_string();
+        assert!(disposition.starts_with("attachment; filename=\"winter_card-"));
+        let snapshot = response.into_bytes().unwrap();
+        assert!(snapshot.starts_with(b"SQLite format 3\0"));
+
+        let response = client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "name": "Dave", "code": "DAVE", "active": true }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let state = client.rocket().state::<AppState>().unwrap();
+        let count = || state.repos.access_codes.list(true).unwrap().len();
+        assert_eq!(count(), 4);
+
+        // le dry run valide sans rien modifier
+        let response = client.post("/admin/api/backup/restore?dry_run=true")
+            .header(header(&client))
+            .body(snapshot.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let report: RestoreReport = response.into_json().unwrap();
+        assert!(report.valid && report.dry_run && !report.restored);
+        assert_eq!(report.access_codes, Some(3));
+        assert_eq!(count(), 4);
+
+        let forged = client.post("/admin/api/backup/restore").body(snapshot.clone()).dispatch();
+        assert_eq!(forged.status(), Status::Forbidden);
+
+        let response = client.post("/admin/api/backup/restore")
+            .header(header(&client))
+            .body(snapshot)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let report: RestoreReport = response.into_json().unwrap();
+        assert!(report.restored);
+        assert_eq!(count(), 3);
+        assert!(state.repos.access_codes.find_active_by_code("DAVE").is_err());
+        let actions: Vec<String> = audit_actions(&client).into_iter().map(|(action, _, _)| action).collect();
+        assert_eq!(actions.last().map(String::as_str), Some("backup.restore"));
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_file() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        login_as_admin(&client);
+
+        let response = client.post("/admin/api/backup/restore")
+            .header(header(&client))
+            .body("not a database")
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let report: RestoreReport = response.into_json().unwrap();
+        assert!(!report.valid && !report.restored);
+        assert!(!report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_backup_requires_admin() {
+        let client = Client::tracked(setup_rocket()).expect("valid rocket instance");
+        insert_participants(&client);
+        login(&client, "ALICE");
+        assert_eq!(client.get("/admin/api/backup").dispatch().status(), Status::Forbidden);
+    }
+
+    // Les handlers ne dépendent que des repositories : la base SQLite n'est
+    // même pas initialisée ici.
+    #[test]
+    fn test_handlers_with_memory_repo() {
+        let state = AppState::with_repos(Database::Sqlite(init_pool(":memory:")), Repos::memory(), AppConfig::default());
+        let rocket = rocket::build()
+            .manage(state)
+            .mount("/", routes![
+                crate::routes::index::index,
+                crate::routes::index::login,
+                list_access_codes,
+                create_access_code,
+                update_access_code,
+                delete_access_code,
+                restore_access_code,
+                purge_access_code,
+                draw_on_behalf,
+            ])
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as_admin(&client);
+
+        let response = client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "name": "Alice", "code": "ALICE", "active": true }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let alice: AccessCode = response.into_json().expect("valid json");
+
+        let duplicate = client.post("/admin/api/codes")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "name": "Alice bis", "code": "ALICE", "active": true }"#)
+            .dispatch();
+        assert_eq!(duplicate.status(), Status::Conflict);
+
+        let response = client.post("/admin/api/draws")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(format!(r#"{{ "giver_id": {} }}"#, alice.id))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let codes = client.get("/admin/api/codes").dispatch().into_json::<AccessCodePage>().expect("valid json").codes;
+        let listed = codes.iter().find(|c| c.id == alice.id).expect("code listed");
+        assert!(listed.drawn);
+        assert_eq!(listed.receiver_id, Some(1));
+
+        let response = client.delete(format!("/admin/api/codes/{}", alice.id))
+            .header(header(&client))
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let response = client.delete(format!("/admin/api/codes/{}", alice.id))
+            .header(header(&client))
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.delete(format!("/admin/api/codes/{}/purge", alice.id))
+            .header(header(&client))
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let (entries, _) = state.repos.audit.list(1, 10).unwrap();
+        let actions: Vec<&str> = entries.iter().rev().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, vec!["login", "code.create", "draw.on_behalf", "code.archive", "code.purge"]);
+    }
+}
@@ -0,0 +1,334 @@
+use rocket::http::Status;
+use rocket::response::status::Created;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::audit::{AuditAction, AuditEvent};
+use crate::auth::AdminUser;
+use crate::csrf::{CsrfToken, CsrfVerified};
+use crate::i18n::Locale;
+use crate::models::access_code::parse_timestamp;
+use crate::models::api_token::{generate_token, hash_token, ApiScope, ApiToken};
+use crate::repo::NewApiToken;
+use crate::state::AppState;
+
+/// Longueur maximale du nom d'un jeton.
+const NAME_MAX_LEN: usize = 100;
+
+/// Jeton d'API tel qu'affiché à l'admin.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiTokenEntry {
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+    /// Ni révoqué ni expiré.
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiToken {
+    /// Nom du script ou de la machine qui utilise le jeton.
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+/// Jeton créé : `token` n'est renvoyé qu'une seule fois.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub api_token: ApiToken,
+    /// À envoyer dans l'en-tête `Authorization: Bearer …`.
+    pub token: String,
+}
+
+#[get("/admin/tokens")]
+pub fn api_tokens_page(_admin: AdminUser, locale: Locale, csrf: CsrfToken) -> Template {
+    Template::render("api_tokens", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+        scopes: ApiScope::ALL.map(ApiScope::as_str),
+    })
+}
+
+/// Jetons d'API, révoqués compris, du plus récent au plus ancien.
+#[utoipa::path(
+    tag = "api_tokens",
+    responses(
+        (status = 200, description = "Jetons d'API", body = Vec<ApiTokenEntry>),
+        (status = 403, description = "Réservé à l'admin"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/tokens")]
+pub async fn list_api_tokens(_admin: AdminUser, state: &State<AppState>) -> Result<Json<Vec<ApiTokenEntry>>, Status> {
+    let api_tokens = state.repos.run(|repos| repos.api_tokens.list()).await?;
+    let now = state.clock.timestamp();
+    Ok(Json(api_tokens.into_iter().map(|api_token| ApiTokenEntry {
+        active: api_token.is_valid_at(&now),
+        api_token,
+    }).collect()))
+}
+
+/// Crée un jeton d'API nommé, avec ses droits et une date de fin facultative.
+///
+/// Seule son empreinte est conservée : le jeton n'est affiché qu'une fois.
+#[utoipa::path(
+    tag = "api_tokens",
+    request_body = CreateApiToken,
+    responses(
+        (status = 201, description = "Jeton créé", body = CreatedApiToken),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 422, description = "Nom vide ou trop long, aucun droit, droit inconnu ou date de fin passée"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/tokens", data = "<req>")]
+pub async fn create_api_token(
+    admin: AdminUser,
+    _csrf: CsrfVerified,
+    req: Json<CreateApiToken>,
+    state: &State<AppState>,
+) -> Result<Created<Json<CreatedApiToken>>, Status> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > NAME_MAX_LEN || req.scopes.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let expires_at = match req.expires_at.as_deref().map(str::trim).filter(|input| !input.is_empty()) {
+        Some(input) => Some(parse_timestamp(input).filter(|until| *until > state.clock.timestamp()).ok_or(Status::UnprocessableEntity)?),
+        None => None,
+    };
+    // droits dédoublonnés, dans l'ordre de `ApiScope::ALL`
+    let scopes = ApiScope::ALL.into_iter().filter(|scope| req.scopes.contains(scope)).collect();
+
+    let token = generate_token();
+    let new = NewApiToken { name, token_hash: hash_token(&token), scopes, created_by: admin.access_code.id, expires_at };
+    let api_token = state.repos.run(move |repos| repos.api_tokens.create(&new)).await?;
+    AuditEvent::new(AuditAction::ApiTokenCreated)
+        .actor(&admin.access_code)
+        .after(&api_token)
+        .detail(format!("api_token={}", api_token.id))
+        .record(&state.repos).await;
+    Ok(Created::new("/admin/api/tokens").body(Json(CreatedApiToken { api_token, token })))
+}
+
+/// Révoque un jeton d'API : les scripts qui l'utilisent reçoivent `401`.
+#[utoipa::path(
+    tag = "api_tokens",
+    responses(
+        (status = 204, description = "Jeton révoqué"),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 404, description = "Jeton inconnu"),
+        (status = 409, description = "Jeton déjà révoqué"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[delete("/admin/api/tokens/<id>")]
+pub async fn revoke_api_token(admin: AdminUser, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Status, Status> {
+    state.repos.run(move |repos| repos.api_tokens.revoke(id)).await?;
+    AuditEvent::new(AuditAction::ApiTokenRevoked)
+        .actor(&admin.access_code)
+        .detail(format!("api_token={id}"))
+        .record(&state.repos).await;
+    Ok(Status::NoContent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::repo::Repos;
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::{json, Value};
+
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    fn setup_client(repos: Option<Repos>) -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        let db = Database::Sqlite(pool);
+        let state = match repos {
+            Some(repos) => AppState::with_repos(db, repos, AppConfig::default()),
+            None => AppState::new(db, AppConfig::default()),
+        };
+        let rocket = rocket::build()
+            .manage(AppState { clock: Clock::fixed(NOW), ..state })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login_as_admin(client: &Client) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=Winter2025&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    fn create_token(client: &Client, body: Value) -> Value {
+        let response = client.post("/admin/api/tokens")
+            .header(header(client))
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        response.into_json().unwrap()
+    }
+
+    fn bearer(token: &Value) -> Header<'static> {
+        Header::new("Authorization", format!("Bearer {}", token["token"].as_str().unwrap()))
+    }
+
+    #[test]
+    fn test_scoped_tokens() {
+        let repos = Repos::memory();
+        let admin = setup_client(Some(repos.clone()));
+        login_as_admin(&admin);
+        let reader = create_token(&admin, json!({ "name": " export nightly ", "scopes": ["participants:read", "participants:read"] }));
+        assert_eq!(reader["name"], "export nightly");
+        assert_eq!(reader["scopes"], json!(["participants:read"]));
+        let writer = create_token(&admin, json!({ "name": "sync", "scopes": ["participants:write", "participants:read"] }));
+        assert_eq!(writer["scopes"], json!(["participants:read", "participants:write"]));
+
+        // un script : ni session ni cookie CSRF
+        let script = setup_client(Some(repos.clone()));
+        let response = script.get("/admin/api/codes").header(bearer(&reader)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let page = response.into_json::<Value>().unwrap();
+        assert_eq!(page["total"], 1);
+        // les codes d'accès ne sont pas remis aux jetons
+        assert!(page["codes"][0].get("code").is_none());
+        assert_eq!(admin.get("/admin/api/codes").dispatch().into_json::<Value>().unwrap()["codes"][0]["code"], "Winter2025");
+        assert_eq!(script.get("/admin/api/codes").dispatch().status(), Status::Unauthorized);
+
+        let create = |token: &Value| script.post("/admin/api/codes")
+            .header(bearer(token))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Zoé", "code": "ZOE2025", "active": true }).to_string())
+            .dispatch()
+            .status();
+        assert_eq!(create(&reader), Status::Forbidden);
+        assert_eq!(create(&writer), Status::Created);
+        assert_eq!(create(&json!({ "token": "wc_unknown" })), Status::Unauthorized);
+
+        // routes réservées à la session : la gestion des jetons, les invitations
+        assert_eq!(script.get("/admin/api/tokens").header(bearer(&writer)).dispatch().status(), Status::Unauthorized);
+        assert_eq!(script.get("/admin/api/invitations").header(bearer(&writer)).dispatch().status(), Status::Unauthorized);
+        assert_eq!(script.get("/admin/api/backup").header(bearer(&writer)).dispatch().status(), Status::Unauthorized);
+
+        // un jeton ne peut pas prendre la main sur le compte administrateur
+        let update = script.patch("/admin/api/codes/1")
+            .header(bearer(&writer))
+            .header(ContentType::JSON)
+            .body(json!({ "id": 1, "name": "Admin", "code": "PWNED", "active": true }).to_string())
+            .dispatch();
+        assert_eq!(update.status(), Status::Forbidden);
+        let link = |id: i64| script.post(format!("/admin/api/codes/{id}/login-link")).header(bearer(&writer)).dispatch().status();
+        assert_eq!(link(1), Status::Forbidden);
+        let zoe = repos.access_codes.find_active_by_code("ZOE2025").unwrap();
+        assert_eq!(link(zoe.id), Status::Ok);
+        assert!(repos.access_codes.find_active_by_code("Winter2025").is_ok());
+
+        // les actions du script sont attribuées à l'admin, avec l'id du jeton
+        let (entries, _) = repos.audit.list(1, 10).unwrap();
+        let created = entries.iter().find(|entry| entry.action == "code.create").unwrap();
+        assert_eq!(created.actor_id, Some(1));
+        assert_eq!(created.detail.as_deref(), Some(format!("api_token={}", writer["id"]).as_str()));
+
+        let page = admin.get("/admin/tokens").dispatch();
+        assert_eq!(page.status(), Status::Ok);
+        assert!(page.into_string().unwrap().contains("participants:write"));
+        let tokens = admin.get("/admin/api/tokens").dispatch().into_json::<Vec<Value>>().unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.iter().all(|token| token["last_used_at"] == "2025-12-24 00:00:00" && token.get("token").is_none()));
+
+        let response = admin.delete(format!("/admin/api/tokens/{}", reader["id"])).header(header(&admin)).dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        let response = admin.delete(format!("/admin/api/tokens/{}", reader["id"])).header(header(&admin)).dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        assert_eq!(script.get("/admin/api/codes").header(bearer(&reader)).dispatch().status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_export_scope() {
+        let repos = Repos::memory();
+        let admin = setup_client(Some(repos.clone()));
+        login_as_admin(&admin);
+        let exporter = create_token(&admin, json!({ "name": "export", "scopes": ["export"] }));
+
+        let script = setup_client(Some(repos.clone()));
+        let response = script.get("/admin/api/export").header(bearer(&exporter)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let export: Value = response.into_json().unwrap();
+        assert_eq!(export["generated_at"], "2025-12-24 00:00:00");
+        assert_eq!(export["participants"][0]["name"], "Admin");
+        assert!(export["participants"][0].get("code").is_none());
+        assert_eq!(export["draws"], json!([]));
+        assert!(!export.to_string().contains("Winter2025"));
+
+        // la sauvegarde complète reste réservée à la session de l'admin
+        assert_eq!(script.get("/admin/api/backup").header(bearer(&exporter)).dispatch().status(), Status::Unauthorized);
+        assert_eq!(script.get("/admin/api/codes").header(bearer(&exporter)).dispatch().status(), Status::Forbidden);
+
+        let (entries, _) = repos.audit.list(1, 1).unwrap();
+        assert_eq!(entries[0].action, "data.export");
+    }
+
+    #[test]
+    fn test_token_expiry_and_validation() {
+        let admin = setup_client(Some(Repos::memory()));
+        login_as_admin(&admin);
+        let status = |body: Value| admin.post("/admin/api/tokens")
+            .header(header(&admin))
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch()
+            .status();
+        assert_eq!(status(json!({ "name": "ci", "scopes": [] })), Status::UnprocessableEntity);
+        assert_eq!(status(json!({ "name": " ", "scopes": ["export"] })), Status::UnprocessableEntity);
+        assert_eq!(status(json!({ "name": "ci", "scopes": ["admin"] })), Status::UnprocessableEntity);
+        assert_eq!(status(json!({ "name": "ci", "scopes": ["export"], "expires_at": "2025-12-23" })), Status::UnprocessableEntity);
+
+        let token = create_token(&admin, json!({ "name": "ci", "scopes": ["draws:run"], "expires_at": "2025-12-24T01:00:00Z" }));
+        assert_eq!(token["expires_at"], "2025-12-24 01:00:00");
+        let draw = || admin.delete("/admin/api/draws/2025").header(bearer(&token)).dispatch().status();
+        assert_eq!(draw(), Status::Ok);
+
+        admin.rocket().state::<AppState>().unwrap().clock.advance(3600);
+        assert_eq!(draw(), Status::Unauthorized);
+        let tokens = admin.get("/admin/api/tokens").dispatch().into_json::<Vec<Value>>().unwrap();
+        assert_eq!(tokens[0]["active"], false);
+    }
+
+    #[test]
+    fn test_bearer_ignores_session() {
+        let admin = setup_client(None);
+        login_as_admin(&admin);
+        let token = create_token(&admin, json!({ "name": "ci", "scopes": ["participants:read"] }));
+
+        // avec une session ouverte, un jeton invalide ne retombe pas sur la session
+        let invalid = Header::new("Authorization", "Bearer wc_unknown");
+        assert_eq!(admin.get("/admin/api/codes").header(invalid).dispatch().status(), Status::Unauthorized);
+        assert_eq!(admin.get("/admin/api/codes").dispatch().status(), Status::Ok);
+
+        // ni les pages ni les routes du participant n'acceptent un jeton
+        let response = admin.get("/secret_santa").header(bearer(&token)).dispatch();
+        assert_ne!(response.status(), Status::Ok);
+        let response = admin.post("/admin/api/tokens").header(bearer(&token)).header(ContentType::JSON)
+            .body(json!({ "name": "escalade", "scopes": ["export"] }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+}
@@ -0,0 +1,112 @@
+use rocket::http::{ContentType, Status};
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+
+use crate::auth::AdminUser;
+use crate::card::{Card, render_card_svg};
+use crate::i18n::{DEFAULT_LOCALE, Locale, supported};
+use crate::models::access_code::AccessCode;
+use crate::state::AppState;
+
+fn card_svg(state: &AppState, access_code: &AccessCode) -> Result<String, Status> {
+    let config = &state.config;
+    let login_url = config.url("/");
+    render_card_svg(&Card {
+        name: &access_code.name,
+        code: &access_code.code,
+        login_url: &login_url,
+        event_name: &config.event_name,
+        event_budget: config.event_budget.as_deref(),
+        event_date: config.event_date.as_deref(),
+        // chaque carte est imprimée dans la langue de son destinataire
+        lang: access_code.locale.as_deref().and_then(supported).unwrap_or(DEFAULT_LOCALE),
+    }).map_err(|_| Status::InternalServerError)
+}
+
+// Toutes les cartes des participants actifs, prêtes à imprimer en une fois
+#[get("/admin/cards")]
+pub async fn print_all_cards(_admin: AdminUser, locale: Locale, state: &State<AppState>) -> Result<Template, Status> {
+    let codes = state.repos.run(|repos| repos.access_codes.list_active()).await?;
+
+    let cards = codes.iter()
+        .map(|code| card_svg(state, code))
+        .collect::<Result<Vec<String>, Status>>()?;
+
+    Ok(Template::render("cards", context! {
+        lang: locale.0,
+        event_name: &state.config.event_name,
+        cards,
+    }))
+}
+
+#[get("/admin/cards/<id>")]
+pub async fn print_card(_admin: AdminUser, id: i64, locale: Locale, state: &State<AppState>) -> Result<Template, Status> {
+    let access_code = state.repos.run(move |repos| repos.access_codes.find(id)).await?;
+    let cards = vec![card_svg(state, &access_code)?];
+    Ok(Template::render("cards", context! {
+        lang: locale.0,
+        event_name: &state.config.event_name,
+        cards,
+    }))
+}
+
+#[get("/admin/cards/<id>/svg")]
+pub async fn card_svg_file(_admin: AdminUser, id: i64, state: &State<AppState>) -> Result<(ContentType, String), Status> {
+    let access_code = state.repos.run(move |repos| repos.access_codes.find(id)).await?;
+    Ok((ContentType::SVG, card_svg(state, &access_code)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::db::{init_db, init_pool, Database};
+    use crate::csrf::testing::token;
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+
+    // client connecté avec le code `code` ; Alice (id 2) existe en plus de l'admin
+    fn setup_client(code: &str) -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        pool.get().unwrap()
+            .execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", [])
+            .unwrap();
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), AppConfig::default()))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        let token = token(&client);
+        client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        client
+    }
+
+    #[test]
+    fn test_card_svg() {
+        let client = setup_client("Winter2025");
+        let response = client.get("/admin/cards/1/svg").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::SVG));
+        assert!(response.into_string().unwrap().contains("Winter2025"));
+    }
+
+    #[test]
+    fn test_print_all_cards() {
+        let client = setup_client("Winter2025");
+        let response = client.get("/admin/cards").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("Winter2025"));
+    }
+
+    #[test]
+    fn test_cards_require_admin() {
+        let client = setup_client("ALICE");
+        let response = client.get("/admin/cards/1/svg").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Serialize;
+use serde_json::json;
+use utoipa::ToSchema;
+
+use crate::audit::{AuditAction, AuditEvent};
+use crate::auth::{scope, AdminAccess, AuthenticatedUser};
+use crate::commitment::{self, PairingProof};
+use crate::csrf::CsrfVerified;
+use crate::models::commitment::DrawCommitment;
+use crate::repo::{NewCommitment, RepoError};
+use crate::state::AppState;
+
+/// Engagement publié d'une année. Le sel et l'affectation n'apparaissent
+/// qu'après la révélation.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CommitmentInfo {
+    pub year: i32,
+    /// Racine de Merkle des paires salées, en hexadécimal.
+    pub commitment: String,
+    pub created_at: String,
+    pub revealed_at: Option<String>,
+    /// Participants de l'affectation scellée.
+    pub participants: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignment: Option<Vec<RevealedPairing>>,
+}
+
+/// Paire publiée à la révélation ; le nom est absent si le participant a été
+/// purgé depuis.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RevealedPairing {
+    pub giver_id: i64,
+    pub giver_name: Option<String>,
+    pub receiver_id: i64,
+    pub receiver_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProofResponse {
+    pub commitment: String,
+    pub proof: PairingProof,
+}
+
+impl CommitmentInfo {
+    fn sealed(commitment: &DrawCommitment) -> Self {
+        Self {
+            year: commitment.year,
+            commitment: commitment.commitment.clone(),
+            created_at: commitment.created_at.clone(),
+            revealed_at: commitment.revealed_at.clone(),
+            participants: commitment.assignment.len(),
+            salt: None,
+            assignment: None,
+        }
+    }
+
+    fn revealed(commitment: &DrawCommitment, names: &HashMap<i64, String>) -> Self {
+        let assignment = commitment.assignment.iter()
+            .map(|pairing| RevealedPairing {
+                giver_id: pairing.giver_id,
+                giver_name: names.get(&pairing.giver_id).cloned(),
+                receiver_id: pairing.receiver_id,
+                receiver_name: names.get(&pairing.receiver_id).cloned(),
+            })
+            .collect();
+        Self { salt: Some(commitment.salt.clone()), assignment: Some(assignment), ..Self::sealed(commitment) }
+    }
+}
+
+/// Scelle le tirage de l'année en cours avant le premier tirage : l'affectation
+/// complète des participants actifs est tirée d'un coup, et seul son
+/// engagement est publié. Les tirages suivants révèlent chacun leur paire de
+/// l'affectation scellée.
+#[utoipa::path(
+    tag = "draws",
+    responses(
+        (status = 200, description = "Tirage scellé", body = CommitmentInfo),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, droit du jeton d'API manquant, ou jeton CSRF invalide"),
+        (status = 409, description = "Année déjà scellée, ou tirage déjà commencé"),
+        (status = 422, description = "Autre année que l'année en cours, ou moins de deux participants actifs"),
+    ),
+    security(("session" = [], "csrf" = []), ("api_token" = ["draws:run"])),
+)]
+#[post("/admin/api/draws/<year>/commitment")]
+pub async fn seal_draw(admin: AdminAccess<scope::RunDraw>, _csrf: CsrfVerified, year: i32, state: &State<AppState>) -> Result<Json<CommitmentInfo>, Status> {
+    // les participants ne tirent que pour l'année en cours
+    if year != state.clock.year() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let participants: Vec<i64> = state.repos.run(|repos| repos.access_codes.list_active()).await?
+        .iter()
+        .map(|access_code| access_code.id)
+        .collect();
+    let assignment = commitment::random_assignment(&participants);
+    let salt = commitment::generate_salt();
+    let root = commitment::commit(year, &salt, &assignment).ok_or(Status::UnprocessableEntity)?;
+
+    let new = NewCommitment { year, commitment: root, salt, assignment };
+    let sealed = state.repos.run(move |repos| repos.commitments.create(&new)).await?;
+
+    // ni le sel ni l'affectation : l'audit ne doit pas dévoiler les paires
+    AuditEvent::new(AuditAction::DrawSealed)
+        .actor(&admin.access_code)
+        .via(admin.api_token.as_ref())
+        .after(&json!({ "year": year, "commitment": sealed.commitment, "participants": sealed.assignment.len() }))
+        .record(&state.repos).await;
+    Ok(Json(CommitmentInfo::sealed(&sealed)))
+}
+
+/// Engagement du tirage d'une année (par défaut l'année en cours) ; après la
+/// révélation, le sel et l'affectation complète pour le vérifier.
+#[utoipa::path(
+    tag = "secret_santa",
+    params(("year" = Option<i32>, Query, description = "Année du tirage, l'année en cours par défaut")),
+    responses(
+        (status = 200, description = "Engagement publié", body = CommitmentInfo),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 404, description = "Tirage de l'année non scellé"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/secret_santa/api/commitment?<year>")]
+pub async fn get_commitment(_user: AuthenticatedUser, year: Option<i32>, state: &State<AppState>) -> Result<Json<CommitmentInfo>, Status> {
+    let year = year.unwrap_or_else(|| state.clock.year());
+    let info = state.repos.run(move |repos| {
+        let commitment = repos.commitments.find(year)?.ok_or(RepoError::NotFound)?;
+        if commitment.revealed_at.is_none() {
+            return Ok(CommitmentInfo::sealed(&commitment));
+        }
+        let names = repos.access_codes.list(true)?.into_iter().map(|code| (code.id, code.name)).collect();
+        Ok(CommitmentInfo::revealed(&commitment, &names))
+    }).await?;
+    Ok(Json(info))
+}
+
+/// Preuve que la paire du participant connecté appartient à l'affectation
+/// engagée d'une année (par défaut l'année en cours), disponible une fois son
+/// tirage fait.
+#[utoipa::path(
+    tag = "secret_santa",
+    params(("year" = Option<i32>, Query, description = "Année du tirage, l'année en cours par défaut")),
+    responses(
+        (status = 200, description = "Preuve d'inclusion de la paire", body = ProofResponse),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 404, description = "Tirage de l'année non scellé, ou pas encore tiré"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/secret_santa/api/proof?<year>")]
+pub async fn get_proof(user: AuthenticatedUser, year: Option<i32>, state: &State<AppState>) -> Result<Json<ProofResponse>, Status> {
+    let year = year.unwrap_or_else(|| state.clock.year());
+    let giver_id = user.access_code.id;
+    let (sealed, drawn) = state.repos.run(move |repos| {
+        Ok((repos.commitments.find(year)?, repos.draws.has_drawn(giver_id, year)?))
+    }).await?;
+    let sealed = sealed.filter(|_| drawn).ok_or(Status::NotFound)?;
+    let proof = commitment::prove(year, &sealed.salt, &sealed.assignment, giver_id).ok_or(Status::NotFound)?;
+    Ok(Json(ProofResponse { commitment: sealed.commitment, proof }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::commitment::{self, PairingProof};
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::models::commitment::Pairing;
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::Value;
+
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        {
+            let conn = pool.get().unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (3, 'Bob', 'BOB', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (4, 'Chloé', 'CHLOE', 1)", []).unwrap();
+        }
+        let rocket = rocket::build()
+            .manage(AppState { clock: Clock::fixed(NOW), ..AppState::new(Database::Sqlite(pool), AppConfig::default()) })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login_as(client: &Client, code: &str) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    #[test]
+    fn test_sealed_draw() {
+        let client = setup_client();
+        let year = 2025;
+
+        login_as(&client, "ALICE");
+        assert_eq!(client.get("/secret_santa/api/commitment").dispatch().status(), Status::NotFound);
+        let seal = format!("/admin/api/draws/{year}/commitment");
+        assert_eq!(client.post(&seal).header(header(&client)).dispatch().status(), Status::Forbidden);
+
+        login_as(&client, "Winter2025");
+        let response = client.post(&seal).header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let sealed: Value = response.into_json().unwrap();
+        let root = sealed["commitment"].as_str().unwrap().to_string();
+        assert_eq!((sealed["participants"].as_u64(), sealed.get("salt")), (Some(4), None));
+        assert_eq!(client.post(&seal).header(header(&client)).dispatch().status(), Status::Conflict);
+        // seule l'année en cours, celle des tirages, peut être scellée
+        let next_year = format!("/admin/api/draws/{}/commitment", year + 1);
+        assert_eq!(client.post(&next_year).header(header(&client)).dispatch().status(), Status::UnprocessableEntity);
+
+        // avant la révélation : l'engagement seul, ni sel ni paires
+        login_as(&client, "ALICE");
+        assert_eq!(client.get("/secret_santa/api/proof").dispatch().status(), Status::NotFound);
+        let body = client.get("/secret_santa/api/commitment").dispatch().into_string().unwrap();
+        assert!(body.contains(&root) && !body.contains("salt") && !body.contains("assignment"));
+
+        // chaque tirage suit l'affectation scellée, et sa preuve la vérifie
+        let mut pairings = Vec::new();
+        for (id, code) in [(1, "Winter2025"), (2, "ALICE"), (3, "BOB"), (4, "CHLOE")] {
+            login_as(&client, code);
+            let response = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let receiver_id = response.into_json::<Value>().unwrap()["assigned_id"].as_i64().unwrap();
+
+            let proof: Value = client.get("/secret_santa/api/proof").dispatch().into_json().unwrap();
+            assert_eq!(proof["commitment"], root.as_str());
+            let proof: PairingProof = serde_json::from_value(proof["proof"].clone()).unwrap();
+            assert_eq!((proof.giver_id, proof.receiver_id), (id, receiver_id));
+            assert!(commitment::verify(&proof, &root));
+            pairings.push(Pairing { giver_id: id, receiver_id });
+        }
+        let mut receivers: Vec<i64> = pairings.iter().map(|p| p.receiver_id).collect();
+        receivers.sort_unstable();
+        assert_eq!(receivers, [1, 2, 3, 4]);
+
+        // après la révélation : le sel et l'affectation recalculent l'engagement
+        login_as(&client, "Winter2025");
+        let reveal = format!("/admin/api/draws/{year}/reveal");
+        assert_eq!(client.post(&reveal).header(header(&client)).dispatch().status(), Status::NoContent);
+        let revealed: Value = client.get("/secret_santa/api/commitment").dispatch().into_json().unwrap();
+        assert!(revealed["revealed_at"].is_string());
+        let salt = revealed["salt"].as_str().unwrap();
+        let assignment: Vec<Pairing> = serde_json::from_value(revealed["assignment"].clone()).unwrap();
+        assert_eq!(assignment, pairings);
+        assert_eq!(revealed["assignment"][1]["giver_name"], "Alice");
+        assert_eq!(commitment::commit(year, salt, &assignment).unwrap(), root);
+
+        // une année scellée ne peut plus être annulée : l'engagement reste publié
+        let reset = format!("/admin/api/draws/{year}");
+        assert_eq!(client.delete(&reset).header(header(&client)).dispatch().status(), Status::Conflict);
+        assert_eq!(client.get("/secret_santa/api/commitment").dispatch().status(), Status::Ok);
+
+        // l'année suivante, l'engagement passé reste consultable avec `year`
+        client.rocket().state::<AppState>().unwrap().clock.advance(365 * 24 * 3600);
+        assert_eq!(client.get("/secret_santa/api/commitment").dispatch().status(), Status::NotFound);
+        let past: Value = client.get(format!("/secret_santa/api/commitment?year={year}")).dispatch().into_json().unwrap();
+        assert_eq!(past["commitment"], root.as_str());
+        login_as(&client, "ALICE");
+        let proof: Value = client.get(format!("/secret_santa/api/proof?year={year}")).dispatch().into_json().unwrap();
+        assert_eq!(proof["commitment"], root.as_str());
+        assert_eq!(client.get("/secret_santa/api/proof").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_unsealed_year_can_be_reset() {
+        let client = setup_client();
+        login_as(&client, "ALICE");
+        assert_eq!(client.post("/secret_santa/api/draw").header(header(&client)).dispatch().status(), Status::Ok);
+
+        // un tirage commencé sans scellement s'annule, puis l'année peut être scellée
+        login_as(&client, "Winter2025");
+        let seal = "/admin/api/draws/2025/commitment";
+        assert_eq!(client.post(seal).header(header(&client)).dispatch().status(), Status::Conflict);
+        assert_eq!(client.delete("/admin/api/draws/2025").header(header(&client)).dispatch().status(), Status::Ok);
+        assert_eq!(client.post(seal).header(header(&client)).dispatch().status(), Status::Ok);
+    }
+}
@@ -0,0 +1,220 @@
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::tokio::sync::broadcast::Receiver;
+use rocket::{Shutdown, State};
+
+use crate::auth::{scope, AdminAccess, AuthenticatedUser};
+use crate::events::{progress, LiveEvent, LiveEventKind};
+use crate::state::AppState;
+
+/// Flux des évènements de l'année pour la page d'administration : tirages,
+/// arrivées et désactivations de participants, révélation.
+#[utoipa::path(
+    tag = "events",
+    responses(
+        (status = 200, description = "Flux SSE : `progress` à l'ouverture, puis `drew`, `joined`, `deactivated` et `reveal`", content_type = "text/event-stream", body = String),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, ou droit du jeton d'API manquant"),
+    ),
+    security(("session" = []), ("api_token" = ["participants:read"])),
+)]
+#[get("/admin/api/events")]
+pub async fn admin_events(_admin: AdminAccess<scope::ReadParticipants>, state: &State<AppState>, shutdown: Shutdown) -> EventStream![] {
+    let (rx, initial) = subscribe(state).await;
+    stream(rx, initial, shutdown, LiveEvent::clone)
+}
+
+/// Flux des évènements de l'année pour les participants. Le destinataire d'un
+/// tirage n'est jamais transmis, ni le nom d'un participant désactivé.
+#[utoipa::path(
+    tag = "events",
+    responses(
+        (status = 200, description = "Flux SSE : `progress` à l'ouverture, puis `drew`, `joined`, `deactivated` et `reveal`", content_type = "text/event-stream", body = String),
+        (status = 401, description = "Session absente ou expirée"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/secret_santa/api/events")]
+pub async fn participant_events(_user: AuthenticatedUser, state: &State<AppState>, shutdown: Shutdown) -> EventStream![] {
+    let (rx, initial) = subscribe(state).await;
+    stream(rx, initial, shutdown, LiveEvent::for_participant)
+}
+
+/// Abonnement au bus, puis avancement courant : un évènement publié entre
+/// les deux est reçu après l'état initial plutôt que perdu.
+async fn subscribe(state: &AppState) -> (Receiver<LiveEvent>, LiveEvent) {
+    let rx = state.events.subscribe();
+    let year = state.clock.year();
+    let mut initial = LiveEvent::new(LiveEventKind::Progress, year);
+    initial.progress = progress(state, year).await.unwrap_or_default();
+    (rx, initial)
+}
+
+fn stream(
+    mut rx: Receiver<LiveEvent>,
+    initial: LiveEvent,
+    mut shutdown: Shutdown,
+    view: fn(&LiveEvent) -> LiveEvent,
+) -> EventStream![] {
+    let year = initial.year;
+    EventStream! {
+        yield to_sse(&initial);
+        loop {
+            let event = select! {
+                received = rx.recv() => match received {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    // abonné trop lent : les évènements perdus sont rattrapés
+                    // par l'avancement porté par les suivants
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::debug!("live events lagged skipped={skipped}");
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            if event.year == year {
+                yield to_sse(&view(&event));
+            }
+        }
+    }
+}
+
+fn to_sse(event: &LiveEvent) -> Event {
+    Event::json(event).event(event.kind.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::config::AppConfig;
+    use crate::db::{init_db, init_pool, Database};
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::asynchronous::{Client, LocalResponse};
+    use rocket::tokio::io::AsyncReadExt;
+    use rocket::tokio::time::{timeout, Duration};
+
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    async fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        {
+            let conn = pool.get().unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (3, 'Bob', 'BOB', 1)", []).unwrap();
+        }
+        let rocket = rocket::build()
+            .manage(AppState {
+                clock: Clock::fixed(NOW),
+                ..AppState::new(Database::Sqlite(pool), AppConfig::default())
+            })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).await.expect("valid rocket instance")
+    }
+
+    // `csrf::testing` ne sert que le client bloquant
+    async fn csrf_token(client: &Client) -> String {
+        if client.cookies().get_private(crate::csrf::CSRF_COOKIE).is_none() {
+            client.get("/").dispatch().await;
+        }
+        client.cookies().get_private(crate::csrf::CSRF_COOKIE).expect("csrf cookie").value().to_string()
+    }
+
+    async fn login_as(client: &Client, code: &str) {
+        let token = csrf_token(client).await;
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch().await;
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    /// Lit le flux jusqu'à l'évènement `kind` et renvoie ses données, en
+    /// sautant les précédents.
+    async fn next_event(response: &mut LocalResponse<'_>, buffer: &mut String, kind: &str) -> serde_json::Value {
+        let marker = format!("event:{kind}\n");
+        let mut chunk = [0u8; 1024];
+        loop {
+            if let Some(start) = buffer.find(&marker)
+                && let Some(end) = buffer[start..].find("\n\n")
+            {
+                let block: String = buffer.drain(..start + end + 2).collect();
+                let data = block[start..].lines()
+                    .find_map(|line| line.strip_prefix("data:"))
+                    .expect("event data");
+                return serde_json::from_str(data).unwrap();
+            }
+            let read = timeout(Duration::from_secs(5), response.read(&mut chunk)).await
+                .unwrap_or_else(|_| panic!("no `{kind}` event, received: {buffer}"))
+                .unwrap();
+            assert!(read > 0, "stream closed before `{kind}`");
+            buffer.push_str(std::str::from_utf8(&chunk[..read]).unwrap());
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_live_events() {
+        let client = setup_client().await;
+        assert_eq!(client.get("/secret_santa/api/events").dispatch().await.status(), Status::Unauthorized);
+
+        // les flux ouverts restent liés à la session de leur ouverture
+        login_as(&client, "ALICE").await;
+        assert_eq!(client.get("/admin/api/events").dispatch().await.status(), Status::Forbidden);
+        let mut participant = client.get("/secret_santa/api/events").dispatch().await;
+        assert_eq!(participant.status(), Status::Ok);
+        assert_eq!(participant.content_type(), Some(ContentType::EventStream));
+        let mut participant_buffer = String::new();
+        let initial = next_event(&mut participant, &mut participant_buffer, "progress").await;
+        assert_eq!(initial["progress"], serde_json::json!({ "drawn": 0, "participants": 3 }));
+        // l'année des évènements suit l'horloge de l'application
+        assert_eq!(initial["year"], 2025);
+
+        login_as(&client, "Winter2025").await;
+        let mut admin = client.get("/admin/api/events").dispatch().await;
+        assert_eq!(admin.status(), Status::Ok);
+        let mut admin_buffer = String::new();
+        next_event(&mut admin, &mut admin_buffer, "progress").await;
+
+        login_as(&client, "BOB").await;
+        let token = csrf_token(&client).await;
+        let response = client.post("/secret_santa/api/draw")
+            .header(Header::new(crate::csrf::CSRF_HEADER, token))
+            .dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let receiver = response.into_json::<serde_json::Value>().await.unwrap()["assigned_name"].as_str().unwrap().to_string();
+
+        let drew = next_event(&mut participant, &mut participant_buffer, "drew").await;
+        assert_eq!((drew["access_code_id"].as_i64(), drew["name"].as_str()), (Some(3), Some("Bob")));
+        assert_eq!(drew["progress"], serde_json::json!({ "drawn": 1, "participants": 3 }));
+        assert_eq!(drew["year"], 2025);
+        // le destinataire n'apparaît nulle part dans le flux
+        assert!(!drew.to_string().contains(&receiver));
+        assert_eq!(next_event(&mut admin, &mut admin_buffer, "drew").await, drew);
+
+        login_as(&client, "Winter2025").await;
+        let token = csrf_token(&client).await;
+        let response = client.delete("/admin/api/codes/3")
+            .header(Header::new(crate::csrf::CSRF_HEADER, token.clone()))
+            .dispatch().await;
+        assert_eq!(response.status(), Status::NoContent);
+        let deactivated = next_event(&mut admin, &mut admin_buffer, "deactivated").await;
+        assert_eq!((deactivated["name"].as_str(), deactivated["year"].as_i64()), (Some("Bob"), Some(2025)));
+        assert_eq!(deactivated["progress"]["participants"], 2);
+        let deactivated = next_event(&mut participant, &mut participant_buffer, "deactivated").await;
+        assert_eq!((deactivated["access_code_id"].is_null(), deactivated["name"].is_null()), (true, true));
+
+        let year = drew["year"].as_i64().unwrap();
+        let response = client.post(format!("/admin/api/draws/{year}/reveal"))
+            .header(Header::new(crate::csrf::CSRF_HEADER, token))
+            .dispatch().await;
+        assert_eq!(response.status(), Status::NoContent);
+        next_event(&mut participant, &mut participant_buffer, "reveal").await;
+        next_event(&mut admin, &mut admin_buffer, "reveal").await;
+    }
+}
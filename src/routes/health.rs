@@ -0,0 +1,136 @@
+use rocket::http::{ContentType, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde_json::{json, Value};
+
+use crate::auth::bearer_token;
+use crate::csrf::same_token;
+use crate::state::AppState;
+
+/// Le processus répond.
+#[get("/healthz")]
+pub fn healthz() -> &'static str {
+    "ok"
+}
+
+// Prêt à servir : une connexion s'obtient et le schéma est à jour
+#[get("/readyz")]
+pub async fn readyz(state: &State<AppState>) -> Custom<Json<Value>> {
+    let db = state.db.clone();
+    let result = rocket::tokio::task::spawn_blocking(move || db.check_ready())
+        .await
+        .unwrap_or_else(|e| Err(format!("task: {e}")));
+    match result {
+        Ok(version) => Custom(Status::Ok, Json(json!({ "status": "ready", "schema_version": version }))),
+        Err(error) => {
+            log::warn!("not ready error=\"{error}\"");
+            Custom(Status::ServiceUnavailable, Json(json!({ "status": "unavailable", "error": error })))
+        }
+    }
+}
+
+/// Jeton `Authorization: Bearer …` de la requête, s'il y en a un.
+pub struct BearerToken(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BearerToken {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(BearerToken(bearer_token(request).map(str::to_string)))
+    }
+}
+
+#[get("/metrics")]
+pub async fn metrics(token: BearerToken, state: &State<AppState>) -> Result<(ContentType, String), Status> {
+    if let Some(expected) = &state.config.metrics_token
+        && !token.0.is_some_and(|token| same_token(expected, &token))
+    {
+        return Err(Status::Unauthorized);
+    }
+
+    // sans base, les autres métriques restent exposées
+    let draws_by_year = state.repos.run(|repos| repos.draws.count_by_year()).await.unwrap_or_else(|e| {
+        log::warn!("metrics draws unavailable error={e:?}");
+        Vec::new()
+    });
+    let body = state.metrics.render(state.db.pool_usage(), &draws_by_year);
+    let content_type = ContentType::new("text", "plain").with_params([("version", "0.0.4"), ("charset", "utf-8")]);
+    Ok((content_type, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::csrf::testing::token;
+    use crate::db::{init_db, init_pool, Database};
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+
+    fn setup_client(config: AppConfig, init: bool) -> Client {
+        let pool = init_pool(":memory:");
+        if init {
+            init_db(&pool);
+        }
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), config))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing)
+            .attach(crate::metrics::MetricsFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    #[test]
+    fn test_health_and_readiness() {
+        let client = setup_client(AppConfig::default(), true);
+        assert_eq!(client.get("/healthz").dispatch().into_string().as_deref(), Some("ok"));
+
+        let response = client.get("/readyz").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body: serde_json::Value = response.into_json().unwrap();
+        assert_eq!(body["schema_version"], crate::db::schema_version());
+
+        // base jamais migrée
+        let client = setup_client(AppConfig::default(), false);
+        assert_eq!(client.get("/healthz").dispatch().status(), Status::Ok);
+        assert_eq!(client.get("/readyz").dispatch().status(), Status::ServiceUnavailable);
+    }
+
+    #[test]
+    fn test_metrics_count_requests_and_logins() {
+        let client = setup_client(AppConfig::default(), true);
+        let token = token(&client);
+        client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=WRONG&csrf_token={token}"))
+            .dispatch();
+        client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=Winter2025&csrf_token={token}"))
+            .dispatch();
+
+        let response = client.get("/metrics").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type().unwrap().media_type().sub(), "plain");
+        let text = response.into_string().unwrap();
+        assert!(text.contains("winter_card_http_requests_total{method=\"POST\",route=\"/login\",status=\"303\"} 1"), "{text}");
+        assert!(text.contains("winter_card_logins_total{method=\"code\",result=\"success\"} 1"));
+        assert!(text.contains("winter_card_logins_total{method=\"code\",result=\"failure\"} 1"));
+        assert!(text.contains("winter_card_db_pool_max_connections 10"));
+    }
+
+    #[test]
+    fn test_metrics_token() {
+        let config = AppConfig { metrics_token: Some("s3cret".to_string()), ..Default::default() };
+        let client = setup_client(config, true);
+        assert_eq!(client.get("/metrics").dispatch().status(), Status::Unauthorized);
+        let response = client.get("/metrics").header(Header::new("Authorization", "Bearer nope")).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client.get("/metrics").header(Header::new("Authorization", "Bearer s3cret")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
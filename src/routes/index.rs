@@ -1,110 +1,734 @@
 use rocket::form::Form;
 use rocket::State;
+use rocket::http::{CookieJar, Status};
 use rocket::response::Redirect;
 use rocket_dyn_templates::{Template, context};
 use crate::state::AppState;
-use rusqlite::params;
+use crate::auth::{
+    AuthenticatedUser, PendingLogin, end_pending_login, end_session, pending_login, start_pending_login,
+    start_session,
+};
+use crate::i18n::{Locale, supported};
+use crate::audit::{AuditAction, AuditEvent};
+use crate::csrf::CsrfToken;
+use crate::metrics::LoginMethod;
 use crate::models::access_code::AccessCode;
+use crate::repo::{RepoError, Repos};
+use crate::totp;
+use std::net::IpAddr;
 
 #[derive(FromForm)]
 pub struct LoginForm {
     password: String,
+    csrf_token: String,
 }
 
+#[derive(FromForm)]
+pub struct TotpForm {
+    /// Code à 6 chiffres de l'application, ou code de secours.
+    code: String,
+    csrf_token: String,
+}
+
+#[derive(FromForm)]
+pub struct LocaleForm {
+    locale: String,
+    csrf_token: String,
+}
+
+#[derive(FromForm)]
+pub struct LogoutForm {
+    csrf_token: String,
+}
+
+/// Motif du refus d'une connexion par code.
+enum LoginRefusal {
+    /// Code inconnu, inactif ou archivé.
+    Invalid,
+    /// Code connu mais hors de sa fenêtre de validité ou épuisé : le motif est
+    /// journalisé, l'utilisateur ne voit qu'un message générique.
+    Expired { id: i64, reason: &'static str },
+}
+
+impl LoginRefusal {
+    /// Motif journalisé pour un lien de connexion refusé.
+    fn link_reason(&self) -> String {
+        match self {
+            LoginRefusal::Invalid => "database unavailable".to_string(),
+            LoginRefusal::Expired { id, reason } => format!("access code {id} refused: {reason}"),
+        }
+    }
+}
+
+/// Le code peut-il encore ouvrir une session à `now` (`TIMESTAMP_FORMAT`) ?
+/// Vérifié dès le premier facteur, puis de nouveau par `admit`.
+fn check_admissible(access_code: &AccessCode, now: &str) -> Result<(), LoginRefusal> {
+    let id = access_code.id;
+    if !access_code.is_valid_at(now) {
+        return Err(LoginRefusal::Expired { id, reason: "outside validity window" });
+    }
+    if access_code.uses_exhausted() {
+        return Err(LoginRefusal::Expired { id, reason: "max uses reached" });
+    }
+    Ok(())
+}
+
+/// Vérifie la fenêtre de validité d'un code actif puis compte la connexion
+/// (refusée si le nombre maximal de connexions est atteint). N'est appelée
+/// qu'une fois tous les facteurs vérifiés.
+async fn admit(access_code: AccessCode, state: &AppState) -> Result<AccessCode, LoginRefusal> {
+    let id = access_code.id;
+    let now = state.clock.timestamp();
+    check_admissible(&access_code, &now)?;
+    match state.repos.run(move |repos| repos.access_codes.record_login(id, &now)).await {
+        Ok(()) => Ok(access_code),
+        Err(RepoError::Conflict) => Err(LoginRefusal::Expired { id, reason: "max uses reached" }),
+        Err(e) => {
+            log::error!("login not recorded id={id} error={e:?}");
+            Err(LoginRefusal::Invalid)
+        }
+    }
+}
+
+/// Journalise le refus d'une connexion par code et renvoie la clé du message
+/// affiché.
+async fn record_refusal(refusal: LoginRefusal, client_ip: Option<IpAddr>, state: &AppState) -> &'static str {
+    let client = client_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string());
+    let event = AuditEvent::new(AuditAction::LoginFailed);
+    let (event, message) = match refusal {
+        LoginRefusal::Invalid => {
+            log::warn!("login failed client={client}");
+            (event.detail(format!("client={client}")), "login.invalid_code")
+        }
+        LoginRefusal::Expired { id, reason } => {
+            log::warn!("login refused client={client} id={id} reason=\"{reason}\"");
+            (event.target(id).detail(format!("client={client} reason={reason}")), "login.code_expired")
+        }
+    };
+    event.record(&state.repos).await;
+    message
+}
+
+/// Second facteur accepté pour terminer une connexion.
+enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+impl SecondFactor {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SecondFactor::Totp => "totp",
+            SecondFactor::RecoveryCode => "recovery_code",
+        }
+    }
+}
+
+/// La connexion de ce code exige-t-elle un code TOTP ? En cas d'erreur de la
+/// base, on l'exige : la connexion échouera plutôt que de sauter le second
+/// facteur.
+async fn requires_totp(access_code: &AccessCode, state: &AppState) -> bool {
+    let id = access_code.id;
+    state.repos.run(move |repos| repos.totp.find(id)).await
+        .map(|credential| credential.is_some_and(|credential| credential.is_enabled()))
+        .unwrap_or(true)
+}
+
+/// Issue de la saisie d'un second facteur.
+enum SecondFactorCheck {
+    Accepted(SecondFactor),
+    Rejected,
+    /// Trop de codes erronés : plus aucun code n'est vérifié avant la fin du
+    /// blocage (`totp::LOCKOUT_SECS`).
+    Locked,
+}
+
+/// Vérifie un code TOTP (consommé : il ne peut pas resservir) ou, à défaut, un
+/// code de secours. Les codes erronés sont comptés en base : au-delà de
+/// `totp::MAX_FAILURES`, le second facteur est bloqué quel que soit le cookie
+/// présenté. Bloquant.
+fn check_second_factor(repos: &Repos, id: i64, input: &str, now: i64) -> Result<SecondFactorCheck, RepoError> {
+    let Some(credential) = repos.totp.find(id)?.filter(|credential| credential.is_enabled()) else {
+        return Ok(SecondFactorCheck::Rejected);
+    };
+    if credential.is_locked(now) {
+        return Ok(SecondFactorCheck::Locked);
+    }
+    let factor = if totp::is_totp_code(input) {
+        match totp::verify(&credential.secret, input, now, credential.last_step) {
+            Some(step) if repos.totp.record_step(id, step)? => Some(SecondFactor::Totp),
+            _ => None,
+        }
+    } else {
+        let used = repos.totp.use_recovery_code(id, &totp::hash_recovery_code(input))?;
+        used.then_some(SecondFactor::RecoveryCode)
+    };
+    match factor {
+        Some(factor) => {
+            repos.totp.clear_failures(id)?;
+            Ok(SecondFactorCheck::Accepted(factor))
+        }
+        None if repos.totp.record_failure(id, now)? >= totp::MAX_FAILURES => Ok(SecondFactorCheck::Locked),
+        None => Ok(SecondFactorCheck::Rejected),
+    }
+}
+
+fn login_page(locale: &Locale, csrf: CsrfToken, error: &str) -> Template {
+    Template::render("login", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: false,
+        error: Some(locale.t(error))
+    })
+}
 
 #[get("/")]
-pub fn index(state: &State<AppState>) -> Template {
-    let is_authenticated = *state.is_authenticated.lock().unwrap();
-    
-    if is_authenticated {
-        let conn: r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager> = state.db_pool.get().expect("db connection");
-        let user_id = state.current_access_code.lock().unwrap().as_ref().unwrap().id;
-        let access_code_res = conn.query_row(
-            "SELECT * FROM access_codes WHERE id = ?1 AND active = 1",
-            params![user_id],
-            |row| {
-                Ok(AccessCode {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    code: row.get(2)?,
-                    active: row.get::<_, i64>(3)? != 0,
-                })
-            },
-        );
-
-        Template::render("index", context! {
+pub fn index(user: Option<AuthenticatedUser>, locale: Locale, csrf: CsrfToken) -> Template {
+    match user {
+        Some(user) => Template::render("index", context! {
+            lang: locale.0,
+            csrf_token: csrf.0,
             is_authenticated: true,
-            current_access_code: access_code_res.ok()
-        })
-        
-    } else {
-        Template::render("login", context! {
+            current_access_code: user.access_code
+        }),
+        None => Template::render("login", context! {
+            lang: locale.0,
+            csrf_token: csrf.0,
             is_authenticated: false,
             error: None::<String>
-        })
+        }),
     }
 }
 
 #[post("/login", data = "<form>")]
-pub async fn login(form: Form<LoginForm>, state: &State<AppState>) -> Result<Redirect, Template> {
-    let conn = state.db_pool.get().expect("db connection");
-
-    // Récupérer le code d'accès complet
-    let access_code_res = conn.query_row(
-        "SELECT id, name, code, active FROM access_codes WHERE code = ?1 AND active = 1",
-        params![&form.password],
-        |row| {
-            Ok(AccessCode {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                code: row.get(2)?,
-                active: row.get::<_, i64>(3)? != 0,
-            })
-        },
-    );
-
-    match access_code_res {
+pub async fn login(
+    form: Form<LoginForm>,
+    locale: Locale,
+    client_ip: Option<IpAddr>,
+    cookies: &CookieJar<'_>,
+    csrf: CsrfToken,
+    state: &State<AppState>,
+) -> Result<Redirect, Template> {
+    // formulaire périmé ou soumis depuis un autre site
+    if !csrf.verify(&form.csrf_token) {
+        log::warn!("login rejected reason=csrf");
+        return Err(Template::render("login", context! {
+            lang: locale.0,
+            csrf_token: csrf.0,
+            is_authenticated: false,
+            error: Some(locale.t("login.csrf_invalid"))
+        }));
+    }
+
+    let password = form.password.clone();
+    let now = state.clock.timestamp();
+    let found = match state.repos.run(move |repos| repos.access_codes.find_active_by_code(&password)).await {
+        Ok(access_code) => check_admissible(&access_code, &now).map(|()| access_code),
+        Err(_) => Err(LoginRefusal::Invalid),
+    };
+    let admitted = match found {
+        // la connexion ne sera comptée qu'après le second facteur
+        Ok(access_code) if requires_totp(&access_code, state).await => {
+            start_pending_login(cookies, &PendingLogin::new(access_code.id, LoginMethod::Code, state.clock.now()));
+            return Ok(Redirect::to(uri!(login_totp_page)));
+        }
+        Ok(access_code) => admit(access_code, state).await,
+        Err(refusal) => Err(refusal),
+    };
+    state.metrics.record_login(LoginMethod::Code, admitted.is_ok());
+    match admitted {
         Ok(access_code) => {
-            // marquer comme authentifié
-            if let Ok(mut is_auth) = state.is_authenticated.lock() {
-                *is_auth = true;
-            }
+            AuditEvent::new(AuditAction::Login).actor(&access_code).target(access_code.id).record(&state.repos).await;
+            start_session(cookies, &access_code);
+            Ok(Redirect::to("/"))
+        }
+        Err(refusal) => {
+            let message = record_refusal(refusal, client_ip, state).await;
+            Err(login_page(&locale, csrf, message))
+        }
+    }
+}
 
-            // stocker l'access code
-            if let Ok(mut current_access) = state.current_access_code.lock() {
-                *current_access = Some(access_code);
-            }
+// Connexion via un lien à usage unique (QR code généré depuis l'admin)
+#[get("/login/link?<token>")]
+#[allow(clippy::result_large_err)] // même signature que `login`
+pub async fn login_link(
+    token: &str,
+    locale: Locale,
+    cookies: &CookieJar<'_>,
+    csrf: CsrfToken,
+    state: &State<AppState>,
+) -> Result<Redirect, Template> {
+    let admitted = match consume_link(token, state).await {
+        // le lien est consommé, mais la connexion ne sera comptée qu'après le second facteur
+        Ok((access_code, _)) if requires_totp(&access_code, state).await => {
+            start_pending_login(cookies, &PendingLogin::new(access_code.id, LoginMethod::Link, state.clock.now()));
+            return Ok(Redirect::to(uri!(login_totp_page)));
+        }
+        Ok((access_code, link_id)) => admit(access_code, state).await
+            .map(|access_code| (access_code, link_id))
+            .map_err(|refusal| refusal.link_reason()),
+        Err(reason) => Err(reason),
+    };
+    state.metrics.record_login(LoginMethod::Link, admitted.is_ok());
+    match admitted {
+        Ok((access_code, link_id)) => {
+            AuditEvent::new(AuditAction::LoginLink)
+                .actor(&access_code)
+                .target(access_code.id)
+                .detail(format!("link={link_id}"))
+                .record(&state.repos).await;
+            start_session(cookies, &access_code);
+            Ok(Redirect::to("/"))
+        }
+        Err(reason) => {
+            log::warn!("login link rejected reason=\"{reason}\"");
+            AuditEvent::new(AuditAction::LoginLinkRejected).detail(reason).record(&state.repos).await;
+            Err(Template::render("login", context! {
+                lang: locale.0,
+                csrf_token: csrf.0,
+                is_authenticated: false,
+                error: Some(locale.t("login.invalid_link"))
+            }))
+        }
+    }
+}
+
+#[get("/login/totp")]
+#[allow(clippy::result_large_err)] // redirection vers l'accueil sans connexion en attente
+pub fn login_totp_page(locale: Locale, cookies: &CookieJar<'_>, csrf: CsrfToken, state: &State<AppState>) -> Result<Template, Redirect> {
+    pending_login(cookies, state.clock.now()).ok_or_else(|| Redirect::to("/"))?;
+    Ok(Template::render("login_totp", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: false,
+        error: None::<String>
+    }))
+}
 
-            // associer current_user
-            if let Ok(user_id) = conn.query_row(
-                "SELECT id FROM users WHERE password = ?1 LIMIT 1",
-                params![&form.password],
-                |r| r.get::<_, i64>(0),
-            ) {
-                if let Ok(mut cur) = state.current_user.lock() {
-                    *cur = Some(user_id);
-                }
+/// Second étape de la connexion d'un code protégé par TOTP : la session
+/// n'est ouverte qu'après un code de l'application ou un code de secours.
+#[post("/login/totp", data = "<form>")]
+pub async fn login_totp(
+    form: Form<TotpForm>,
+    locale: Locale,
+    client_ip: Option<IpAddr>,
+    cookies: &CookieJar<'_>,
+    csrf: CsrfToken,
+    state: &State<AppState>,
+) -> Result<Redirect, Template> {
+    let now = state.clock.now();
+    let Some(pending) = pending_login(cookies, now) else {
+        return Err(login_page(&locale, csrf, "login.totp_expired"));
+    };
+    let totp_page = |csrf: CsrfToken, error: &str| Template::render("login_totp", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: false,
+        error: Some(locale.t(error))
+    });
+    if !csrf.verify(&form.csrf_token) {
+        return Err(totp_page(csrf, "login.csrf_invalid"));
+    }
+
+    let id = pending.access_code_id;
+    let input = form.code.trim().to_string();
+    let checked = state.repos.run(move |repos| check_second_factor(repos, id, &input, now)).await;
+    let factor = match checked {
+        Ok(SecondFactorCheck::Accepted(factor)) => factor,
+        refused => {
+            state.metrics.record_login(LoginMethod::Totp, false);
+            if let Err(e) = &refused {
+                log::error!("second factor not checked id={id} error={e:?}");
             }
+            let locked = matches!(refused, Ok(SecondFactorCheck::Locked));
+            let reason = if locked { "second factor locked" } else { "invalid second factor" };
+            let client = client_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string());
+            log::warn!("second factor rejected client={client} id={id} reason=\"{reason}\"");
+            AuditEvent::new(AuditAction::LoginFailed)
+                .target(id)
+                .detail(format!("client={client} reason={reason}"))
+                .record(&state.repos).await;
+            if locked {
+                end_pending_login(cookies);
+                return Err(login_page(&locale, csrf, "login.totp_locked"));
+            }
+            return Err(totp_page(csrf, "login.totp_invalid"));
+        }
+    };
+
+    // second facteur accepté : la connexion est enfin comptée
+    end_pending_login(cookies);
+    let admitted = match state.repos.run(move |repos| repos.access_codes.find_active(id)).await {
+        Ok(access_code) => admit(access_code, state).await,
+        Err(_) => Err(LoginRefusal::Invalid),
+    };
+    state.metrics.record_login(pending.method, admitted.is_ok());
+    match admitted {
+        Ok(access_code) => {
+            AuditEvent::new(AuditAction::Login)
+                .actor(&access_code)
+                .target(id)
+                .detail(format!("second_factor={}", factor.as_str()))
+                .record(&state.repos).await;
+            start_session(cookies, &access_code);
             Ok(Redirect::to("/"))
         }
-        Err(e) => Err(Template::render("login", context! {
-            is_authenticated: false,
-            error: Some(e.to_string())
-        }))
+        Err(refusal) => {
+            let message = record_refusal(refusal, client_ip, state).await;
+            Err(login_page(&locale, csrf, message))
+        }
     }
 }
 
-// Mettre à jour aussi la fonction logout pour réinitialiser l'access code
-#[post("/logout")]
-pub fn logout(state: &State<AppState>) -> Redirect {
-    if let Ok(mut auth) = state.is_authenticated.lock() {
-        *auth = false;
+/// Vérifie et consomme un lien de connexion, sans compter la connexion.
+/// Renvoie l'access code et l'id du lien, ou le motif du refus.
+async fn consume_link(token: &str, state: &AppState) -> Result<(AccessCode, i64), String> {
+    let now = state.clock.now();
+    let claims = state.link_signer.verify(token, now).map_err(|e| e.reason().to_string())?;
+    let (link_id, access_code_id) = (claims.link_id, claims.access_code_id);
+
+    // consommation atomique : un lien ne peut servir qu'une fois
+    let consumed = state.repos
+        .run(move |repos| repos.login_links.consume(link_id, access_code_id, now))
+        .await
+        .map_err(|_| "database unavailable".to_string())?;
+    if !consumed {
+        return Err(format!("link {link_id} already used or revoked"));
+    }
+
+    let access_code = state.repos
+        .run(move |repos| repos.access_codes.find_active(access_code_id))
+        .await
+        .map_err(|_| format!("access code {access_code_id} inactive or deleted"))?;
+    check_admissible(&access_code, &state.clock.timestamp()).map_err(|refusal| refusal.link_reason())?;
+    Ok((access_code, link_id))
+}
+
+// Enregistre la langue préférée sur l'access code connecté
+#[post("/locale", data = "<form>")]
+pub async fn set_locale(user: AuthenticatedUser, form: Form<LocaleForm>, csrf: CsrfToken, state: &State<AppState>) -> Result<Redirect, Status> {
+    if !csrf.verify(&form.csrf_token) {
+        return Err(Status::Forbidden);
+    }
+    let lang = supported(&form.locale).ok_or(Status::UnprocessableEntity)?;
+    let id = user.access_code.id;
+    state.repos.run(move |repos| repos.access_codes.set_locale(id, lang)).await?;
+
+    Ok(Redirect::to("/"))
+}
+
+#[post("/logout", data = "<form>")]
+pub fn logout(form: Form<LogoutForm>, cookies: &CookieJar<'_>, csrf: CsrfToken) -> Result<Redirect, Status> {
+    if !csrf.verify(&form.csrf_token) {
+        return Err(Status::Forbidden);
+    }
+    end_session(cookies);
+    Ok(Redirect::to("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::db::{init_db, init_pool, Database};
+    use crate::login_link::LinkClaims;
+    use crate::auth::SESSION_COOKIE;
+    use crate::csrf::testing::{header, token};
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::blocking::Client;
+    use rusqlite::params;
+
+    fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        pool.get().unwrap()
+            .execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", [])
+            .unwrap();
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), AppConfig::default()))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login(client: &Client, code: &str) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    fn login_as_admin(client: &Client) {
+        login(client, "Winter2025");
+    }
+
+    fn logout(client: &Client) {
+        let token = token(client);
+        client.post("/logout")
+            .header(ContentType::Form)
+            .body(format!("csrf_token={token}"))
+            .dispatch();
+    }
+
+    // id de l'access code de la session courante, lu depuis le cookie privé
+    fn current_id(client: &Client) -> Option<i64> {
+        client.cookies()
+            .get_private(SESSION_COOKIE)
+            .and_then(|cookie| cookie.value().parse().ok())
+    }
+
+    fn create_link(client: &Client, id: i64) -> String {
+        login_as_admin(client);
+        let response = client.post(format!("/admin/api/codes/{id}/login-link")).header(header(client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let link: serde_json::Value = response.into_json().unwrap();
+        assert!(link["qr_svg"].as_str().unwrap().starts_with("<svg"));
+        let url = link["url"].as_str().unwrap().to_string();
+        logout(client);
+        url.trim_start_matches("http://localhost:8000").to_string()
+    }
+
+    #[test]
+    fn test_login_link_is_single_use() {
+        let client = setup_client();
+        let path = create_link(&client, 2);
+
+        let response = client.get(path.as_str()).dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+        assert_eq!(current_id(&client), Some(2));
+
+        logout(&client);
+        let response = client.get(path.as_str()).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("Lien de connexion invalide"));
+        assert_eq!(current_id(&client), None);
+    }
+
+    #[test]
+    fn test_expired_login_link_rejected() {
+        let client = setup_client();
+        let state = client.rocket().state::<AppState>().unwrap();
+        let expires_at = chrono::Utc::now().timestamp() - 60;
+        let conn = state.db.sqlite().unwrap().get().unwrap();
+        conn.execute(
+            "INSERT INTO login_links (access_code_id, expires_at) VALUES (2, ?1)",
+            params![expires_at],
+        ).unwrap();
+        let token = state.link_signer.sign(&LinkClaims {
+            link_id: conn.last_insert_rowid(),
+            access_code_id: 2,
+            expires_at,
+        });
+
+        let response = client.get(format!("/login/link?token={token}")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(current_id(&client), None);
     }
-    if let Ok(mut cur) = state.current_user.lock() {
-        *cur = None;
+
+    #[test]
+    fn test_login_link_requires_admin() {
+        let client = setup_client();
+        let response = client.post("/admin/api/codes/2/login-link").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_login_page_uses_accept_language() {
+        let client = setup_client();
+        let page = client.get("/")
+            .header(Header::new("Accept-Language", "de-DE,de;q=0.9,en;q=0.8"))
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(page.contains("Zugangscode"));
+        assert!(page.contains(r#"<html lang="de">"#));
+    }
+
+    #[test]
+    fn test_locale_preference_stored_on_access_code() {
+        let client = setup_client();
+        login_as_admin(&client);
+        let token = token(&client);
+        let response = client.post("/locale")
+            .header(ContentType::Form)
+            .body(format!("locale=en&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+
+        // la préférence l'emporte sur Accept-Language
+        let page = client.get("/")
+            .header(Header::new("Accept-Language", "de"))
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(page.contains("Welcome"));
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let stored: Option<String> = state.db.sqlite().unwrap().get().unwrap()
+            .query_row("SELECT locale FROM access_codes WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_sessions_are_per_browser() {
+        let client = setup_client();
+        let token = token(&client);
+        let alice = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=ALICE&csrf_token={token}"))
+            .dispatch()
+            .cookies()
+            .get(SESSION_COOKIE)
+            .cloned()
+            .unwrap();
+        login_as_admin(&client);
+
+        // la connexion de l'admin ne change pas la session d'Alice
+        let page = client.get("/admin")
+            .cookie(alice.clone())
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(page.contains("Alice"));
+        assert_eq!(client.get("/admin/audit").cookie(alice).dispatch().status(), Status::Forbidden);
+        assert_eq!(client.get("/admin/audit").dispatch().status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_logout_ends_session() {
+        let client = setup_client();
+        login(&client, "ALICE");
+        assert_eq!(current_id(&client), Some(2));
+        logout(&client);
+        assert_eq!(current_id(&client), None);
+        assert_eq!(client.get("/secret_santa").dispatch().status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_login_requires_csrf_token() {
+        let client = setup_client();
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body("password=ALICE&csrf_token=forged")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("Formulaire expiré"));
+        assert_eq!(current_id(&client), None);
+    }
+
+    #[test]
+    fn test_logout_requires_csrf_token() {
+        let client = setup_client();
+        login(&client, "ALICE");
+        let response = client.post("/logout")
+            .header(ContentType::Form)
+            .body("csrf_token=forged")
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(current_id(&client), Some(2));
+    }
+
+    fn execute(client: &Client, sql: &str) {
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap().execute_batch(sql).unwrap();
     }
-    if let Ok(mut access) = state.current_access_code.lock() {
-        *access = None;
+
+    fn try_login(client: &Client, code: &str) -> String {
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={}", token(client)))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_string().unwrap()
     }
-    Redirect::to("/")
-}
\ No newline at end of file
+
+    #[test]
+    fn test_logins_are_counted_up_to_max_uses() {
+        let client = setup_client();
+        execute(&client, "UPDATE access_codes SET max_uses = 2 WHERE id = 2");
+        for _ in 0..2 {
+            login(&client, "ALICE");
+            logout(&client);
+        }
+        let page = try_login(&client, "ALICE");
+        assert!(page.contains("pas valable actuellement"));
+        assert_eq!(current_id(&client), None);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let alice = state.repos.access_codes.find(2).unwrap();
+        assert_eq!(alice.login_count, 2);
+        assert!(alice.last_login_at.is_some());
+        let (entries, _) = state.repos.audit.list(1, 1).unwrap();
+        assert_eq!(entries[0].action, "login.failed");
+        assert_eq!(entries[0].target_id, Some(2));
+        assert!(entries[0].detail.as_deref().unwrap().ends_with("reason=max uses reached"));
+    }
+
+    // Hors de sa fenêtre de validité, un code est refusé avec le même message
+    // (avant comme après) et ses sessions ouvertes sont fermées
+    #[test]
+    fn test_code_outside_validity_window() {
+        let client = setup_client();
+        login(&client, "ALICE");
+        execute(&client, "UPDATE access_codes SET valid_until = '2000-01-01 00:00:00' WHERE id = 2");
+        assert_eq!(client.get("/secret_santa").dispatch().status(), Status::Unauthorized);
+        logout(&client);
+        let expired = try_login(&client, "ALICE");
+        assert!(expired.contains("pas valable actuellement"));
+
+        execute(&client, "UPDATE access_codes SET valid_until = NULL, valid_from = '2999-01-01 00:00:00' WHERE id = 2");
+        let not_yet = try_login(&client, "ALICE");
+        assert!(not_yet.contains("pas valable actuellement"));
+        assert!(try_login(&client, "UNKNOWN").contains("invalide ou inactif"));
+
+        execute(&client, "UPDATE access_codes SET valid_from = '2000-01-01 00:00:00' WHERE id = 2");
+        login(&client, "ALICE");
+    }
+
+    // Une requête qui attend une connexion du pool ne bloque pas le runtime :
+    // les autres requêtes continuent d'être servies pendant ce temps.
+    #[rocket::async_test]
+    async fn test_waiting_for_db_does_not_block_other_requests() {
+        use rocket::local::asynchronous::Client;
+
+        let config = AppConfig {
+            database_url: ":memory:".to_string(),
+            database_pool_size: 1,
+            database_timeout_secs: 10,
+            ..AppConfig::default()
+        };
+        let db = Database::connect(&config);
+        db.init();
+        let pool = db.sqlite().unwrap().clone();
+        let rocket = rocket::build()
+            .manage(AppState::new(db, config))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        let client = Client::tracked(rocket).await.unwrap();
+
+        client.get("/").dispatch().await;
+        let token = client.cookies().get_private(crate::csrf::CSRF_COOKIE).unwrap().value().to_string();
+
+        // l'unique connexion du pool est occupée : la connexion doit attendre
+        let held = pool.get().unwrap();
+        let login = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password=Winter2025&csrf_token={token}"))
+            .dispatch();
+        let other = async {
+            let response = client.get("/").dispatch().await;
+            drop(held);
+            response.status()
+        };
+        let (login, other) = rocket::tokio::join!(login, other);
+
+        assert_eq!(other, Status::Ok);
+        assert_eq!(login.status(), Status::SeeOther);
+    }
+}
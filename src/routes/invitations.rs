@@ -0,0 +1,514 @@
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::response::status::Created;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::audit::{AuditAction, AuditEvent};
+use crate::auth::{AdminUser, AuthenticatedUser};
+use crate::config::AppConfig;
+use crate::csrf::{CsrfToken, CsrfVerified};
+use crate::events::{LiveEvent, LiveEventKind};
+use crate::i18n::Locale;
+use crate::models::access_code::{generate_code, is_valid_name, parse_timestamp, AccessCode};
+use crate::models::invitation::{generate_token, Invitation, Signup, SignupStatus};
+use crate::repo::{NewInvitation, RepoError};
+use crate::state::AppState;
+
+/// Nombre d'essais si un code généré est déjà pris.
+const SIGNUP_ATTEMPTS: u32 = 5;
+/// Longueur maximale du nom d'un échange.
+const LABEL_MAX_LEN: usize = 100;
+
+/// Lien d'invitation avec son URL publique.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InvitationLink {
+    #[serde(flatten)]
+    pub invitation: Invitation,
+    pub url: String,
+    /// Accepte encore des inscriptions (ni révoqué, ni expiré, ni complet).
+    pub open: bool,
+}
+
+impl InvitationLink {
+    fn new(invitation: Invitation, config: &AppConfig, now: &str) -> Self {
+        Self {
+            url: config.url(&format!("/invite/{}", invitation.token)),
+            open: invitation.is_open_at(now),
+            invitation,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct CreateInvitation {
+    /// Nom de l'échange, affiché sur la page d'inscription.
+    pub label: String,
+    #[serde(default)]
+    pub max_signups: Option<i64>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct SignupForm {
+    name: String,
+    csrf_token: String,
+}
+
+// Page d'inscription : formulaire, lien fermé ou inconnu, ou code obtenu
+struct InvitePage<'a> {
+    invitation: Option<&'a Invitation>,
+    open: bool,
+    error: Option<String>,
+    signup: Option<&'a Signup>,
+}
+
+impl<'a> InvitePage<'a> {
+    /// `now` au format `TIMESTAMP_FORMAT`, lu sur l'horloge de l'application.
+    fn new(invitation: Option<&'a Invitation>, now: &str) -> Self {
+        let open = invitation.is_some_and(|invitation| invitation.is_open_at(now));
+        Self { invitation, open, error: None, signup: None }
+    }
+
+    fn render(self, locale: &Locale, csrf: &CsrfToken, user: &Option<AuthenticatedUser>) -> Template {
+        Template::render("invite", context! {
+            lang: locale.0,
+            csrf_token: &csrf.0,
+            is_authenticated: user.is_some(),
+            label: self.invitation.map(|invitation| &invitation.label),
+            token: self.invitation.map(|invitation| &invitation.token),
+            open: self.open,
+            error: self.error,
+            signup: self.signup,
+        })
+    }
+}
+
+#[get("/invite/<token>")]
+pub async fn invite(
+    token: &str,
+    user: Option<AuthenticatedUser>,
+    locale: Locale,
+    csrf: CsrfToken,
+    state: &State<AppState>,
+) -> Result<(Status, Template), Status> {
+    let lookup = token.to_string();
+    let now = state.clock.timestamp();
+    match state.repos.run(move |repos| repos.invitations.find_by_token(&lookup)).await {
+        Ok(invitation) => {
+            let page = InvitePage::new(Some(&invitation), &now);
+            let status = if page.open { Status::Ok } else { Status::Gone };
+            Ok((status, page.render(&locale, &csrf, &user)))
+        }
+        Err(RepoError::NotFound) => Ok((Status::NotFound, InvitePage::new(None, &now).render(&locale, &csrf, &user))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Inscription depuis un lien d'invitation : le code généré est affiché une
+/// seule fois et ne permet de se connecter qu'après validation par l'admin.
+#[post("/invite/<token>", data = "<form>")]
+pub async fn sign_up(
+    token: &str,
+    form: Form<SignupForm>,
+    user: Option<AuthenticatedUser>,
+    locale: Locale,
+    csrf: CsrfToken,
+    state: &State<AppState>,
+) -> Result<(Status, Template), Status> {
+    let lookup = token.to_string();
+    let now = state.clock.timestamp();
+    let invitation = match state.repos.run(move |repos| repos.invitations.find_by_token(&lookup)).await {
+        Ok(invitation) => invitation,
+        Err(RepoError::NotFound) => return Ok((Status::NotFound, InvitePage::new(None, &now).render(&locale, &csrf, &user))),
+        Err(e) => return Err(e.into()),
+    };
+    let refuse = |status: Status, message: &str| {
+        let page = InvitePage { error: Some(locale.t(message)), ..InvitePage::new(Some(&invitation), &now) };
+        Ok((status, page.render(&locale, &csrf, &user)))
+    };
+    if !csrf.verify(&form.csrf_token) {
+        return refuse(Status::Forbidden, "login.csrf_invalid");
+    }
+    let name = form.name.trim().to_string();
+    if !is_valid_name(&name) {
+        return refuse(Status::UnprocessableEntity, "profile.invalid_name");
+    }
+
+    let invitation_id = invitation.id;
+    let signed_up_at = now.clone();
+    let result = state.repos.run(move |repos| {
+        let mut attempt = 1;
+        loop {
+            match repos.invitations.sign_up(invitation_id, &name, &generate_code(), &signed_up_at) {
+                Err(RepoError::Conflict) if attempt < SIGNUP_ATTEMPTS => attempt += 1,
+                result => return result,
+            }
+        }
+    }).await?;
+    let Some(signup) = result else {
+        // complet, expiré ou révoqué entre l'affichage du formulaire et l'envoi
+        let page = InvitePage { open: false, ..InvitePage::new(Some(&invitation), &now) };
+        return Ok((Status::Gone, page.render(&locale, &csrf, &user)));
+    };
+
+    AuditEvent::new(AuditAction::SignupRequested)
+        .detail(format!("invitation={invitation_id} signup={} name={}", signup.id, signup.name))
+        .record(&state.repos).await;
+    let page = InvitePage { signup: Some(&signup), ..InvitePage::new(Some(&invitation), &now) };
+    Ok((Status::Created, page.render(&locale, &csrf, &user)))
+}
+
+#[get("/admin/invitations")]
+pub fn invitations_page(_admin: AdminUser, locale: Locale, csrf: CsrfToken) -> Template {
+    Template::render("invitations", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+    })
+}
+
+/// Liens d'invitation, du plus récent au plus ancien.
+#[utoipa::path(
+    tag = "invitations",
+    responses(
+        (status = 200, description = "Liens d'invitation", body = Vec<InvitationLink>),
+        (status = 403, description = "Réservé à l'admin"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/invitations")]
+pub async fn list_invitations(_admin: AdminUser, state: &State<AppState>) -> Result<Json<Vec<InvitationLink>>, Status> {
+    let invitations = state.repos.run(|repos| repos.invitations.list()).await?;
+    let now = state.clock.timestamp();
+    Ok(Json(invitations.into_iter().map(|invitation| InvitationLink::new(invitation, &state.config, &now)).collect()))
+}
+
+/// Crée un lien d'invitation, avec un nombre d'inscriptions et une date de
+/// fin facultatifs.
+#[utoipa::path(
+    tag = "invitations",
+    request_body = CreateInvitation,
+    responses(
+        (status = 201, description = "Lien créé", body = InvitationLink),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 422, description = "Nom vide ou trop long, plafond ou date de fin invalide"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/invitations", data = "<req>")]
+pub async fn create_invitation(
+    admin: AdminUser,
+    _csrf: CsrfVerified,
+    req: Json<CreateInvitation>,
+    state: &State<AppState>,
+) -> Result<Created<Json<InvitationLink>>, Status> {
+    let label = req.label.trim().to_string();
+    if label.is_empty() || label.chars().count() > LABEL_MAX_LEN || req.max_signups.is_some_and(|max| max < 1) {
+        return Err(Status::UnprocessableEntity);
+    }
+    let expires_at = match req.expires_at.as_deref().map(str::trim).filter(|input| !input.is_empty()) {
+        Some(input) => Some(parse_timestamp(input).ok_or(Status::UnprocessableEntity)?),
+        None => None,
+    };
+    let new = NewInvitation { token: generate_token(), label, max_signups: req.max_signups, expires_at };
+    let invitation = state.repos.run(move |repos| repos.invitations.create(&new)).await?;
+    AuditEvent::new(AuditAction::InvitationCreated)
+        .actor(&admin.access_code)
+        .after(&invitation)
+        .detail(format!("invitation={}", invitation.id))
+        .record(&state.repos).await;
+
+    let link = InvitationLink::new(invitation, &state.config, &state.clock.timestamp());
+    Ok(Created::new("/admin/api/invitations").body(Json(link)))
+}
+
+/// Ferme un lien d'invitation : les inscriptions déjà faites restent dans la
+/// file de validation.
+#[utoipa::path(
+    tag = "invitations",
+    responses(
+        (status = 204, description = "Lien révoqué"),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 404, description = "Lien inconnu"),
+        (status = 409, description = "Lien déjà révoqué"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[delete("/admin/api/invitations/<id>")]
+pub async fn revoke_invitation(admin: AdminUser, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Status, Status> {
+    state.repos.run(move |repos| repos.invitations.revoke(id)).await?;
+    AuditEvent::new(AuditAction::InvitationRevoked)
+        .actor(&admin.access_code)
+        .detail(format!("invitation={id}"))
+        .record(&state.repos).await;
+    Ok(Status::NoContent)
+}
+
+/// Inscriptions faites depuis les liens d'invitation.
+#[utoipa::path(
+    tag = "invitations",
+    params(("status" = Option<String>, Query, description = "`pending` (par défaut), `approved`, `rejected` ou `all`")),
+    responses(
+        (status = 200, description = "Inscriptions, les plus anciennes d'abord", body = Vec<Signup>),
+        (status = 403, description = "Réservé à l'admin"),
+        (status = 422, description = "État inconnu"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/signups?<status>")]
+pub async fn list_signups(_admin: AdminUser, status: Option<&str>, state: &State<AppState>) -> Result<Json<Vec<Signup>>, Status> {
+    let status = match status.unwrap_or("pending") {
+        "all" => None,
+        status => Some(SignupStatus::parse(status).ok_or(Status::UnprocessableEntity)?),
+    };
+    Ok(Json(state.repos.run(move |repos| repos.invitations.signups(status)).await?))
+}
+
+/// Accepte une inscription : son code devient un access code actif.
+#[utoipa::path(
+    tag = "invitations",
+    responses(
+        (status = 200, description = "Participant créé", body = AccessCode),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 404, description = "Inscription inconnue"),
+        (status = 409, description = "Inscription déjà traitée, ou code donné entre-temps à un autre participant"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/signups/<id>/approve")]
+pub async fn approve_signup(admin: AdminUser, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Json<AccessCode>, Status> {
+    let access_code = state.repos.run(move |repos| repos.invitations.approve(id)).await?;
+    AuditEvent::new(AuditAction::SignupApproved)
+        .actor(&admin.access_code)
+        .target(access_code.id)
+        .after(&access_code)
+        .detail(format!("signup={id}"))
+        .record(&state.repos).await;
+    if access_code.active {
+        LiveEvent::new(LiveEventKind::Joined, state.clock.year())
+            .subject(&access_code)
+            .publish(state)
+            .await;
+    }
+    Ok(Json(access_code))
+}
+
+/// Refuse une inscription, qui libère sa place sur le lien.
+#[utoipa::path(
+    tag = "invitations",
+    responses(
+        (status = 200, description = "Inscription refusée", body = Signup),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 404, description = "Inscription inconnue"),
+        (status = 409, description = "Inscription déjà traitée"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/signups/<id>/reject")]
+pub async fn reject_signup(admin: AdminUser, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Json<Signup>, Status> {
+    let signup = state.repos.run(move |repos| repos.invitations.reject(id)).await?;
+    AuditEvent::new(AuditAction::SignupRejected)
+        .actor(&admin.access_code)
+        .detail(format!("signup={id} name={}", signup.name))
+        .record(&state.repos).await;
+    Ok(Json(signup))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::repo::Repos;
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::{json, Value};
+
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    fn setup_client(repos: Option<Repos>) -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        let db = Database::Sqlite(pool);
+        let state = match repos {
+            Some(repos) => AppState::with_repos(db, repos, AppConfig::default()),
+            None => AppState::new(db, AppConfig::default()),
+        };
+        let rocket = rocket::build()
+            .manage(AppState { clock: Clock::fixed(NOW), ..state })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login(client: &Client, code: &str) -> Status {
+        let token = token(client);
+        client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch()
+            .status()
+    }
+
+    fn create_invitation(client: &Client, body: Value) -> Value {
+        let response = client.post("/admin/api/invitations")
+            .header(header(client))
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        response.into_json().unwrap()
+    }
+
+    fn sign_up(client: &Client, token: &str, name: &str) -> (Status, String) {
+        let csrf = crate::csrf::testing::token(client);
+        let response = client.post(format!("/invite/{token}"))
+            .header(ContentType::Form)
+            .body(format!("name={name}&csrf_token={csrf}"))
+            .dispatch();
+        (response.status(), response.into_string().unwrap())
+    }
+
+    fn pending(client: &Client) -> Vec<Value> {
+        client.get("/admin/api/signups").dispatch().into_json::<Vec<Value>>().unwrap()
+    }
+
+    #[test]
+    fn test_signup_flow() {
+        let client = setup_client(None);
+        login(&client, "Winter2025");
+        let invitation = create_invitation(&client, json!({ "label": " Noël au bureau ", "max_signups": 5 }));
+        assert_eq!(invitation["label"], "Noël au bureau");
+        assert_eq!(invitation["open"], true);
+        let token = invitation["token"].as_str().unwrap().to_string();
+        assert_eq!(invitation["url"], format!("http://localhost:8000/invite/{token}"));
+
+        let page = client.get(format!("/invite/{token}")).dispatch();
+        assert_eq!(page.status(), Status::Ok);
+        assert!(page.into_string().unwrap().contains("Noël au bureau"));
+
+        let (status, _) = sign_up(&client, &token, "%3Cscript%3E");
+        assert_eq!(status, Status::UnprocessableEntity);
+        let (status, body) = sign_up(&client, &token, "Zo%C3%A9+Martin");
+        assert_eq!(status, Status::Created);
+
+        // le code n'ouvre une session qu'après validation
+        let signups = pending(&client);
+        assert_eq!(signups.len(), 1);
+        assert_eq!(signups[0]["name"], "Zoé Martin");
+        assert_eq!(signups[0]["invitation_label"], "Noël au bureau");
+        let code = signups[0]["code"].as_str().unwrap().to_string();
+        assert!(body.contains(&code));
+        assert_eq!(login(&client, &code), Status::Ok);
+
+        login(&client, "Winter2025");
+        let id = signups[0]["id"].as_i64().unwrap();
+        let response = client.post(format!("/admin/api/signups/{id}/approve")).header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let access_code: Value = response.into_json().unwrap();
+        assert_eq!((access_code["name"].as_str(), access_code["active"].as_bool()), (Some("Zoé Martin"), Some(true)));
+        assert!(pending(&client).is_empty());
+        let approved: Vec<Value> = client.get("/admin/api/signups?status=approved").dispatch().into_json().unwrap();
+        assert_eq!(approved[0]["access_code_id"], access_code["id"]);
+        let response = client.post(format!("/admin/api/signups/{id}/approve")).header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+
+        assert_eq!(login(&client, &code), Status::SeeOther);
+    }
+
+    #[test]
+    fn test_invitation_cap_expiry_and_revocation() {
+        let client = setup_client(None);
+        login(&client, "Winter2025");
+        let capped = create_invitation(&client, json!({ "label": "Famille", "max_signups": 1 }));
+        let token = capped["token"].as_str().unwrap().to_string();
+        assert_eq!(sign_up(&client, &token, "Alice").0, Status::Created);
+        assert_eq!(sign_up(&client, &token, "Bob").0, Status::Gone);
+        assert_eq!(client.get(format!("/invite/{token}")).dispatch().status(), Status::Gone);
+
+        // un refus libère la place
+        login(&client, "Winter2025");
+        let id = pending(&client)[0]["id"].as_i64().unwrap();
+        let response = client.post(format!("/admin/api/signups/{id}/reject")).header(header(&client)).dispatch();
+        assert_eq!(response.into_json::<Value>().unwrap()["status"], "rejected");
+        assert_eq!(sign_up(&client, &token, "Bob").0, Status::Created);
+
+        login(&client, "Winter2025");
+        let expired = create_invitation(&client, json!({ "label": "Passé", "expires_at": "2020-01-01" }));
+        assert_eq!(expired["open"], false);
+        assert_eq!(sign_up(&client, expired["token"].as_str().unwrap(), "Carol").0, Status::Gone);
+
+        // l'expiration suit l'horloge de l'application
+        login(&client, "Winter2025");
+        let tomorrow = create_invitation(&client, json!({ "label": "Demain", "expires_at": "2025-12-25" }));
+        let tomorrow_token = tomorrow["token"].as_str().unwrap().to_string();
+        assert_eq!(tomorrow["open"], true);
+        assert_eq!(client.get(format!("/invite/{tomorrow_token}")).dispatch().status(), Status::Ok);
+        client.rocket().state::<AppState>().unwrap().clock.advance(2 * 24 * 3600);
+        assert_eq!(client.get(format!("/invite/{tomorrow_token}")).dispatch().status(), Status::Gone);
+        assert_eq!(sign_up(&client, &tomorrow_token, "Carol").0, Status::Gone);
+        let links: Vec<Value> = client.get("/admin/api/invitations").dispatch().into_json().unwrap();
+        assert!(links.iter().any(|link| link["token"] == tomorrow_token.as_str() && link["open"] == false));
+
+        login(&client, "Winter2025");
+        let open = create_invitation(&client, json!({ "label": "Ouvert" }));
+        let id = open["id"].as_i64().unwrap();
+        let revoke = |client: &Client| client.delete(format!("/admin/api/invitations/{id}")).header(header(client)).dispatch().status();
+        assert_eq!(revoke(&client), Status::NoContent);
+        assert_eq!(revoke(&client), Status::Conflict);
+        assert_eq!(sign_up(&client, open["token"].as_str().unwrap(), "Dan").0, Status::Gone);
+        assert_eq!(sign_up(&client, "inconnu", "Dan").0, Status::NotFound);
+
+        login(&client, "Winter2025");
+        let all: Vec<Value> = client.get("/admin/api/signups?status=all").dispatch().into_json().unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(client.get("/admin/api/signups?status=maybe").dispatch().status(), Status::UnprocessableEntity);
+    }
+
+    #[test]
+    fn test_invitations_require_admin() {
+        let client = setup_client(None);
+        let response = client.post("/admin/api/invitations")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(json!({ "label": "Pirate" }).to_string())
+            .dispatch();
+        assert_ne!(response.status(), Status::Created);
+
+        login(&client, "Winter2025");
+        for body in [json!({ "label": "  " }), json!({ "label": "Zéro", "max_signups": 0 }), json!({ "label": "Date", "expires_at": "demain" })] {
+            let response = client.post("/admin/api/invitations")
+                .header(header(&client))
+                .header(ContentType::JSON)
+                .body(body.to_string())
+                .dispatch();
+            assert_eq!(response.status(), Status::UnprocessableEntity, "{body}");
+        }
+    }
+
+    #[test]
+    fn test_signup_flow_with_memory_repo() {
+        let client = setup_client(Some(Repos::memory()));
+        login(&client, "Winter2025");
+        let invitation = create_invitation(&client, json!({ "label": "Mémoire", "max_signups": 1 }));
+        let token = invitation["token"].as_str().unwrap().to_string();
+        assert_eq!(sign_up(&client, &token, "Eve").0, Status::Created);
+        assert_eq!(sign_up(&client, &token, "Fred").0, Status::Gone);
+
+        login(&client, "Winter2025");
+        let signup = &pending(&client)[0];
+        let id = signup["id"].as_i64().unwrap();
+        let response = client.post(format!("/admin/api/signups/{id}/approve")).header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(login(&client, signup["code"].as_str().unwrap()), Status::SeeOther);
+    }
+}
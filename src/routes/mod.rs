@@ -2,14 +2,27 @@ use rocket::Route;
 
 pub mod index;
 pub mod admin;
+pub mod cards;
 pub mod secret_santa;
+pub mod health;
+pub mod profile;
+pub mod invitations;
+pub mod totp;
+pub mod api_tokens;
+pub mod events;
+pub mod stats;
+pub mod commitments;
 
 pub fn routes() -> Vec<Route> {
     routes![
         // page publique / privée
         index::index,
         index::login,
+        index::login_link,
+        index::login_totp_page,
+        index::login_totp,
         index::logout,
+        index::set_locale,
 
         // admin (page + API)
         admin::admin_panel,
@@ -17,9 +30,75 @@ pub fn routes() -> Vec<Route> {
         admin::create_access_code,
         admin::update_access_code,
         admin::delete_access_code,
+        admin::restore_access_code,
+        admin::purge_access_code,
+        admin::create_login_link,
+        admin::reset_draws,
+        admin::draw_on_behalf,
+        admin::reveal_draws,
+        admin::audit_log,
+        admin::integrity_check,
+        admin::integrity_repair,
+        admin::download_backup,
+        admin::export_data,
+        admin::restore_backup,
+
+        // cartes imprimables
+        cards::print_all_cards,
+        cards::print_card,
+        cards::card_svg_file,
 
         // secret santa
         secret_santa::secret_santa,
         secret_santa::perform_draw, // nouvelle route pour le tirage
+        secret_santa::save_address,
+        secret_santa::delete_address,
+
+        // profil du participant
+        profile::profile_page,
+        profile::update_profile,
+        profile::rotate_code,
+
+        // inscription par lien d'invitation
+        invitations::invite,
+        invitations::sign_up,
+        invitations::invitations_page,
+        invitations::list_invitations,
+        invitations::create_invitation,
+        invitations::revoke_invitation,
+        invitations::list_signups,
+        invitations::approve_signup,
+        invitations::reject_signup,
+
+        // double authentification des admins
+        totp::totp_page,
+        totp::totp_status,
+        totp::begin_enrollment,
+        totp::confirm_enrollment,
+        totp::reset_totp,
+
+        // jetons d'API des scripts d'administration
+        api_tokens::api_tokens_page,
+        api_tokens::list_api_tokens,
+        api_tokens::create_api_token,
+        api_tokens::revoke_api_token,
+
+        // statistiques de l'échange
+        stats::stats_page,
+        stats::get_stats,
+
+        // tirage vérifiable : engagement publié et preuves
+        commitments::seal_draw,
+        commitments::get_commitment,
+        commitments::get_proof,
+
+        // avancement du tirage en direct (SSE)
+        events::admin_events,
+        events::participant_events,
+
+        // supervision
+        health::healthz,
+        health::readyz,
+        health::metrics,
     ]
 }
\ No newline at end of file
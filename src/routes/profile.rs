@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::audit::{AuditAction, AuditEvent};
+use crate::auth::AuthenticatedUser;
+use crate::csrf::{same_token, CsrfToken, CsrfVerified};
+use crate::i18n::{supported, Locale, SUPPORTED_LOCALES};
+use crate::models::access_code::{generate_code, is_valid_name};
+use crate::repo::{Profile, RepoError};
+use crate::state::AppState;
+
+/// Profil du participant connecté.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProfileView {
+    pub name: String,
+    pub locale: Option<String>,
+    pub notify_draw: bool,
+    pub notify_reminders: bool,
+}
+
+impl From<Profile> for ProfileView {
+    fn from(profile: Profile) -> Self {
+        Self {
+            name: profile.name,
+            locale: profile.locale,
+            notify_draw: profile.notify_draw,
+            notify_reminders: profile.notify_reminders,
+        }
+    }
+}
+
+/// Modification partielle du profil : seuls les champs présents sont changés.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ProfileUpdate {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// `fr`, `en` ou `de`.
+    #[serde(default)]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub notify_draw: Option<bool>,
+    #[serde(default)]
+    pub notify_reminders: Option<bool>,
+}
+
+/// Confirmation du changement de code : le code actuel doit être ressaisi.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CodeRotation {
+    pub current_code: String,
+}
+
+/// Nouveau code, affiché une seule fois.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RotatedCode {
+    pub code: String,
+}
+
+/// Erreurs de validation par champ (messages traduits).
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct FieldErrors {
+    pub errors: BTreeMap<String, String>,
+}
+
+impl FieldErrors {
+    fn add(&mut self, field: &str, message: String) {
+        self.errors.insert(field.to_string(), message);
+    }
+}
+
+#[derive(Debug, Responder)]
+pub enum ProfileError {
+    Invalid(Custom<Json<FieldErrors>>),
+    Failed(Status),
+}
+
+impl From<FieldErrors> for ProfileError {
+    fn from(errors: FieldErrors) -> Self {
+        ProfileError::Invalid(Custom(Status::UnprocessableEntity, Json(errors)))
+    }
+}
+
+impl From<RepoError> for ProfileError {
+    fn from(e: RepoError) -> Self {
+        ProfileError::Failed(e.into())
+    }
+}
+
+/// Nombre d'essais si un code généré est déjà pris.
+const ROTATION_ATTEMPTS: u32 = 5;
+
+#[get("/profile")]
+pub fn profile_page(user: AuthenticatedUser, locale: Locale, csrf: CsrfToken) -> Template {
+    let access_code = &user.access_code;
+    Template::render("profile", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+        profile: ProfileView::from(Profile::from(access_code)),
+        locales: SUPPORTED_LOCALES,
+        last_login_at: &access_code.last_login_at,
+        login_count: access_code.login_count,
+    })
+}
+
+/// Modifie le nom, la langue ou les préférences de notification du
+/// participant connecté.
+#[utoipa::path(
+    tag = "profile",
+    request_body = ProfileUpdate,
+    responses(
+        (status = 200, description = "Profil enregistré", body = ProfileView),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Jeton CSRF absent ou invalide"),
+        (status = 422, description = "Champs invalides", body = FieldErrors),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[patch("/profile/api", data = "<update>")]
+pub async fn update_profile(
+    user: AuthenticatedUser,
+    _csrf: CsrfVerified,
+    update: Json<ProfileUpdate>,
+    locale: Locale,
+    state: &State<AppState>,
+) -> Result<Json<ProfileView>, ProfileError> {
+    let update = update.into_inner();
+    let before = Profile::from(&user.access_code);
+    let mut profile = before.clone();
+    let mut errors = FieldErrors::default();
+
+    if let Some(name) = update.name {
+        let name = name.trim();
+        if is_valid_name(name) {
+            profile.name = name.to_string();
+        } else {
+            errors.add("name", locale.t("profile.invalid_name"));
+        }
+    }
+    if let Some(lang) = update.locale {
+        match supported(&lang) {
+            Some(lang) => profile.locale = Some(lang.to_string()),
+            None => errors.add("locale", locale.t("profile.invalid_locale")),
+        }
+    }
+    profile.notify_draw = update.notify_draw.unwrap_or(profile.notify_draw);
+    profile.notify_reminders = update.notify_reminders.unwrap_or(profile.notify_reminders);
+    if !errors.errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    if profile != before {
+        let id = user.access_code.id;
+        let saved = profile.clone();
+        state.repos.run(move |repos| repos.access_codes.update_profile(id, &saved)).await?;
+        AuditEvent::new(AuditAction::ProfileUpdated)
+            .actor(&user.access_code)
+            .target(id)
+            .before(&ProfileView::from(before))
+            .after(&ProfileView::from(profile.clone()))
+            .record(&state.repos).await;
+    }
+    Ok(Json(profile.into()))
+}
+
+/// Remplace le code d'accès du participant connecté par un code aléatoire.
+///
+/// Le code actuel doit être ressaisi. Le nouveau code n'est renvoyé qu'une
+/// fois : la session en cours reste ouverte.
+#[utoipa::path(
+    tag = "profile",
+    request_body = CodeRotation,
+    responses(
+        (status = 200, description = "Nouveau code", body = RotatedCode),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Jeton CSRF absent ou invalide"),
+        (status = 422, description = "Code actuel incorrect", body = FieldErrors),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/profile/api/code", data = "<rotation>")]
+pub async fn rotate_code(
+    user: AuthenticatedUser,
+    _csrf: CsrfVerified,
+    rotation: Json<CodeRotation>,
+    locale: Locale,
+    state: &State<AppState>,
+) -> Result<Json<RotatedCode>, ProfileError> {
+    if !same_token(&user.access_code.code, rotation.current_code.trim()) {
+        let mut errors = FieldErrors::default();
+        errors.add("current_code", locale.t("profile.wrong_code"));
+        return Err(errors.into());
+    }
+
+    let id = user.access_code.id;
+    let code = state.repos.run(move |repos| {
+        let mut attempt = 1;
+        loop {
+            let code = generate_code();
+            match repos.access_codes.set_code(id, &code) {
+                Err(RepoError::Conflict) if attempt < ROTATION_ATTEMPTS => attempt += 1,
+                result => return result.map(|()| code),
+            }
+        }
+    }).await?;
+
+    // le code lui-même n'est pas journalisé
+    AuditEvent::new(AuditAction::CodeRotated)
+        .actor(&user.access_code)
+        .target(id)
+        .record(&state.repos).await;
+    Ok(Json(RotatedCode { code }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::repo::Repos;
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::{json, Value};
+
+    fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        pool.get().unwrap()
+            .execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", [])
+            .unwrap();
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), AppConfig::default()))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login(client: &Client, code: &str) -> Status {
+        let token = token(client);
+        client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch()
+            .status()
+    }
+
+    fn repos(client: &Client) -> &Repos {
+        &client.rocket().state::<AppState>().unwrap().repos
+    }
+
+    #[test]
+    fn test_profile_page_requires_session() {
+        let client = setup_client();
+        assert_eq!(client.get("/profile").dispatch().status(), Status::Unauthorized);
+
+        login(&client, "ALICE");
+        let response = client.get("/profile").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.into_string().unwrap().contains("Alice"));
+    }
+
+    #[test]
+    fn test_update_profile_validates_fields() {
+        let client = setup_client();
+        login(&client, "ALICE");
+
+        let response = client.patch("/profile/api")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "  Alice B.  ", "locale": "en-GB", "notify_draw": true }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let profile: Value = response.into_json().unwrap();
+        assert_eq!(profile, json!({ "name": "Alice B.", "locale": "en", "notify_draw": true, "notify_reminders": false }));
+        let stored = repos(&client).access_codes.find(2).unwrap();
+        assert_eq!((stored.name.as_str(), stored.locale.as_deref(), stored.notify_draw), ("Alice B.", Some("en"), true));
+
+        // rien n'est enregistré si un champ est invalide
+        let response = client.patch("/profile/api")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(json!({ "name": "<b>Alice</b>", "locale": "xx", "notify_reminders": true }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: Value = response.into_json().unwrap();
+        let fields: Vec<&String> = body["errors"].as_object().unwrap().keys().collect();
+        assert_eq!(fields, ["locale", "name"]);
+        assert!(!repos(&client).access_codes.find(2).unwrap().notify_reminders);
+
+        let response = client.patch("/profile/api")
+            .header(ContentType::JSON)
+            .body(json!({ "name": "Mallory" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn test_rotate_code() {
+        let client = setup_client();
+        login(&client, "ALICE");
+
+        let response = client.post("/profile/api/code")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(json!({ "current_code": "WRONG" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        let response = client.post("/profile/api/code")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(json!({ "current_code": "ALICE" }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let code = response.into_json::<Value>().unwrap()["code"].as_str().unwrap().to_string();
+        assert_eq!(repos(&client).access_codes.find(2).unwrap().code, code);
+
+        // la session reste ouverte ; seul le nouveau code permet de se reconnecter
+        assert_eq!(client.get("/profile").dispatch().status(), Status::Ok);
+        assert_eq!(login(&client, "ALICE"), Status::Ok);
+        assert_eq!(login(&client, &code), Status::SeeOther);
+
+        let (entries, _) = repos(&client).audit.list(1, 10).unwrap();
+        let rotation = entries.iter().find(|entry| entry.action == "profile.code_rotate").unwrap();
+        assert_eq!(rotation.target_id, Some(2));
+        assert!(rotation.after.is_none());
+    }
+}
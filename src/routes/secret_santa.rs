@@ -1,103 +1,418 @@
-use rocket::response::content::RawHtml;
 use rocket::serde::{json::Json, Deserialize, Serialize};
 use rocket::http::Status;
 use rocket::State;
-use rusqlite::params;
-use chrono::Datelike;
 
 use crate::auth::AuthenticatedUser;
+use crate::csrf::{CsrfToken, CsrfVerified};
+use crate::i18n::Locale;
+use crate::audit::{AuditAction, AuditEvent};
+use crate::events::{LiveEvent, LiveEventKind};
+use crate::repo::StoredAddress;
 use crate::state::AppState;
 use rocket_dyn_templates::{Template, context};
+use utoipa::ToSchema;
 
 #[get("/secret_santa")]
-pub fn secret_santa(_auth: AuthenticatedUser, state: &State<AppState>) -> Template {
-    // récupérer id connecté (optionnel)
-    let user_id_val = state.current_access_code.lock().unwrap().as_ref().unwrap().id;
-    println!("User ID connecté: {}", user_id_val);
-
-    // vérifier si l'utilisateur a déjà tiré au sort cette année
-    let conn = state.db_pool.get().expect("db connection");
-    let current_year = chrono::Utc::now().year();
-    let already_drawn = conn.query_row(
-        "SELECT COUNT(*) FROM draws WHERE giver_id = ?1 AND year = ?2",
-        params![user_id_val, current_year],
-        |row| row.get::<_, i64>(0),
-    ).unwrap_or(0) > 0;
-    let draw_button_state = if already_drawn { "disabled" } else { "" };
+pub async fn secret_santa(user: AuthenticatedUser, locale: Locale, csrf: CsrfToken, state: &State<AppState>) -> Result<Template, Status> {
+    let user_id_val = user.access_code.id;
+    log::debug!("secret_santa user_id={user_id_val}");
 
+    // vérifier si l'utilisateur a déjà tiré au sort cette année, et qui il a tiré
+    let current_year = state.clock.year();
+    let (receiver, has_address) = state.repos.run(move |repos| {
+        let receiver = match repos.draws.has_drawn(user_id_val, current_year)? {
+            true => repos.draws.receiver_of(user_id_val, current_year)?,
+            false => None,
+        };
+        Ok((receiver, repos.addresses.find(user_id_val)?.is_some()))
+    }).await?;
+    let already_drawn = receiver.is_some();
+    let draw_button_state = if already_drawn { "disabled" } else { "" };
 
-    // Récupérer le nom du destinataire assigné (si déjà tiré)
-    let receiver_name = if already_drawn {
-        conn.query_row(
-            "
-            SELECT access_codes.name
-            FROM draws
-            INNER JOIN access_codes ON draws.receiver_id = access_codes.id
-            WHERE draws.giver_id = ?1 AND draws.year = ?2
-            ",
-            params![user_id_val, current_year],
-            |row| row.get::<_, String>(0),
-        ).unwrap_or("Inconnu".to_string())
-    } else {
-        "Inconnu".to_string()
+    // l'adresse du destinataire n'est visible que par son donateur, après le tirage
+    let receiver_address = match &receiver {
+        Some(receiver) => load_address(state, receiver.id).await,
+        None => None,
     };
+    let receiver_name = receiver
+        .map(|receiver| receiver.name)
+        .unwrap_or_else(|| locale.t("santa.unknown"));
 
     // Render the `secret_santa` template
-    Template::render("secret_santa", context! {
+    Ok(Template::render("secret_santa", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
         is_authenticated: true,
-        user_id: user_id_val,
         draw_button_state,
         hidden_draw: if already_drawn { "" } else { "hidden" },
         receiver_name,
-    })
+        receiver_address,
+        has_address,
+        address_enabled: state.address_cipher.is_some(),
+    }))
+}
+
+/// Déchiffre l'adresse postale d'un participant, si elle existe.
+async fn load_address(state: &AppState, access_code_id: i64) -> Option<String> {
+    let cipher = state.address_cipher.as_ref()?;
+    let stored = state.repos.run(move |repos| repos.addresses.find(access_code_id)).await.ok().flatten()?;
+    cipher.decrypt(access_code_id, &stored.nonce, &stored.ciphertext).ok()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AddressRequest {
+    address: String,
+}
+
+const MAX_ADDRESS_LEN: usize = 500;
+
+/// Enregistre (chiffrée) l'adresse postale du participant connecté.
+#[utoipa::path(
+    tag = "secret_santa",
+    request_body = AddressRequest,
+    responses(
+        (status = 204, description = "Adresse enregistrée"),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Jeton CSRF absent ou invalide"),
+        (status = 422, description = "Adresse vide ou trop longue"),
+        (status = 503, description = "Chiffrement des adresses non configuré"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[put("/secret_santa/api/address", data = "<req>")]
+pub async fn save_address(user: AuthenticatedUser, _csrf: CsrfVerified, req: Json<AddressRequest>, state: &State<AppState>) -> Result<Status, Status> {
+    let cipher = state.address_cipher.as_ref().ok_or(Status::ServiceUnavailable)?;
+    let user_id = user.access_code.id;
+
+    let address = req.address.trim();
+    if address.is_empty() || address.len() > MAX_ADDRESS_LEN {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let (nonce, ciphertext) = cipher.encrypt(user_id, address).map_err(|_| Status::InternalServerError)?;
+    let address = StoredAddress { nonce, ciphertext };
+    state.repos.run(move |repos| repos.addresses.save(user_id, &address)).await?;
+
+    Ok(Status::NoContent)
 }
 
-#[derive(Deserialize)]
-pub struct DrawRequest {
-    user_id: i64,
+/// Supprime l'adresse postale du participant connecté.
+#[utoipa::path(
+    tag = "secret_santa",
+    responses(
+        (status = 204, description = "Adresse supprimée"),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Jeton CSRF absent ou invalide"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[delete("/secret_santa/api/address")]
+pub async fn delete_address(user: AuthenticatedUser, _csrf: CsrfVerified, state: &State<AppState>) -> Result<Status, Status> {
+    let user_id = user.access_code.id;
+    state.repos.run(move |repos| repos.addresses.delete(user_id)).await?;
+    Ok(Status::NoContent)
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct DrawResult {
     assigned_id: i64,
     assigned_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assigned_address: Option<String>,
+}
+
+/// Tire au sort le destinataire du participant connecté pour l'année en cours.
+///
+/// Le donateur est toujours le participant de la session : aucun id n'est accepté du client.
+#[utoipa::path(
+    tag = "secret_santa",
+    responses(
+        (status = 200, description = "Destinataire tiré", body = DrawResult),
+        (status = 401, description = "Session absente ou expirée"),
+        (status = 403, description = "Jeton CSRF absent ou invalide"),
+        (status = 404, description = "Aucun destinataire disponible"),
+        (status = 409, description = "Tirage déjà fait cette année"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/secret_santa/api/draw")]
+pub async fn perform_draw(user: AuthenticatedUser, _csrf: CsrfVerified, state: &State<AppState>) -> Result<Json<DrawResult>, Status> {
+    let current_year = state.clock.year();
+    let giver_id = user.access_code.id;
+
+    let receiver = state.repos.run(move |repos| repos.draws.draw(giver_id, current_year)).await?;
+
+    // le destinataire n'est pas journalisé : le tirage reste secret
+    AuditEvent::new(AuditAction::Draw)
+        .actor(&user.access_code)
+        .target(giver_id)
+        .after(&serde_json::json!({ "giver_id": giver_id, "year": current_year }))
+        .record(&state.repos).await;
+    LiveEvent::new(LiveEventKind::Drew, current_year)
+        .subject(&user.access_code)
+        .publish(state)
+        .await;
+
+    let assigned_address = load_address(state, receiver.id).await;
+    Ok(Json(DrawResult {
+        assigned_id: receiver.id,
+        assigned_name: receiver.name,
+        assigned_address,
+    }))
 }
 
-#[post("/secret_santa/api/draw", data = "<req>")]
-pub fn perform_draw(_auth: AuthenticatedUser, req: Json<DrawRequest>, state: &State<AppState>) -> Result<Json<DrawResult>, Status> {
-    let conn = state.db_pool.get().map_err(|_| Status::InternalServerError)?;
-    let current_year = chrono::Utc::now().year();
-    
-    // choisir une personne aléatoire autre que le demandeur
-    let row_res = conn.query_row(
-        "
-        SELECT access_codes.id, name
-        FROM access_codes 
-        WHERE 
-            access_codes.id != ?1
-            AND access_codes.active == 1
-            AND access_codes.id NOT IN (SELECT receiver_id FROM draws WHERE year == ?2)
-        ORDER BY RANDOM()
-        LIMIT 1;
-        ",
-        params![req.user_id, current_year],
-        |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)),
-    );
-
-    // insérer le tirage dans la table draws pour l'année en cours
-    if let Ok((assigned_id, _)) = &row_res {
-        let insert_res = conn.execute(
-            "INSERT INTO draws (giver_id, receiver_id, year) VALUES (?1, ?2, ?3)",
-            params![req.user_id, assigned_id, current_year],
-        );
-        if insert_res.is_err() {
-            return Err(Status::InternalServerError);
+#[cfg(test)]
+mod tests {
+    use crate::csrf::testing::{header, token};
+    use crate::config::AppConfig;
+    use crate::db::{init_db, init_pool, Database};
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rusqlite::params;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Barrier};
+    use crate::repo::{DrawRepo, RepoError};
+    use crate::repo::sqlite::SqliteRepo;
+
+    const KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+    const ADDRESS: &str = "12 rue des Sapins, 75000 Paris";
+
+    fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        {
+            let conn = pool.get().unwrap();
+            // seuls Alice et Bob participent au tirage
+            conn.execute("UPDATE access_codes SET active = 0 WHERE id = 1", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (3, 'Bob', 'BOB', 1)", []).unwrap();
         }
+        let config = AppConfig { address_key: Some(KEY.to_string()), ..Default::default() };
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), config))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login_as(client: &Client, code: &str) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    fn save_address(client: &Client) {
+        let response = client.put("/secret_santa/api/address")
+            .header(header(client))
+            .header(ContentType::JSON)
+            .body(serde_json::json!({ "address": ADDRESS }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
     }
 
-    match row_res {
-        Ok((assigned_id, assigned_name)) => Ok(Json(DrawResult { assigned_id, assigned_name })),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Err(Status::NotFound),
-        Err(_) => Err(Status::InternalServerError),
+    #[test]
+    fn test_address_encrypted_at_rest() {
+        let client = setup_client();
+        login_as(&client, "ALICE");
+        save_address(&client);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let conn = state.db.sqlite().unwrap().get().unwrap();
+        let ciphertext: Vec<u8> = conn.query_row(
+            "SELECT ciphertext FROM shipping_addresses WHERE access_code_id = ?1",
+            params![2],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(!String::from_utf8_lossy(&ciphertext).contains("Sapins"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_address_revealed_to_giver_after_draw() {
+        let client = setup_client();
+        login_as(&client, "ALICE");
+        save_address(&client);
+
+        // Bob ne voit rien avant le tirage
+        login_as(&client, "BOB");
+        let page = client.get("/secret_santa").dispatch().into_string().unwrap();
+        assert!(!page.contains(ADDRESS));
+
+        let response = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: serde_json::Value = response.into_json().unwrap();
+        assert_eq!(result["assigned_id"], 2);
+        assert_eq!(result["assigned_address"], ADDRESS);
+
+        let page = client.get("/secret_santa").dispatch().into_string().unwrap();
+        assert!(page.contains(ADDRESS));
+    }
+
+    #[test]
+    fn test_address_not_exposed_to_admin_list() {
+        let client = setup_client();
+        login_as(&client, "ALICE");
+        save_address(&client);
+        login_as(&client, "BOB");
+        let response = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        // l'admin, désactivé pour le tirage, doit pouvoir se connecter ici
+        let state = client.rocket().state::<AppState>().unwrap();
+        state.db.sqlite().unwrap().get().unwrap()
+            .execute("UPDATE access_codes SET active = 1 WHERE id = 1", [])
+            .unwrap();
+        login_as(&client, "Winter2025");
+        let year = state.clock.year();
+        for path in ["/admin/api/codes".to_string(), format!("/admin/api/codes?year={year}")] {
+            let response = client.get(path.as_str()).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            let page: serde_json::Value = response.into_json().unwrap();
+            assert!(!page.to_string().contains("Sapins"));
+            let codes = page["codes"].as_array().unwrap();
+            assert_eq!(codes.len(), 3);
+            assert!(codes.iter().all(|code| code.get("address").is_none()));
+        }
+    }
+
+    #[test]
+    fn test_address_disabled_without_key() {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        let rocket = rocket::build()
+            .manage(AppState::new(Database::Sqlite(pool), AppConfig::default()))
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        login_as(&client, "Winter2025");
+        let response = client.put("/secret_santa/api/address")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(serde_json::json!({ "address": ADDRESS }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+    }
+
+    #[test]
+    fn test_draw_uses_session_identity() {
+        let client = setup_client();
+        login_as(&client, "BOB");
+
+        // un `user_id` envoyé par le client est ignoré
+        let response = client.post("/secret_santa/api/draw")
+            .header(header(&client))
+            .header(ContentType::JSON)
+            .body(r#"{ "user_id": 2 }"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let givers: Vec<i64> = state.db.sqlite().unwrap().get().unwrap()
+            .prepare("SELECT giver_id FROM draws").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(givers, vec![3]);
+
+        let again = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+        assert_eq!(again.status(), Status::Conflict);
+    }
+
+    #[test]
+    fn test_draw_requires_session() {
+        let client = setup_client();
+        let response = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    // Tirages simultanés sur la base en mémoire : chaque participant tire une
+    // fois, personne ne se tire lui-même, aucun destinataire n'est tiré deux fois.
+    #[test]
+    fn test_concurrent_draws_are_valid() {
+        const PARTICIPANTS: i64 = 24;
+        const YEAR: i32 = 2025;
+
+        for _ in 0..5 {
+            let pool = init_pool(":memory:");
+            init_db(&pool);
+            {
+                let conn = pool.get().unwrap();
+                for id in 2..PARTICIPANTS + 2 {
+                    conn.execute(
+                        "INSERT INTO access_codes (id, name, code, active) VALUES (?1, ?2, ?3, 1)",
+                        params![id, format!("P{id}"), format!("CODE{id}")],
+                    ).unwrap();
+                }
+            }
+            // l'admin (id 1) participe aussi
+            let givers: Vec<i64> = (1..PARTICIPANTS + 2).collect();
+            let barrier = Arc::new(Barrier::new(givers.len()));
+
+            let repo = Arc::new(SqliteRepo::new(pool.clone()));
+            let handles: Vec<_> = givers.iter().map(|&giver| {
+                let repo = Arc::clone(&repo);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    // chaque participant clique deux fois
+                    let first = repo.draw(giver, YEAR);
+                    let second = repo.draw(giver, YEAR);
+                    (first, second)
+                })
+            }).collect();
+
+            let mut drawn = 0;
+            for handle in handles {
+                let (first, second) = handle.join().unwrap();
+                match first {
+                    Ok(_) => {
+                        drawn += 1;
+                        assert_eq!(second.err(), Some(RepoError::Conflict));
+                    }
+                    // le dernier participant peut n'avoir plus que lui-même
+                    Err(e) => assert_eq!(e, RepoError::NotFound),
+                }
+            }
+            assert!(drawn >= givers.len() - 1);
+
+            let conn = pool.get().unwrap();
+            let pairs: Vec<(i64, i64)> = conn
+                .prepare("SELECT giver_id, receiver_id FROM draws WHERE year = ?1").unwrap()
+                .query_map(params![YEAR], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(pairs.len(), drawn);
+            let receivers: HashSet<i64> = pairs.iter().map(|(_, receiver)| *receiver).collect();
+            let givers: HashSet<i64> = pairs.iter().map(|(giver, _)| *giver).collect();
+            assert_eq!(receivers.len(), pairs.len());
+            assert_eq!(givers.len(), pairs.len());
+            assert!(pairs.iter().all(|(giver, receiver)| giver != receiver));
+        }
+    }
+
+    #[test]
+    fn test_state_changes_require_csrf_header() {
+        let client = setup_client();
+        login_as(&client, "BOB");
+
+        let response = client.post("/secret_santa/api/draw").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.post("/secret_santa/api/draw")
+            .header(rocket::http::Header::new(crate::csrf::CSRF_HEADER, "forged"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let response = client.delete("/secret_santa/api/address").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+
+        let state = client.rocket().state::<AppState>().unwrap();
+        let draws: i64 = state.db.sqlite().unwrap().get().unwrap()
+            .query_row("SELECT COUNT(*) FROM draws", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(draws, 0);
+    }
+}
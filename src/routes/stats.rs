@@ -0,0 +1,129 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+
+use crate::auth::{scope, AdminAccess, AdminUser};
+use crate::csrf::CsrfToken;
+use crate::i18n::Locale;
+use crate::stats::{self, DrawFact, Stats};
+use crate::state::AppState;
+
+async fn load(state: &AppState) -> Result<Stats, Status> {
+    let (access_codes, draws, signups) = state.repos.run(|repos| {
+        // le destinataire est écarté dès la lecture
+        let draws: Vec<DrawFact> = repos.draws.list()?.into_iter().map(DrawFact::from).collect();
+        Ok((repos.access_codes.list(true)?, draws, repos.invitations.signups(None)?))
+    }).await?;
+    let now = state.clock.timestamp();
+    Ok(stats::compute(state.clock.year(), &now, &access_codes, &draws, &signups))
+}
+
+#[get("/admin/stats")]
+pub async fn stats_page(_admin: AdminUser, locale: Locale, csrf: CsrfToken, state: &State<AppState>) -> Result<Template, Status> {
+    let stats = load(state).await?;
+    Ok(Template::render("stats", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+        stats,
+    }))
+}
+
+/// Statistiques de l'échange : avancement du tirage de l'année, participation
+/// et fidélité par année, connexions, délai entre inscription et tirage.
+///
+/// Aucune donnée ne dépend du destinataire d'un tirage.
+#[utoipa::path(
+    tag = "stats",
+    responses(
+        (status = 200, description = "Statistiques", body = Stats),
+        (status = 401, description = "Session absente ou expirée, ou jeton d'API invalide"),
+        (status = 403, description = "Réservé à l'admin, ou droit du jeton d'API manquant"),
+    ),
+    security(("session" = []), ("api_token" = ["participants:read"])),
+)]
+#[get("/admin/api/stats")]
+pub async fn get_stats(_admin: AdminAccess<scope::ReadParticipants>, state: &State<AppState>) -> Result<Json<Stats>, Status> {
+    Ok(Json(load(state).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::Clock;
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::state::AppState;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::Value;
+
+    /// 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    fn setup_client() -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        {
+            let conn = pool.get().unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (2, 'Alice', 'ALICE', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (3, 'Bob', 'BOB', 1)", []).unwrap();
+            conn.execute("INSERT INTO access_codes (id, name, code, active) VALUES (4, 'Chloé', 'CHLOE', 1)", []).unwrap();
+            conn.execute(
+                "INSERT INTO draws (giver_id, receiver_id, year, created_at) VALUES (2, 3, 2024, '2024-12-01 10:00:00'), (3, 2, 2024, '2024-12-01 11:00:00')",
+                [],
+            ).unwrap();
+        }
+        let state = AppState::new(Database::Sqlite(pool), AppConfig::default());
+        let rocket = rocket::build()
+            .manage(AppState { clock: Clock::fixed(NOW), ..state })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn login_as(client: &Client, code: &str) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    #[test]
+    fn test_stats() {
+        let client = setup_client();
+        login_as(&client, "ALICE");
+        assert_eq!(client.get("/admin/api/stats").dispatch().status(), Status::Forbidden);
+        assert_eq!(client.get("/admin/stats").dispatch().status(), Status::Forbidden);
+
+        // Alice tire en 2025 (le tirage suit l'horloge de l'application)
+        let response = client.post("/secret_santa/api/draw").header(header(&client)).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let receiver = response.into_json::<Value>().unwrap()["assigned_name"].as_str().unwrap().to_string();
+
+        login_as(&client, "Winter2025");
+        let response = client.get("/admin/api/stats").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let body = response.into_string().unwrap();
+        // ni destinataire, ni paire donateur / destinataire
+        assert!(!body.contains("receiver") && !body.contains(&receiver) && !body.contains("Alice"));
+
+        let stats: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(stats["year"], 2025);
+        assert_eq!(stats["years"][0], serde_json::json!({
+            "year": 2024, "participants": 2, "new_participants": 2, "returning": 0, "retention_percent": null,
+        }));
+        // Alice et l'admin se sont connectés
+        assert_eq!((stats["logins"]["participants"].as_i64(), stats["logins"]["logged_in"].as_i64()), (Some(4), Some(2)));
+        assert_eq!(stats["signups"]["average_hours_to_draw"], Value::Null);
+
+        let page = client.get("/admin/stats").dispatch();
+        assert_eq!(page.status(), Status::Ok);
+        let html = page.into_string().unwrap();
+        assert!(html.contains("<td>2024</td>"));
+    }
+}
@@ -0,0 +1,416 @@
+use rocket::http::Status;
+use rocket::response::status::Created;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{Template, context};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::audit::{AuditAction, AuditEvent};
+use crate::auth::AdminUser;
+use crate::card::render_qr_svg;
+use crate::csrf::{CsrfToken, CsrfVerified};
+use crate::i18n::Locale;
+use crate::repo::RepoError;
+use crate::state::AppState;
+use crate::totp;
+
+/// Émetteur affiché par les applications d'authentification.
+const TOTP_ISSUER: &str = "Winter Card";
+
+/// État de la double authentification de l'admin connecté.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpStatus {
+    pub enabled: bool,
+    /// Enrôlement commencé mais pas encore confirmé par un premier code.
+    pub pending: bool,
+    pub recovery_codes_left: i64,
+}
+
+/// Secret à enregistrer dans l'application d'authentification.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpEnrollment {
+    /// Secret en base32, pour une saisie manuelle.
+    pub secret: String,
+    /// URI `otpauth://totp/…` (RFC 6238).
+    pub uri: String,
+    pub qr_svg: String,
+}
+
+/// Premier code de l'application, qui confirme l'enrôlement.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TotpConfirmation {
+    pub code: String,
+}
+
+/// Codes de secours, affichés une seule fois.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[get("/admin/totp")]
+pub fn totp_page(admin: AdminUser, locale: Locale, csrf: CsrfToken) -> Template {
+    Template::render("totp", context! {
+        lang: locale.0,
+        csrf_token: csrf.0,
+        is_authenticated: true,
+        admin_id: admin.access_code.id,
+    })
+}
+
+/// État de la double authentification de l'admin connecté.
+#[utoipa::path(
+    tag = "totp",
+    responses(
+        (status = 200, description = "État de la double authentification", body = TotpStatus),
+        (status = 403, description = "Réservé à l'admin"),
+    ),
+    security(("session" = [])),
+)]
+#[get("/admin/api/totp")]
+pub async fn totp_status(admin: AdminUser, state: &State<AppState>) -> Result<Json<TotpStatus>, Status> {
+    let id = admin.access_code.id;
+    let status = state.repos.run(move |repos| {
+        let credential = repos.totp.find(id)?;
+        Ok(TotpStatus {
+            enabled: credential.as_ref().is_some_and(|credential| credential.is_enabled()),
+            pending: credential.as_ref().is_some_and(|credential| !credential.is_enabled()),
+            recovery_codes_left: repos.totp.remaining_recovery_codes(id)?,
+        })
+    }).await?;
+    Ok(Json(status))
+}
+
+/// Commence l'enrôlement : génère un nouveau secret, à confirmer par un
+/// premier code. Un enrôlement non confirmé est remplacé.
+#[utoipa::path(
+    tag = "totp",
+    responses(
+        (status = 201, description = "Secret à enregistrer dans l'application", body = TotpEnrollment),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 409, description = "Double authentification déjà active"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/totp")]
+pub async fn begin_enrollment(admin: AdminUser, _csrf: CsrfVerified, state: &State<AppState>) -> Result<Created<Json<TotpEnrollment>>, Status> {
+    let id = admin.access_code.id;
+    let secret = totp::generate_secret();
+    let stored = secret.clone();
+    state.repos.run(move |repos| repos.totp.begin_enrollment(id, &stored)).await?;
+
+    let uri = totp::provisioning_uri(&secret, TOTP_ISSUER, &admin.access_code.name);
+    let qr_svg = render_qr_svg(&uri).map_err(|_| Status::InternalServerError)?;
+    let enrollment = TotpEnrollment { secret: totp::base32(&secret), uri, qr_svg };
+    Ok(Created::new("/admin/api/totp").body(Json(enrollment)))
+}
+
+/// Active la double authentification avec un premier code de l'application
+/// et renvoie les codes de secours.
+#[utoipa::path(
+    tag = "totp",
+    request_body = TotpConfirmation,
+    responses(
+        (status = 200, description = "Double authentification active", body = RecoveryCodes),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 409, description = "Aucun enrôlement en cours"),
+        (status = 422, description = "Code incorrect"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[post("/admin/api/totp/confirm", data = "<confirmation>")]
+pub async fn confirm_enrollment(
+    admin: AdminUser,
+    _csrf: CsrfVerified,
+    confirmation: Json<TotpConfirmation>,
+    state: &State<AppState>,
+) -> Result<Json<RecoveryCodes>, Status> {
+    let id = admin.access_code.id;
+    let now = state.clock.now();
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|code| totp::hash_recovery_code(code)).collect();
+    let input = confirmation.into_inner().code;
+
+    let confirmed = state.repos.run(move |repos| {
+        let credential = repos.totp.find(id)?.filter(|credential| !credential.is_enabled()).ok_or(RepoError::Conflict)?;
+        match totp::verify(&credential.secret, &input, now, None) {
+            Some(step) => repos.totp.confirm(id, step, &hashes).map(|()| true),
+            None => Ok(false),
+        }
+    }).await?;
+    if !confirmed {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    AuditEvent::new(AuditAction::TotpEnabled)
+        .actor(&admin.access_code)
+        .target(id)
+        .record(&state.repos).await;
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+/// Désactive la double authentification d'un code (secret et codes de
+/// secours), par exemple après la perte du téléphone.
+#[utoipa::path(
+    tag = "totp",
+    responses(
+        (status = 204, description = "Double authentification désactivée"),
+        (status = 403, description = "Réservé à l'admin, ou jeton CSRF invalide"),
+        (status = 404, description = "Double authentification non configurée pour ce code"),
+    ),
+    security(("session" = [], "csrf" = [])),
+)]
+#[delete("/admin/api/codes/<id>/totp")]
+pub async fn reset_totp(admin: AdminUser, _csrf: CsrfVerified, id: i64, state: &State<AppState>) -> Result<Status, Status> {
+    state.repos.run(move |repos| repos.totp.reset(id)).await?;
+    AuditEvent::new(AuditAction::TotpReset)
+        .actor(&admin.access_code)
+        .target(id)
+        .record(&state.repos).await;
+    Ok(Status::NoContent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{PENDING_LOGIN_COOKIE, SESSION_COOKIE};
+    use crate::clock::Clock;
+    use crate::config::AppConfig;
+    use crate::csrf::testing::{header, token};
+    use crate::db::{init_db, init_pool, Database};
+    use crate::repo::Repos;
+    use crate::state::AppState;
+    use crate::totp::{code_at, step_at, LOCKOUT_SECS, MAX_FAILURES, STEP_SECS};
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use serde_json::{json, Value};
+
+    // 2025-12-24 00:00:00 UTC
+    const NOW: i64 = 1_766_534_400;
+
+    fn setup_client(repos: Option<Repos>) -> Client {
+        let pool = init_pool(":memory:");
+        init_db(&pool);
+        let db = Database::Sqlite(pool);
+        let state = match repos {
+            Some(repos) => AppState::with_repos(db, repos, AppConfig::default()),
+            None => AppState::new(db, AppConfig::default()),
+        };
+        let rocket = rocket::build()
+            .manage(AppState { clock: Clock::fixed(NOW), ..state })
+            .mount("/", crate::routes::routes())
+            .attach(crate::i18n::template_fairing())
+            .attach(crate::csrf::CsrfFairing);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn state(client: &Client) -> &AppState {
+        client.rocket().state::<AppState>().unwrap()
+    }
+
+    fn post_login(client: &Client, code: &str) -> (Status, Option<String>) {
+        let token = token(client);
+        let response = client.post("/login")
+            .header(ContentType::Form)
+            .body(format!("password={code}&csrf_token={token}"))
+            .dispatch();
+        (response.status(), response.headers().get_one("Location").map(str::to_string))
+    }
+
+    fn post_totp(client: &Client, code: &str) -> (Status, String) {
+        let token = token(client);
+        let response = client.post("/login/totp")
+            .header(ContentType::Form)
+            .body(format!("code={code}&csrf_token={token}"))
+            .dispatch();
+        let status = response.status();
+        (status, response.into_string().unwrap_or_default())
+    }
+
+    fn logged_in(client: &Client) -> bool {
+        client.cookies().get_private(SESSION_COOKIE).is_some()
+    }
+
+    fn logout(client: &Client) {
+        let token = token(client);
+        client.post("/logout")
+            .header(ContentType::Form)
+            .body(format!("csrf_token={token}"))
+            .dispatch();
+    }
+
+    // enrôle l'admin connecté et renvoie le secret et les codes de secours
+    fn enroll(client: &Client) -> (Vec<u8>, Vec<String>) {
+        let response = client.post("/admin/api/totp").header(header(client)).dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let enrollment: Value = response.into_json().unwrap();
+        assert!(enrollment["uri"].as_str().unwrap().starts_with("otpauth://totp/Winter%20Card:Admin?secret="));
+        assert!(enrollment["qr_svg"].as_str().unwrap().starts_with("<svg"));
+        let secret = state(client).repos.totp.find(1).unwrap().unwrap().secret;
+        assert_eq!(enrollment["secret"], crate::totp::base32(&secret));
+
+        let confirm = |code: &str| client.post("/admin/api/totp/confirm")
+            .header(header(client))
+            .header(ContentType::JSON)
+            .body(json!({ "code": code }).to_string())
+            .dispatch();
+        assert_eq!(confirm("000000").status(), Status::UnprocessableEntity);
+        let response = confirm(&code_at(&secret, step_at(NOW)));
+        assert_eq!(response.status(), Status::Ok);
+        let codes: Value = response.into_json().unwrap();
+        let recovery_codes = codes["recovery_codes"].as_array().unwrap().iter()
+            .map(|code| code.as_str().unwrap().to_string())
+            .collect();
+        (secret, recovery_codes)
+    }
+
+    #[test]
+    fn test_login_requires_totp_once_enrolled() {
+        let client = setup_client(None);
+        assert_eq!(post_login(&client, "Winter2025").1.as_deref(), Some("/"));
+        let (secret, recovery_codes) = enroll(&client);
+        assert_eq!(recovery_codes.len(), 10);
+        let status: Value = client.get("/admin/api/totp").dispatch().into_json().unwrap();
+        assert_eq!(status, json!({ "enabled": true, "pending": false, "recovery_codes_left": 10 }));
+        // un second enrôlement est refusé tant que la double authentification est active
+        assert_eq!(client.post("/admin/api/totp").header(header(&client)).dispatch().status(), Status::Conflict);
+        logout(&client);
+
+        // le code d'accès seul n'ouvre pas de session et ne compte pas de connexion
+        let login_count = || state(&client).repos.access_codes.find_active(1).unwrap().login_count;
+        let before = login_count();
+        assert_eq!(post_login(&client, "Winter2025").1.as_deref(), Some("/login/totp"));
+        assert!(!logged_in(&client));
+        assert_eq!(login_count(), before);
+        assert_eq!(client.get("/admin/audit").dispatch().status(), Status::Unauthorized);
+        assert_eq!(client.get("/login/totp").dispatch().status(), Status::Ok);
+
+        // le code de l'enrôlement (même pas) ne peut pas resservir
+        let (status, page) = post_totp(&client, &code_at(&secret, step_at(NOW)));
+        assert_eq!(status, Status::Ok);
+        assert!(page.contains("Code incorrect"));
+        assert!(!logged_in(&client));
+
+        state(&client).clock.advance(STEP_SECS);
+        let (status, _) = post_totp(&client, &code_at(&secret, step_at(NOW + STEP_SECS)));
+        assert_eq!(status, Status::SeeOther);
+        assert!(logged_in(&client));
+        assert_eq!(login_count(), before + 1);
+        assert_eq!(client.get("/admin/audit").dispatch().status(), Status::Ok);
+
+        let (entries, _) = state(&client).repos.audit.list(1, 1).unwrap();
+        assert_eq!((entries[0].action.as_str(), entries[0].detail.as_deref()), ("login", Some("second_factor=totp")));
+
+        // une connexion en deux étapes n'est comptée qu'une fois, sous son mode d'entrée
+        let metrics = client.get("/metrics").dispatch().into_string().unwrap();
+        assert!(metrics.contains("winter_card_logins_total{method=\"code\",result=\"success\"} 2"));
+        assert!(metrics.contains("winter_card_logins_total{method=\"totp\",result=\"success\"} 0"));
+        assert!(metrics.contains("winter_card_logins_total{method=\"totp\",result=\"failure\"} 1"));
+    }
+
+    #[test]
+    fn test_recovery_codes_are_single_use() {
+        let client = setup_client(None);
+        post_login(&client, "Winter2025");
+        let (_, recovery_codes) = enroll(&client);
+        logout(&client);
+
+        post_login(&client, "Winter2025");
+        let (status, _) = post_totp(&client, &recovery_codes[0].to_lowercase());
+        assert_eq!(status, Status::SeeOther);
+        assert!(logged_in(&client));
+        let status: Value = client.get("/admin/api/totp").dispatch().into_json().unwrap();
+        assert_eq!(status["recovery_codes_left"], 9);
+        logout(&client);
+
+        post_login(&client, "Winter2025");
+        assert_eq!(post_totp(&client, &recovery_codes[0]).0, Status::Ok);
+        assert!(!logged_in(&client));
+    }
+
+    #[test]
+    fn test_pending_login_expires_and_locks() {
+        let client = setup_client(None);
+        post_login(&client, "Winter2025");
+        let (secret, _) = enroll(&client);
+        logout(&client);
+
+        // délai dépassé : il faut ressaisir le code d'accès
+        post_login(&client, "Winter2025");
+        state(&client).clock.advance(crate::auth::PENDING_LOGIN_TTL_SECS + STEP_SECS);
+        let now = state(&client).clock.now();
+        let (status, page) = post_totp(&client, &code_at(&secret, step_at(now)));
+        assert_eq!(status, Status::Ok);
+        assert!(page.contains("name=\"password\""));
+        assert!(!logged_in(&client));
+        assert_eq!(client.get("/login/totp").dispatch().status(), Status::SeeOther);
+
+        // après 5 codes erronés, même un code valable est refusé
+        post_login(&client, "Winter2025");
+        for _ in 0..MAX_FAILURES {
+            assert_eq!(post_totp(&client, "999999").0, Status::Ok);
+        }
+        post_totp(&client, &code_at(&secret, step_at(now)));
+        assert!(!logged_in(&client));
+    }
+
+    #[test]
+    fn test_lock_survives_cookie_replay() {
+        let client = setup_client(None);
+        post_login(&client, "Winter2025");
+        let (secret, _) = enroll(&client);
+        logout(&client);
+
+        post_login(&client, "Winter2025");
+        let pending = client.cookies().get_private(PENDING_LOGIN_COOKIE).unwrap();
+        for _ in 1..MAX_FAILURES {
+            assert!(post_totp(&client, "999999").1.contains("Code incorrect"));
+        }
+        assert!(post_totp(&client, "999999").1.contains("Trop de codes incorrects"));
+
+        // rejouer le cookie de la connexion en attente ne remet pas le compte à zéro
+        let code = code_at(&secret, step_at(NOW));
+        let token = token(&client);
+        let page = client.post("/login/totp")
+            .private_cookie(pending)
+            .header(ContentType::Form)
+            .body(format!("code={code}&csrf_token={token}"))
+            .dispatch()
+            .into_string()
+            .unwrap();
+        assert!(page.contains("Trop de codes incorrects"));
+        assert!(!logged_in(&client));
+
+        // ressaisir le code d'accès non plus
+        post_login(&client, "Winter2025");
+        assert!(post_totp(&client, &code).1.contains("Trop de codes incorrects"));
+        assert!(!logged_in(&client));
+
+        // le blocage prend fin après LOCKOUT_SECS
+        state(&client).clock.advance(LOCKOUT_SECS);
+        post_login(&client, "Winter2025");
+        let now = state(&client).clock.now();
+        assert_eq!(post_totp(&client, &code_at(&secret, step_at(now))).0, Status::SeeOther);
+        assert!(logged_in(&client));
+        assert_eq!(state(&client).repos.totp.find(1).unwrap().unwrap().failed_attempts, 0);
+    }
+
+    #[test]
+    fn test_admin_reset_and_access() {
+        let client = setup_client(Some(Repos::memory()));
+        post_login(&client, "Winter2025");
+        enroll(&client);
+
+        let reset = |client: &Client| client.delete("/admin/api/codes/1/totp").header(header(client)).dispatch().status();
+        assert_eq!(reset(&client), Status::NoContent);
+        assert_eq!(reset(&client), Status::NotFound);
+        logout(&client);
+        assert_eq!(post_login(&client, "Winter2025").1.as_deref(), Some("/"));
+
+        // l'enrôlement est réservé à l'admin
+        logout(&client);
+        assert_eq!(client.post("/admin/api/totp").header(header(&client)).dispatch().status(), Status::Unauthorized);
+        let (entries, _) = state(&client).repos.audit.list(1, 10).unwrap();
+        assert!(entries.iter().any(|entry| entry.action == "totp.reset" && entry.target_id == Some(1)));
+    }
+}
@@ -1,22 +1,48 @@
-use std::sync::Mutex;
-use r2d2::Pool;
-use r2d2_sqlite::SqliteConnectionManager;
-use crate::models::access_code::AccessCode;
+use crate::clock::Clock;
+use crate::config::AppConfig;
+use crate::db::Database;
+use crate::events::EventBus;
+use crate::crypto::AddressCipher;
+use crate::login_link::LinkSigner;
+use crate::metrics::Metrics;
+use crate::repo::Repos;
 
 pub struct AppState {
-    pub db_pool: Pool<SqliteConnectionManager>,
-    pub is_authenticated: Mutex<bool>,
-    pub current_user: Mutex<Option<i64>>,
-    pub current_access_code: Mutex<Option<AccessCode>>, // Nouveau champ
+    pub db: Database,
+    pub repos: Repos,
+    pub address_cipher: Option<AddressCipher>,
+    pub link_signer: LinkSigner,
+    pub metrics: Metrics,
+    pub clock: Clock,
+    /// Évènements diffusés aux flux SSE ouverts.
+    pub events: EventBus,
+    pub config: AppConfig,
 }
 
 impl AppState {
-    pub fn new(db_pool: Pool<SqliteConnectionManager>) -> Self {
+    pub fn new(db: Database, config: AppConfig) -> Self {
+        let repos = db.repos();
+        Self::with_repos(db, repos, config)
+    }
+
+    /// Comme `new`, avec des repositories fournis (ex. `Repos::memory()` en test).
+    pub fn with_repos(db: Database, repos: Repos, config: AppConfig) -> Self {
+        let address_cipher = config.address_key.as_deref().and_then(|key| {
+            AddressCipher::from_base64(key)
+                .map_err(|_| log::warn!("address_key invalide : saisie des adresses désactivée"))
+                .ok()
+        });
+        let link_signer = LinkSigner::from_config(config.login_link_key.as_deref());
+
         Self {
-            db_pool,
-            is_authenticated: Mutex::new(false),
-            current_user: Mutex::new(None),
-            current_access_code: Mutex::new(None),
+            db,
+            repos,
+            address_cipher,
+            link_signer,
+            metrics: Metrics::default(),
+            clock: Clock::default(),
+            events: EventBus::default(),
+            config,
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,272 @@
+//! Statistiques de l'échange pour l'organisateur : participation par année,
+//! avancement du tirage, connexions, délai entre l'inscription et le tirage,
+//! fidélité d'une année sur l'autre.
+//!
+//! Seuls le donateur, l'année et la date de chaque tirage sont lus : le
+//! destinataire n'entre dans aucun calcul, les statistiques ne peuvent donc
+//! pas révéler qui offre à qui.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::access_code::{AccessCode, TIMESTAMP_FORMAT};
+use crate::models::draw::Draw;
+use crate::models::invitation::{Signup, SignupStatus};
+
+/// Tirage réduit à ce que les statistiques ont le droit de voir.
+#[derive(Debug, Clone)]
+pub struct DrawFact {
+    pub giver_id: i64,
+    pub year: i32,
+    pub created_at: String,
+}
+
+impl From<Draw> for DrawFact {
+    fn from(draw: Draw) -> Self {
+        Self { giver_id: draw.giver_id, year: draw.year, created_at: draw.created_at }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Stats {
+    /// Année courante, celle de `progress`.
+    pub year: i32,
+    pub progress: DrawProgressStats,
+    /// Participation par année, par année croissante.
+    pub years: Vec<YearStats>,
+    pub logins: LoginStats,
+    pub signups: SignupStats,
+}
+
+/// Avancement du tirage de l'année courante.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DrawProgressStats {
+    /// Participants actifs, non archivés.
+    pub participants: i64,
+    pub drawn: i64,
+    /// Part des participants qui ont tiré, en pourcentage.
+    pub percent_drawn: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct YearStats {
+    pub year: i32,
+    /// Participants qui ont tiré cette année-là.
+    pub participants: i64,
+    /// Participants dont c'est le premier tirage.
+    pub new_participants: i64,
+    /// Participants qui avaient déjà tiré l'année précédente.
+    pub returning: i64,
+    /// Part des participants de l'année précédente revenus cette année, en
+    /// pourcentage ; absente sans tirage l'année précédente.
+    pub retention_percent: Option<f64>,
+}
+
+/// Connexions des participants non archivés.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct LoginStats {
+    pub participants: i64,
+    /// Participants connectés au moins une fois.
+    pub logged_in: i64,
+    pub never_logged_in: i64,
+    pub total_logins: i64,
+    pub last_7_days: i64,
+    pub last_30_days: i64,
+}
+
+/// Inscriptions par lien d'invitation.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SignupStats {
+    pub pending: i64,
+    pub approved: i64,
+    pub rejected: i64,
+    /// Inscrits validés qui ont tiré depuis leur inscription.
+    pub drawn: i64,
+    /// Délai moyen entre l'inscription et le premier tirage qui la suit, en
+    /// heures ; absent tant qu'aucun inscrit n'a tiré.
+    pub average_hours_to_draw: Option<f64>,
+}
+
+/// Calcule les statistiques à la date `now` (`TIMESTAMP_FORMAT`, UTC).
+pub fn compute(year: i32, now: &str, access_codes: &[AccessCode], draws: &[DrawFact], signups: &[Signup]) -> Stats {
+    let current: Vec<&AccessCode> = access_codes.iter().filter(|code| code.archived_at.is_none()).collect();
+
+    let participants = current.iter().filter(|code| code.active).count() as i64;
+    let drawn = draws.iter().filter(|draw| draw.year == year).count() as i64;
+    let progress = DrawProgressStats { participants, drawn, percent_drawn: percent(drawn, participants).unwrap_or(0.0) };
+
+    Stats {
+        year,
+        progress,
+        years: year_stats(draws),
+        logins: login_stats(now, &current),
+        signups: signup_stats(draws, signups),
+    }
+}
+
+fn year_stats(draws: &[DrawFact]) -> Vec<YearStats> {
+    let mut givers: BTreeMap<i32, BTreeSet<i64>> = BTreeMap::new();
+    for draw in draws {
+        givers.entry(draw.year).or_default().insert(draw.giver_id);
+    }
+
+    let mut seen = BTreeSet::new();
+    givers.iter().map(|(&year, ids)| {
+        let previous = givers.get(&(year - 1));
+        let returning = previous.map_or(0, |previous| ids.intersection(previous).count()) as i64;
+        let new_participants = ids.iter().filter(|id| !seen.contains(*id)).count() as i64;
+        seen.extend(ids.iter().copied());
+        YearStats {
+            year,
+            participants: ids.len() as i64,
+            new_participants,
+            returning,
+            retention_percent: previous.and_then(|previous| percent(returning, previous.len() as i64)),
+        }
+    }).collect()
+}
+
+fn login_stats(now: &str, access_codes: &[&AccessCode]) -> LoginStats {
+    let since = |days: i64| {
+        parse(now).map(|now| (now - chrono::Duration::days(days)).format(TIMESTAMP_FORMAT).to_string())
+    };
+    // les dates au format `TIMESTAMP_FORMAT` se comparent comme des chaînes
+    let logged_in_since = |since: Option<String>| since.map_or(0, |since| {
+        access_codes.iter().filter(|code| code.last_login_at.as_ref().is_some_and(|at| *at >= since)).count() as i64
+    });
+    let logged_in = access_codes.iter().filter(|code| code.login_count > 0 || code.last_login_at.is_some()).count() as i64;
+    LoginStats {
+        participants: access_codes.len() as i64,
+        logged_in,
+        never_logged_in: access_codes.len() as i64 - logged_in,
+        total_logins: access_codes.iter().map(|code| code.login_count).sum(),
+        last_7_days: logged_in_since(since(7)),
+        last_30_days: logged_in_since(since(30)),
+    }
+}
+
+fn signup_stats(draws: &[DrawFact], signups: &[Signup]) -> SignupStats {
+    let count = |status: SignupStatus| signups.iter().filter(|signup| signup.status == status).count() as i64;
+
+    let delays: Vec<f64> = signups.iter()
+        .filter(|signup| signup.status == SignupStatus::Approved)
+        .filter_map(|signup| {
+            let access_code_id = signup.access_code_id?;
+            let signed_up = parse(&signup.created_at)?;
+            let first_draw = draws.iter()
+                .filter(|draw| draw.giver_id == access_code_id)
+                .filter_map(|draw| parse(&draw.created_at))
+                .filter(|drawn_at| *drawn_at >= signed_up)
+                .min()?;
+            Some((first_draw - signed_up).num_seconds() as f64 / 3600.0)
+        })
+        .collect();
+
+    SignupStats {
+        pending: count(SignupStatus::Pending),
+        approved: count(SignupStatus::Approved),
+        rejected: count(SignupStatus::Rejected),
+        drawn: delays.len() as i64,
+        average_hours_to_draw: (!delays.is_empty()).then(|| round(delays.iter().sum::<f64>() / delays.len() as f64)),
+    }
+}
+
+fn parse(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// Pourcentage arrondi au dixième, absent si `total` est nul.
+fn percent(part: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| round(100.0 * part as f64 / total as f64))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(id: i64, active: bool, last_login_at: Option<&str>, login_count: i64) -> AccessCode {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("P{id}"),
+            "code": format!("CODE{id}"),
+            "active": active,
+            "last_login_at": last_login_at,
+            "login_count": login_count,
+        })).unwrap()
+    }
+
+    fn draw(giver_id: i64, year: i32, created_at: &str) -> DrawFact {
+        DrawFact { giver_id, year, created_at: created_at.to_string() }
+    }
+
+    fn signup(id: i64, status: SignupStatus, created_at: &str, access_code_id: Option<i64>) -> Signup {
+        Signup {
+            id,
+            invitation_id: 1,
+            invitation_label: "Famille".to_string(),
+            name: format!("S{id}"),
+            code: format!("SIGNUP{id}"),
+            status,
+            created_at: created_at.to_string(),
+            decided_at: None,
+            access_code_id,
+        }
+    }
+
+    #[test]
+    fn test_compute() {
+        let now = "2025-12-20 12:00:00";
+        let mut archived = code(5, true, Some("2025-12-19 10:00:00"), 4);
+        archived.archived_at = Some("2025-12-01 00:00:00".to_string());
+        let access_codes = [
+            code(1, true, Some("2025-12-19 08:00:00"), 3),
+            code(2, true, Some("2025-11-30 08:00:00"), 1),
+            code(3, true, None, 0),
+            code(4, false, Some("2024-12-01 08:00:00"), 2),
+            archived,
+        ];
+        let draws = [
+            draw(1, 2024, "2024-12-01 10:00:00"),
+            draw(2, 2024, "2024-12-01 11:00:00"),
+            draw(4, 2024, "2024-12-02 11:00:00"),
+            draw(1, 2025, "2025-12-10 10:00:00"),
+            draw(3, 2025, "2025-12-12 10:00:00"),
+        ];
+        let signups = [
+            signup(1, SignupStatus::Approved, "2025-12-10 22:00:00", Some(3)),
+            signup(2, SignupStatus::Approved, "2025-12-11 08:00:00", Some(2)),
+            signup(3, SignupStatus::Approved, "2025-12-11 08:00:00", None),
+            signup(4, SignupStatus::Pending, "2025-12-15 08:00:00", None),
+            signup(5, SignupStatus::Rejected, "2025-12-15 09:00:00", None),
+        ];
+
+        let stats = compute(2025, now, &access_codes, &draws, &signups);
+        assert_eq!(stats.progress, DrawProgressStats { participants: 3, drawn: 2, percent_drawn: 66.7 });
+        assert_eq!(stats.years, vec![
+            YearStats { year: 2024, participants: 3, new_participants: 3, returning: 0, retention_percent: None },
+            YearStats { year: 2025, participants: 2, new_participants: 1, returning: 1, retention_percent: Some(33.3) },
+        ]);
+        // le participant archivé n'est pas compté
+        assert_eq!(stats.logins, LoginStats {
+            participants: 4,
+            logged_in: 3,
+            never_logged_in: 1,
+            total_logins: 6,
+            last_7_days: 1,
+            last_30_days: 2,
+        });
+        // seule l'inscription 1 a été suivie d'un tirage, 36 h plus tard
+        assert_eq!(stats.signups, SignupStats { pending: 1, approved: 3, rejected: 1, drawn: 1, average_hours_to_draw: Some(36.0) });
+
+        let empty = compute(2025, now, &[], &[], &[]);
+        assert_eq!(empty.progress.percent_drawn, 0.0);
+        assert_eq!((empty.years.len(), empty.signups.average_hours_to_draw), (0, None));
+    }
+}
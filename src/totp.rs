@@ -0,0 +1,191 @@
+//! Double authentification TOTP (RFC 6238) : codes à 6 chiffres calculés par
+//! HMAC-SHA1 sur des pas de 30 s, URI de provisionnement pour les applications
+//! d'authentification et codes de secours à usage unique.
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use rocket::http::RawStr;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::csrf::same_token;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Durée d'un pas, en secondes.
+pub const STEP_SECS: i64 = 30;
+/// Nombre de chiffres d'un code.
+pub const DIGITS: usize = 6;
+/// Nombre de codes de secours remis à l'activation.
+pub const RECOVERY_CODES: usize = 10;
+/// Codes erronés tolérés avant de bloquer le second facteur.
+pub const MAX_FAILURES: i64 = 5;
+/// Durée du blocage, et fenêtre dans laquelle les échecs se cumulent, en secondes.
+pub const LOCKOUT_SECS: i64 = 300;
+/// Décalage d'horloge toléré avec le téléphone, en pas de part et d'autre.
+const SKEW_STEPS: i64 = 1;
+/// Taille du secret (160 bits, recommandée par la RFC 4226).
+const SECRET_LEN: usize = 20;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// sans 0/O ni 1/I/L, faciles à confondre à la saisie
+const RECOVERY_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_LEN];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Encodage base32 (RFC 4648) sans remplissage, attendu par les applications.
+pub fn base32(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+/// Pas TOTP contenant l'instant `unix` (secondes).
+pub fn step_at(unix: i64) -> i64 {
+    unix.div_euclid(STEP_SECS)
+}
+
+/// Code HOTP (RFC 4226) du compteur `counter`, sur `DIGITS` chiffres.
+pub fn code_at(secret: &[u8], counter: i64) -> String {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    // troncature dynamique
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([hash[offset] & 0x7f, hash[offset + 1], hash[offset + 2], hash[offset + 3]]);
+    format!("{:0width$}", value % 10u32.pow(DIGITS as u32), width = DIGITS)
+}
+
+/// La saisie ressemble-t-elle à un code TOTP (plutôt qu'à un code de secours) ?
+pub fn is_totp_code(input: &str) -> bool {
+    let digits: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    digits.len() == DIGITS && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Pas du code `input` s'il est valable à `now`, à un pas près.
+///
+/// Les pas antérieurs ou égaux à `last_step` (dernier code accepté) sont
+/// refusés : un code intercepté ne peut pas être rejoué.
+pub fn verify(secret: &[u8], input: &str, now: i64, last_step: Option<i64>) -> Option<i64> {
+    if !is_totp_code(input) {
+        return None;
+    }
+    let input: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let current = step_at(now);
+    (current - SKEW_STEPS..=current + SKEW_STEPS)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| same_token(&code_at(secret, *step), &input))
+}
+
+/// URI `otpauth://` à afficher en QR code lors de l'enrôlement.
+pub fn provisioning_uri(secret: &[u8], issuer: &str, account: &str) -> String {
+    let label = format!("{issuer}:{account}");
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        RawStr::new(&label).percent_encode(),
+        base32(secret),
+        RawStr::new(issuer).percent_encode(),
+    )
+}
+
+/// Codes de secours au format `XXXXX-XXXXX`, affichés une seule fois.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| RECOVERY_ALPHABET[(OsRng.next_u32() as usize) % RECOVERY_ALPHABET.len()] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Empreinte stockée d'un code de secours (SHA-256, en hexadécimal). La
+/// saisie est normalisée : casse, tirets et espaces sont ignorés.
+pub fn hash_recovery_code(input: &str) -> String {
+    let normalized: String = input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    Sha256::digest(normalized.as_bytes()).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // secret des vecteurs de test de la RFC 6238 (annexe B, SHA-1)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        for (time, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ] {
+            assert_eq!(code_at(RFC_SECRET, step_at(time)), code, "t={time}");
+        }
+    }
+
+    #[test]
+    fn test_base32_and_provisioning_uri() {
+        assert_eq!(base32(RFC_SECRET), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base32(b"fooba"), "MZXW6YTB");
+        assert_eq!(
+            provisioning_uri(RFC_SECRET, "Winter Card", "Admin"),
+            "otpauth://totp/Winter%20Card:Admin?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=Winter%20Card&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_verify_window_and_replay() {
+        let now = 1_111_111_111;
+        let step = step_at(now);
+        assert_eq!(verify(RFC_SECRET, "050471", now, None), Some(step));
+        assert_eq!(verify(RFC_SECRET, " 050 471 ", now, None), Some(step));
+        // un pas de décalage est toléré, pas deux
+        assert_eq!(verify(RFC_SECRET, &code_at(RFC_SECRET, step - 1), now, None), Some(step - 1));
+        assert_eq!(verify(RFC_SECRET, &code_at(RFC_SECRET, step + 1), now, None), Some(step + 1));
+        assert_eq!(verify(RFC_SECRET, &code_at(RFC_SECRET, step - 2), now, None), None);
+        // rejeu du même code, ou d'un code plus ancien que le dernier accepté
+        assert_eq!(verify(RFC_SECRET, "050471", now, Some(step)), None);
+        assert_eq!(verify(RFC_SECRET, &code_at(RFC_SECRET, step - 1), now, Some(step)), None);
+        assert_eq!(verify(RFC_SECRET, "abcdef", now, None), None);
+        assert_eq!(verify(RFC_SECRET, "0504710", now, None), None);
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODES);
+        assert!(codes.iter().all(|code| code.len() == 11 && code.as_bytes()[5] == b'-'));
+        assert!(codes.iter().all(|code| !is_totp_code(code)));
+        let code = &codes[0];
+        assert_eq!(hash_recovery_code(code), hash_recovery_code(&code.to_lowercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(code), hash_recovery_code(&codes[1]));
+        assert_eq!(generate_secret().len(), 20);
+    }
+}